@@ -1,12 +1,12 @@
 use codes::crypt::aes::{AESKey, AES};
-use codes::crypt::chacha20::{XChaCha20};
+use codes::crypt::chacha20::XChaCha20;
 use codes::crypt::mac::HMAC;
 use codes::crypt::pbkdf2;
+use codes::crypt::stream::{DecryptReader, EncryptWriter};
 use codes::crypt::twofish::{Twofish, TwofishKey};
-use codes::crypt::Cipher;
-use codes::error::hamming::ErrorDetection;
+use codes::error::hamming;
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 use structopt::StructOpt;
 
 #[derive(StructOpt)]
@@ -61,9 +61,111 @@ struct Cli {
     mode: Mode,
 }
 
-fn run(args: Cli) -> Result<(), std::io::Error> {
-    let mut bytes = Vec::new();
+/// Plaintext bytes that encode to a whole number of hamming blocks.
+const PLAIN_CHUNK: usize = 88;
+/// The encoded size of `PLAIN_CHUNK` bytes.
+const CODED_CHUNK: usize = 128;
 
+/// Hamming encodes everything written to it, a chunk at a time.
+struct HammingWriter<W: Write> {
+    inner: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> HammingWriter<W> {
+    fn new(inner: W) -> Self {
+        HammingWriter {
+            inner,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Encode what is left, padding the last block with zeros.
+    fn finish(mut self) -> io::Result<W> {
+        if !self.buffer.is_empty() {
+            self.inner.write_all(&hamming::encode(self.buffer))?;
+        }
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for HammingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        let whole = self.buffer.len() - self.buffer.len() % PLAIN_CHUNK;
+        let rest = self.buffer.split_off(whole);
+        let chunks = std::mem::replace(&mut self.buffer, rest);
+        for chunk in chunks.chunks(PLAIN_CHUNK) {
+            self.inner.write_all(&hamming::encode(chunk.to_vec()))?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Hamming decodes everything read from it, a chunk at a time.
+/// The output is truncated to a multiple of `block`, since the padding
+/// of the last hamming block might decode to an extra byte.
+struct HammingReader<R: Read> {
+    inner: R,
+    block: usize,
+    total: usize,
+    decoded: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> HammingReader<R> {
+    fn new(inner: R, block: usize) -> Self {
+        HammingReader {
+            inner,
+            block,
+            total: 0,
+            decoded: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for HammingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.decoded.len() {
+            let mut chunk = Vec::with_capacity(CODED_CHUNK);
+            (&mut self.inner)
+                .take(CODED_CHUNK as u64)
+                .read_to_end(&mut chunk)?;
+            // an odd byte can not be decoded
+            chunk.truncate(chunk.len() & !1);
+            self.decoded = hamming::decode(chunk);
+            self.position = 0;
+            if self.decoded.len() < PLAIN_CHUNK {
+                // the last chunk
+                // a truncated or corrupt input may end before the last whole block
+                let end = self.total + self.decoded.len();
+                let len = (end - end % self.block)
+                    .checked_sub(self.total)
+                    .ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "the coded input does not end with a whole block",
+                        )
+                    })?;
+                self.decoded.truncate(len);
+            }
+            self.total += self.decoded.len();
+        }
+
+        let n = buf.len().min(self.decoded.len() - self.position);
+        buf[..n].clone_from_slice(&self.decoded[self.position..(self.position + n)]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+fn run(args: Cli) -> Result<(), Box<dyn std::error::Error>> {
     // get password up/down to 256 bit length
     // TODO: better IV creation
     let key_vec = pbkdf2(
@@ -72,19 +174,27 @@ fn run(args: Cli) -> Result<(), std::io::Error> {
         10000,
         256,
         &HMAC::default(),
-    );
+    )?;
     assert_eq!(key_vec.len(), 32);
     let mut key = [0u8; 32];
     key[..32].clone_from_slice(&key_vec[..]);
 
     // read input, either from file or from stdin
-    if let Some(filename) = args.input {
-        File::open(filename)?.read_to_end(&mut bytes)?;
+    let mut input: Box<dyn Read> = if let Some(filename) = args.input {
+        Box::new(io::BufReader::new(File::open(filename)?))
     } else {
-        std::io::stdin().read_to_end(&mut bytes)?;
-    }
+        Box::new(io::stdin())
+    };
+
+    // write the processed data to either a file or to stdout
+    let output: Box<dyn Write> = if let Some(filename) = args.output {
+        Box::new(io::BufWriter::new(File::create(filename)?))
+    } else {
+        Box::new(io::stdout())
+    };
 
     let iv: Vec<u8> = (0..16).collect();
+    let nonce: Vec<u8> = (0..24).collect();
 
     let cipher = match args.cipher {
         CipherType::AES => Some(AES::new(AESKey::AES256(key))),
@@ -92,45 +202,36 @@ fn run(args: Cli) -> Result<(), std::io::Error> {
         _ => None,
     };
 
-    let data = match args.mode {
+    // the data is piped through in chunks, so it never has to fit in memory
+    match args.mode {
         Mode::Encrypt => {
-            match cipher {
-                Some(c) => c.encrypt(&iv[..], &mut bytes).unwrap(),
-                None => {
-                    let chacha = XChaCha20::new(&key);
-                    chacha.encrypt(&(0..24).collect::<Vec<u8>>()[..], &mut bytes).unwrap();
-                }
-            }
-            bytes.into_iter().encode().collect()
+            let output = HammingWriter::new(output);
+            let mut writer = match &cipher {
+                Some(c) => EncryptWriter::cbc(&**c, &iv[..], output)?,
+                None => EncryptWriter::keystream(XChaCha20::new(&key).keystream(&nonce)?, output),
+            };
+            io::copy(&mut input, &mut writer)?;
+            writer.finish()?.finish()?;
         }
         Mode::Decrypt => {
-            bytes = bytes.into_iter().decode().collect();
-            match cipher {
+            let mut output = output;
+            let mut reader = match &cipher {
                 Some(c) => {
-                    while bytes.len() & 15 != 0 {
-                        bytes.pop();
-                    }
-                    c.decrypt(&iv[..], &mut bytes).unwrap();
+                    DecryptReader::cbc(&**c, &iv[..], HammingReader::new(input, c.block_size()))?
                 }
-                None => {
-                    let chacha = XChaCha20::new(&key);
-                    chacha.decrypt(&(0..24).collect::<Vec<u8>>()[..], &mut bytes).unwrap();
-                }
-            }
-            bytes
+                None => DecryptReader::keystream(
+                    XChaCha20::new(&key).keystream(&nonce)?,
+                    HammingReader::new(input, 1),
+                ),
+            };
+            io::copy(&mut reader, &mut output)?;
+            output.flush()?;
         }
-    };
-
-    // write the processed data to either a file or to stdout
-    if let Some(filename) = args.output {
-        File::create(filename)?.write(&data)
-    } else {
-        std::io::stdout().write(&data)
-    }?;
+    }
 
     Ok(())
 }
 
-fn main() -> Result<(), std::io::Error> {
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     run(Cli::from_args())
 }
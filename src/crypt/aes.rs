@@ -11,7 +11,7 @@
 //!     let mut message = plaintext.clone();
 //!
 //!     // Look up in some NIST publication or RFC for salt generation.
-//!     let key_vec = pbkdf2(secret_password, &salt[..], iteration_count, 256, &HMAC::default());
+//!     let key_vec = pbkdf2(secret_password, &salt[..], iteration_count, 256, &HMAC::default()).unwrap();
 //!
 //!     // Just move the key into an array, lots of ways to do this.
 //!     let mut key = [0u8; 32];
@@ -256,7 +256,7 @@ pub enum AESKey {
 }
 
 impl<'a> std::convert::TryFrom<super::CipherKey<'a>> for AESKey {
-    type Error=String;
+    type Error = super::CryptoError;
 
     fn try_from(key: super::CipherKey) -> Result<AESKey, Self::Error> {
         match key.key.len() {
@@ -275,7 +275,7 @@ impl<'a> std::convert::TryFrom<super::CipherKey<'a>> for AESKey {
                 key_arr[..].clone_from_slice(key.key);
                 Ok(AESKey::AES256(key_arr))
             }
-            _ => Err("Not implemented yet".to_string()),
+            _ => Err(super::CryptoError::InvalidKeyLength(key.key.len())),
         }
     }
 }
@@ -289,6 +289,7 @@ pub struct AES {
     w: Vec<u8>,
     nr: usize,
     mode: super::EncryptionMode,
+    padding: super::Padding,
     key: AESKey,
 }
 
@@ -333,6 +334,7 @@ impl AES {
             w,
             nr,
             mode: super::EncryptionMode::CBC,
+            padding: super::Padding::default(),
             key: key_size,
         })
     }
@@ -460,6 +462,14 @@ impl BlockCipher for AES {
         self.mode
     }
 
+    fn change_padding(&mut self, padding: super::Padding) {
+        self.padding = padding;
+    }
+
+    fn padding(&self) -> super::Padding {
+        self.padding
+    }
+
     fn get_key(&self) -> &[u8] {
         use AESKey::*;
         match &self.key {
@@ -469,7 +479,7 @@ impl BlockCipher for AES {
         }
     }
 
-    fn set_key(&mut self, key: &[u8]) -> Result<(), String> {
+    fn set_key(&mut self, key: &[u8]) -> Result<(), super::CryptoError> {
         use AESKey::*;
         let key_sized;
         match key.len() {
@@ -488,7 +498,7 @@ impl BlockCipher for AES {
                 k[..].clone_from_slice(key);
                 key_sized = AES128(k);
             }
-            _ => return Err(format!("{} is not a key length for AES", key.len())),
+            _ => return Err(super::CryptoError::InvalidKeyLength(key.len())),
         }
 
         let (key, nr, nk) = match &key_sized {
@@ -718,4 +728,57 @@ mod tests {
 
         assert_eq!(encrypted[0..ciphertext.len()], ciphertext[..]);
     }
+
+    #[test]
+    fn invalid_lengths() {
+        use crate::crypt::{CipherKey, CryptoError};
+        use std::convert::TryFrom;
+
+        assert!(AESKey::try_from(CipherKey { key: &[0u8; 16] }).is_ok());
+        assert_eq!(
+            AESKey::try_from(CipherKey { key: &[0u8; 17] }).err(),
+            Some(CryptoError::InvalidKeyLength(17))
+        );
+
+        let mut aes = AES::new(AESKey::AES128([0u8; 16]));
+        assert_eq!(aes.set_key(&[0u8; 31]), Err(CryptoError::InvalidKeyLength(31)));
+
+        let mut message = b"hello".to_vec();
+        assert_eq!(
+            aes.encrypt(&[0u8; 15], &mut message),
+            Err(CryptoError::InvalidIvLength {
+                expected: 16,
+                got: 15
+            })
+        );
+
+        let mut ciphertext = vec![0u8; 17];
+        assert_eq!(
+            aes.decrypt(&[0u8; 16], &mut ciphertext),
+            Err(CryptoError::InvalidDataLength(17))
+        );
+    }
+
+    #[test]
+    fn corrupted_padding() {
+        use crate::crypt::CryptoError;
+
+        let aes = AES::new(AESKey::AES128([7u8; 16]));
+        let iv = [0u8; 16];
+        let mut message = b"hello".to_vec();
+        aes.encrypt(&iv, &mut message).unwrap();
+
+        // garbage decrypts to garbage padding
+        for i in 0..16 {
+            let mut corrupted = message.clone();
+            corrupted[i] ^= 0x40;
+            assert_eq!(
+                aes.decrypt(&iv, &mut corrupted),
+                Err(CryptoError::BadPadding)
+            );
+        }
+
+        let mut empty = Vec::new();
+        assert_eq!(aes.decrypt(&iv, &mut empty), Err(CryptoError::BadPadding));
+    }
 }
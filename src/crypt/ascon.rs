@@ -0,0 +1,322 @@
+//! ASCON-128 and ASCON-Hash, version 1.2 by the [designers](https://ascon.iaik.tugraz.at/),
+//! selected by NIST for lightweight cryptography.
+//! Both are a sponge of a 320 bit permutation, which is only AND, XOR and rotations.
+//! The rate is 64 bits, the state is five big endian words and the first word is the rate.
+//!
+//!     # use codes::crypt::{ascon::Ascon128, AEAD};
+//!     let ascon = Ascon128::new(&[0x42; 16]);
+//!
+//!     let mut message = b"attack at dawn".to_vec();
+//!     ascon.encrypt(&[0; 16], b"header", &mut message).unwrap();
+//!     assert_eq!(message.len(), 14 + 16);
+//!
+//!     ascon.decrypt(&[0; 16], b"header", &mut message).unwrap();
+//!     assert_eq!(message, b"attack at dawn");
+
+use super::sha::Hash;
+use super::{verify_tag, CryptoError, AEAD};
+
+const RATE: usize = 8;
+
+/// The key, rate and round numbers of ASCON-128.
+const IV_AEAD: u64 = 0x80400c0600000000;
+
+/// The rate, round numbers and the 256 bit output of ASCON-Hash.
+const IV_HASH: u64 = 0x00400c0000000100;
+
+/// The permutation with the last `rounds` of the twelve round constants.
+fn permute(s: &mut [u64; 5], rounds: usize) {
+    for r in (12 - rounds)..12 {
+        s[2] ^= (0xf0 - r * 0x0f) as u64;
+
+        // the 5 bit S-box, bitsliced over the words
+        s[0] ^= s[4];
+        s[4] ^= s[3];
+        s[2] ^= s[1];
+        let t = [
+            !s[0] & s[1],
+            !s[1] & s[2],
+            !s[2] & s[3],
+            !s[3] & s[4],
+            !s[4] & s[0],
+        ];
+        for i in 0..5 {
+            s[i] ^= t[(i + 1) % 5];
+        }
+        s[1] ^= s[0];
+        s[0] ^= s[4];
+        s[3] ^= s[2];
+        s[2] = !s[2];
+
+        s[0] ^= s[0].rotate_right(19) ^ s[0].rotate_right(28);
+        s[1] ^= s[1].rotate_right(61) ^ s[1].rotate_right(39);
+        s[2] ^= s[2].rotate_right(1) ^ s[2].rotate_right(6);
+        s[3] ^= s[3].rotate_right(10) ^ s[3].rotate_right(17);
+        s[4] ^= s[4].rotate_right(7) ^ s[4].rotate_right(41);
+    }
+}
+
+/// A block of at most eight bytes, padded with a one bit and zeros if it is shorter.
+fn load(bytes: &[u8]) -> u64 {
+    let mut b = [0u8; RATE];
+    b[..bytes.len()].clone_from_slice(bytes);
+    if bytes.len() < RATE {
+        b[bytes.len()] = 0x80;
+    }
+    u64::from_be_bytes(b)
+}
+
+fn words(bytes: &[u8]) -> [u64; 2] {
+    let mut b = [0u8; 8];
+    b.clone_from_slice(&bytes[..8]);
+    let w0 = u64::from_be_bytes(b);
+    b.clone_from_slice(&bytes[8..16]);
+    [w0, u64::from_be_bytes(b)]
+}
+
+/// ASCON-128, with a 128 bit key, nonce and tag.
+pub struct Ascon128 {
+    key: [u64; 2],
+}
+
+impl Ascon128 {
+    pub fn new(key: &[u8; 16]) -> Self {
+        Ascon128 { key: words(key) }
+    }
+
+    /// The state after the initialization and the associated data.
+    fn start(&self, nonce: &[u8], ad: &[u8]) -> Result<[u64; 5], CryptoError> {
+        if nonce.len() != 16 {
+            return Err(CryptoError::InvalidIvLength {
+                expected: 16,
+                got: nonce.len(),
+            });
+        }
+        let [k0, k1] = self.key;
+        let [n0, n1] = words(nonce);
+        let mut s = [IV_AEAD, k0, k1, n0, n1];
+        permute(&mut s, 12);
+        s[3] ^= k0;
+        s[4] ^= k1;
+
+        // there is no padded block when there is no associated data
+        if !ad.is_empty() {
+            for chunk in ad.chunks(RATE) {
+                s[0] ^= load(chunk);
+                permute(&mut s, 6);
+            }
+            if ad.len().is_multiple_of(RATE) {
+                s[0] ^= load(&[]);
+                permute(&mut s, 6);
+            }
+        }
+        s[4] ^= 1;
+        Ok(s)
+    }
+
+    fn finish(&self, s: &mut [u64; 5]) -> Vec<u8> {
+        let [k0, k1] = self.key;
+        s[1] ^= k0;
+        s[2] ^= k1;
+        permute(s, 12);
+        let mut tag = (s[3] ^ k0).to_be_bytes().to_vec();
+        tag.extend_from_slice(&(s[4] ^ k1).to_be_bytes());
+        tag
+    }
+}
+
+impl AEAD for Ascon128 {
+    fn encrypt(&self, nonce: &[u8], ad: &[u8], plaintext: &mut Vec<u8>) -> Result<(), CryptoError> {
+        let mut s = self.start(nonce, ad)?;
+
+        let full = plaintext.len() / RATE * RATE;
+        for chunk in plaintext[..full].chunks_mut(RATE) {
+            s[0] ^= load(chunk);
+            chunk.clone_from_slice(&s[0].to_be_bytes());
+            permute(&mut s, 6);
+        }
+        // the last block is always padded, and may be empty
+        let last = &mut plaintext[full..];
+        s[0] ^= load(last);
+        let len = last.len();
+        last.clone_from_slice(&s[0].to_be_bytes()[..len]);
+
+        let tag = self.finish(&mut s);
+        plaintext.extend_from_slice(&tag);
+        Ok(())
+    }
+
+    fn decrypt(
+        &self,
+        nonce: &[u8],
+        ad: &[u8],
+        ciphertext: &mut Vec<u8>,
+    ) -> Result<(), CryptoError> {
+        if ciphertext.len() < 16 {
+            return Err(CryptoError::InvalidDataLength(ciphertext.len()));
+        }
+        let len = ciphertext.len() - 16;
+        let mut s = self.start(nonce, ad)?;
+
+        let mut plaintext = ciphertext[..len].to_vec();
+        let full = len / RATE * RATE;
+        for chunk in plaintext[..full].chunks_mut(RATE) {
+            let c = load(chunk);
+            chunk.clone_from_slice(&(s[0] ^ c).to_be_bytes());
+            s[0] = c;
+            permute(&mut s, 6);
+        }
+        // the ciphertext replaces the first bytes of the rate, the padding is XORed in
+        let last = &mut plaintext[full..];
+        let mut rate = s[0].to_be_bytes();
+        for (p, r) in last.iter_mut().zip(rate.iter_mut()) {
+            let c = *p;
+            *p ^= *r;
+            *r = c;
+        }
+        rate[last.len()] ^= 0x80;
+        s[0] = u64::from_be_bytes(rate);
+
+        let tag = self.finish(&mut s);
+        verify_tag(&tag, &ciphertext[len..])?;
+
+        // the plaintext is only released when the tag is correct
+        *ciphertext = plaintext;
+        Ok(())
+    }
+}
+
+/// ASCON-Hash, with a 256 bit digest.
+pub struct AsconHash;
+
+impl Hash for AsconHash {
+    fn hash<T>(&self, data: T) -> Box<[u8]>
+    where
+        std::vec::Vec<u8>: From<T>,
+    {
+        let data = Vec::from(data);
+        let mut s = [IV_HASH, 0, 0, 0, 0];
+        permute(&mut s, 12);
+
+        // the message is always padded, so there is a last block even if it is empty
+        let full = data.len() / RATE * RATE;
+        for chunk in data[..full].chunks(RATE) {
+            s[0] ^= load(chunk);
+            permute(&mut s, 12);
+        }
+        s[0] ^= load(&data[full..]);
+
+        let mut digest = Vec::with_capacity(32);
+        for _ in 0..4 {
+            permute(&mut s, 12);
+            digest.extend_from_slice(&s[0].to_be_bytes());
+        }
+        digest.into_boxed_slice()
+    }
+
+    fn size(&self) -> usize {
+        256
+    }
+
+    fn block_size(&self) -> usize {
+        RATE << 3
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::tests::hex;
+
+    /// The key, nonce, associated data and plaintext of the known answers count up from zero.
+    fn counting(len: usize) -> Vec<u8> {
+        (0..len as u8).collect()
+    }
+
+    fn ascon() -> Ascon128 {
+        let mut key = [0u8; 16];
+        key.clone_from_slice(&counting(16));
+        Ascon128::new(&key)
+    }
+
+    #[test]
+    fn aead() {
+        // Count = 1, 2 and 34 of LWC_AEAD_KAT_128_128.txt of the reference implementation,
+        // where the lengths of the plaintext and the associated data are (Count - 1) / 33
+        // and (Count - 1) % 33
+        let vectors = [
+            (1, "e355159f292911f794cb1432a0103a8a"),
+            (2, "944df887cd4901614c5dedbc42fc0da0"),
+            (34, "bc18c3f4e39eca7222490d967c79bffc92"),
+        ];
+        for (count, expected) in vectors.iter() {
+            let (pt_len, ad_len) = ((count - 1) / 33, (count - 1) % 33);
+            let mut data = counting(pt_len);
+            ascon()
+                .encrypt(&counting(16), &counting(ad_len), &mut data)
+                .unwrap();
+            assert_eq!(data, hex(expected));
+            ascon()
+                .decrypt(&counting(16), &counting(ad_len), &mut data)
+                .unwrap();
+            assert_eq!(data, counting(pt_len));
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let ascon = ascon();
+        for len in 0..40 {
+            let mut data = counting(len);
+            ascon.encrypt(&[1; 16], &counting(len), &mut data).unwrap();
+            assert_eq!(data.len(), len + 16);
+            ascon.decrypt(&[1; 16], &counting(len), &mut data).unwrap();
+            assert_eq!(data, counting(len));
+        }
+    }
+
+    #[test]
+    fn forgery() {
+        let ascon = ascon();
+        let mut data = b"a message longer than a block".to_vec();
+        ascon.encrypt(&[0; 16], b"ad", &mut data).unwrap();
+
+        for i in 0..data.len() {
+            let mut corrupted = data.clone();
+            corrupted[i] ^= 1;
+            let before = corrupted.clone();
+            assert_eq!(
+                ascon.decrypt(&[0; 16], b"ad", &mut corrupted),
+                Err(CryptoError::AuthenticationFailure)
+            );
+            assert_eq!(corrupted, before);
+        }
+        assert!(ascon.decrypt(&[0; 16], b"da", &mut data.clone()).is_err());
+        assert!(ascon.decrypt(&[0; 12], b"ad", &mut data.clone()).is_err());
+        assert!(ascon.decrypt(&[0; 16], b"ad", &mut vec![0; 15]).is_err());
+    }
+
+    #[test]
+    fn hash() {
+        // Count = 1, 2 and 3 of LWC_HASH_KAT_256.txt of the reference implementation,
+        // where the message is Count - 1 bytes
+        let vectors = [
+            (
+                1,
+                "7346bc14f036e87ae03d0997913088f5f68411434b3cf8b54fa796a80d251f91",
+            ),
+            (
+                2,
+                "8dd446ada58a7740ecf56eb638ef775f7d5c0fd5f0c2bbbdfdec29609d3c43a2",
+            ),
+            (
+                3,
+                "f77ca13bf89146d3254f1cfb7eddba8fa1bf162284bb29e7f645545cf9e08424",
+            ),
+        ];
+        for (count, digest) in vectors.iter() {
+            assert_eq!(AsconHash.hash(counting(count - 1))[..], hex(digest)[..]);
+        }
+        assert_eq!(AsconHash.size(), 256);
+    }
+}
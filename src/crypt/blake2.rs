@@ -0,0 +1,495 @@
+//! The BLAKE2b and BLAKE2s hash functions, by RFC 7693,
+//! with an optional key, salt and personalization string.
+//! BLAKE2b has 64 bit words and digests of 1 to 64 bytes,
+//! BLAKE2s has 32 bit words and digests of 1 to 32 bytes.
+//!
+//!     # use codes::crypt::blake2::{blake2b, Blake2, Variant};
+//!     # use codes::crypt::sha::Hash;
+//!     let digest = blake2b(&[], b"abc", 64).unwrap();
+//!     assert_eq!(digest[..4], [0xba, 0x80, 0xa5, 0x3f]);
+//!
+//!     let blake2s = Blake2::new(Variant::Blake2s, 256).unwrap();
+//!     assert_eq!(blake2s.hash("abc")[..4], [0x50, 0x8c, 0x5e, 0x8c]);
+
+use super::mac::MAC;
+use super::sha::Hash;
+use super::CryptoError;
+
+/// The same as the initial hash value of SHA-512.
+const IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+/// The same as the initial hash value of SHA-256.
+const IV_S: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// The message word permutations of each round.
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+/// The two word sizes, where everything but the constants is the same.
+trait Word: Copy + std::ops::BitXor<Output = Self> + std::ops::Not<Output = Self> {
+    const BYTES: usize;
+    const ROUNDS: usize;
+    const ROTATIONS: [u32; 4];
+    const IV: [Self; 8];
+
+    fn add(self, other: Self) -> Self;
+    fn rotr(self, n: u32) -> Self;
+    fn from_le(bytes: &[u8]) -> Self;
+    fn to_le(self) -> Vec<u8>;
+    /// The low word of the number.
+    fn truncate(t: u128) -> Self;
+}
+
+impl Word for u64 {
+    const BYTES: usize = 8;
+    const ROUNDS: usize = 12;
+    const ROTATIONS: [u32; 4] = [32, 24, 16, 63];
+    const IV: [u64; 8] = IV;
+
+    fn add(self, other: u64) -> u64 {
+        self.wrapping_add(other)
+    }
+
+    fn rotr(self, n: u32) -> u64 {
+        self.rotate_right(n)
+    }
+
+    fn from_le(bytes: &[u8]) -> u64 {
+        let mut b = [0u8; 8];
+        b.clone_from_slice(bytes);
+        u64::from_le_bytes(b)
+    }
+
+    fn to_le(self) -> Vec<u8> {
+        self.to_le_bytes().to_vec()
+    }
+
+    fn truncate(t: u128) -> u64 {
+        t as u64
+    }
+}
+
+impl Word for u32 {
+    const BYTES: usize = 4;
+    const ROUNDS: usize = 10;
+    const ROTATIONS: [u32; 4] = [16, 12, 8, 7];
+    const IV: [u32; 8] = IV_S;
+
+    fn add(self, other: u32) -> u32 {
+        self.wrapping_add(other)
+    }
+
+    fn rotr(self, n: u32) -> u32 {
+        self.rotate_right(n)
+    }
+
+    fn from_le(bytes: &[u8]) -> u32 {
+        let mut b = [0u8; 4];
+        b.clone_from_slice(bytes);
+        u32::from_le_bytes(b)
+    }
+
+    fn to_le(self) -> Vec<u8> {
+        self.to_le_bytes().to_vec()
+    }
+
+    fn truncate(t: u128) -> u32 {
+        t as u32
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn g<W: Word>(v: &mut [W; 16], a: usize, b: usize, c: usize, d: usize, x: W, y: W) {
+    let r = W::ROTATIONS;
+    v[a] = v[a].add(v[b]).add(x);
+    v[d] = (v[d] ^ v[a]).rotr(r[0]);
+    v[c] = v[c].add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotr(r[1]);
+    v[a] = v[a].add(v[b]).add(y);
+    v[d] = (v[d] ^ v[a]).rotr(r[2]);
+    v[c] = v[c].add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotr(r[3]);
+}
+
+/// Compress a block of 16 words into the state, `t` is the number of bytes so far.
+fn compress<W: Word>(h: &mut [W; 8], block: &[u8], t: u128, last: bool) {
+    let mut m = [W::truncate(0); 16];
+    for (m, b) in m.iter_mut().zip(block.chunks(W::BYTES)) {
+        *m = W::from_le(b);
+    }
+
+    let mut v = [W::truncate(0); 16];
+    v[..8].clone_from_slice(h);
+    v[8..].clone_from_slice(&W::IV);
+    v[12] = v[12] ^ W::truncate(t);
+    v[13] = v[13] ^ W::truncate(t >> (W::BYTES << 3));
+    if last {
+        v[14] = !v[14];
+    }
+
+    for i in 0..W::ROUNDS {
+        let s = &SIGMA[i % 10];
+        g(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+        g(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+        g(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+        g(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+        g(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+        g(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+        g(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+        g(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+    }
+
+    for (i, h) in h.iter_mut().enumerate() {
+        *h = *h ^ v[i] ^ v[i + 8];
+    }
+}
+
+/// The hash with the parameter block of eight words,
+/// the key is at most a block and the output length in bytes at most eight words.
+fn hash<W: Word>(params: &[u8], key: &[u8], data: &[u8], out_len: usize) -> Vec<u8> {
+    let block_len = W::BYTES << 4;
+    let mut h = W::IV;
+    for (h, p) in h.iter_mut().zip(params.chunks(W::BYTES)) {
+        *h = *h ^ W::from_le(p);
+    }
+
+    // the key is padded to a block, and hashed before the data
+    let mut message = Vec::with_capacity(block_len + data.len());
+    if !key.is_empty() {
+        message.extend_from_slice(key);
+        message.resize(block_len, 0);
+    }
+    message.extend_from_slice(data);
+
+    // the last block is compressed differently, even if it is full, and there is always one
+    let blocks = message.len().saturating_sub(1) / block_len;
+    for (i, chunk) in message.chunks(block_len).take(blocks).enumerate() {
+        compress(&mut h, chunk, ((i + 1) * block_len) as u128, false);
+    }
+    let last = &message[(blocks * block_len)..];
+    let mut block = vec![0u8; block_len];
+    block[..last.len()].clone_from_slice(last);
+    compress(&mut h, &block, message.len() as u128, true);
+
+    h.iter().flat_map(|w| w.to_le()).take(out_len).collect()
+}
+
+pub enum Variant {
+    Blake2b,
+    Blake2s,
+}
+
+impl Variant {
+    /// The word size in bytes.
+    fn word(&self) -> usize {
+        match self {
+            Variant::Blake2b => 8,
+            Variant::Blake2s => 4,
+        }
+    }
+}
+
+/// BLAKE2 with fixed parameters, the output size is in bits.
+/// It is a `Hash` without a key, and a `MAC` with one.
+pub struct Blake2 {
+    variant: Variant,
+    out_len: usize,
+    salt: Vec<u8>,
+    personalization: Vec<u8>,
+}
+
+impl Blake2 {
+    /// The output is a whole number of bytes, at most 512 bits for BLAKE2b and 256 for BLAKE2s.
+    pub fn new(variant: Variant, out_len: usize) -> Result<Self, CryptoError> {
+        let max = variant.word() << 6;
+        if out_len == 0 || out_len > max || out_len & 7 != 0 {
+            return Err(CryptoError::InvalidParameter(format!(
+                "a digest of {} bits, it must be a positive number of bytes up to {} bits",
+                out_len, max
+            )));
+        }
+        Ok(Blake2 {
+            variant,
+            out_len,
+            salt: Vec::new(),
+            personalization: Vec::new(),
+        })
+    }
+
+    /// The salt is at most 16 bytes for BLAKE2b and 8 for BLAKE2s.
+    pub fn salted(mut self, salt: &[u8]) -> Result<Self, CryptoError> {
+        self.salt = self.parameter(salt, "salt")?;
+        Ok(self)
+    }
+
+    /// The personalization string has the same length limit as the salt.
+    pub fn personalized(mut self, personalization: &[u8]) -> Result<Self, CryptoError> {
+        self.personalization = self.parameter(personalization, "personalization")?;
+        Ok(self)
+    }
+
+    fn parameter(&self, value: &[u8], name: &str) -> Result<Vec<u8>, CryptoError> {
+        let max = self.variant.word() << 1;
+        if value.len() > max {
+            return Err(CryptoError::InvalidParameter(format!(
+                "a {} of {} bytes, it can be at most {}",
+                name,
+                value.len(),
+                max
+            )));
+        }
+        Ok(value.to_vec())
+    }
+
+    /// The digest of the data, keyed if the key is not empty.
+    pub fn digest(&self, key: &[u8], data: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let w = self.variant.word();
+        if key.len() > w << 3 {
+            return Err(CryptoError::InvalidKeyLength(key.len()));
+        }
+
+        // the parameter block, the lengths, a fanout and depth of one for sequential hashing,
+        // then the salt and personalization in the last four words
+        let mut params = vec![0u8; w << 3];
+        params[0] = (self.out_len >> 3) as u8;
+        params[1] = key.len() as u8;
+        params[2] = 1;
+        params[3] = 1;
+        params[(w << 2)..(w << 2) + self.salt.len()].clone_from_slice(&self.salt);
+        params[6 * w..6 * w + self.personalization.len()].clone_from_slice(&self.personalization);
+
+        Ok(match self.variant {
+            Variant::Blake2b => hash::<u64>(&params, key, data, self.out_len >> 3),
+            Variant::Blake2s => hash::<u32>(&params, key, data, self.out_len >> 3),
+        })
+    }
+}
+
+impl Hash for Blake2 {
+    fn hash<T>(&self, data: T) -> Box<[u8]>
+    where
+        std::vec::Vec<u8>: From<T>,
+    {
+        self.digest(&[], &Vec::from(data))
+            .expect("the empty key is valid")
+            .into_boxed_slice()
+    }
+
+    fn size(&self) -> usize {
+        self.out_len
+    }
+
+    fn block_size(&self) -> usize {
+        self.variant.word() << 7
+    }
+}
+
+/// The keyed mode, a tag shorter than the output size is truncated, like with HMAC.
+impl MAC for Blake2 {
+    fn mac(&self, key: &[u8], text: &[u8], tag_len: usize) -> Result<Vec<u8>, CryptoError> {
+        if tag_len == 0 || tag_len > self.out_len || tag_len & 7 != 0 {
+            return Err(CryptoError::InvalidTagLength(tag_len));
+        }
+        let mut tag = self.digest(key, text)?;
+        tag.truncate(tag_len >> 3);
+        Ok(tag)
+    }
+}
+
+/// Hash the data to a digest of `out_len` bytes (at most 64).
+/// With a key (at most 64 bytes), this is a MAC.
+pub fn blake2b(key: &[u8], data: &[u8], out_len: usize) -> Result<Vec<u8>, CryptoError> {
+    Blake2::new(Variant::Blake2b, out_len << 3)?.digest(key, data)
+}
+
+/// Hash the data to a digest of `out_len` bytes (at most 32).
+/// With a key (at most 32 bytes), this is a MAC.
+pub fn blake2s(key: &[u8], data: &[u8], out_len: usize) -> Result<Vec<u8>, CryptoError> {
+    Blake2::new(Variant::Blake2s, out_len << 3)?.digest(key, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::tests::hex;
+
+    #[test]
+    fn rfc7693() {
+        // appendix A
+        assert_eq!(
+            blake2b(&[], b"abc", 64).unwrap(),
+            hex(
+                "BA80A53F981C4D0D6A2797B69F12F6E94C212F14685AC4B74B12BB6FDBFFA2D1
+                 7D87C5392AAB792DC252D5DE4533CC9518D38AA8DBF1925AB92386EDD4009923"
+            )
+        );
+        // appendix B
+        assert_eq!(
+            blake2s(&[], b"abc", 32).unwrap(),
+            hex("508C5E8C327C14E2E1A72BA34EEB452F37458B209ED63A294D999B4C86675982")
+        );
+    }
+
+    /// The deterministic sequence of appendix E, a Fibonacci generator.
+    fn selftest_seq(len: usize, seed: u32) -> Vec<u8> {
+        let mut a = 0xDEAD4BADu32.wrapping_mul(seed);
+        let mut b = 1u32;
+        (0..len)
+            .map(|_| {
+                let t = a.wrapping_add(b);
+                a = b;
+                b = t;
+                (t >> 24) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rfc7693_selftest() {
+        // appendix E, the hash of the digests of several lengths, with and without a key
+        let tests = [
+            (
+                Variant::Blake2b,
+                [20, 32, 48, 64],
+                [0, 3, 128, 129, 255, 1024],
+                "C23A7800D98123BD10F506C61E29DA5603D763B8BBAD2E737F5E765A7BCCD475",
+            ),
+            (
+                Variant::Blake2s,
+                [16, 20, 28, 32],
+                [0, 3, 64, 65, 255, 1024],
+                "6A411F08CE25ADCDFB02ABA641451CEC53C598B24F4FC787FBDC88797F4C1DFE",
+            ),
+        ];
+
+        for (variant, md_lens, in_lens, expected) in tests.iter() {
+            let hash = |key: &[u8], data: &[u8], out_len: usize| match variant {
+                Variant::Blake2b => blake2b(key, data, out_len).unwrap(),
+                Variant::Blake2s => blake2s(key, data, out_len).unwrap(),
+            };
+            let mut digests = Vec::new();
+            for out_len in md_lens.iter() {
+                for in_len in in_lens.iter() {
+                    let data = selftest_seq(*in_len, *in_len as u32);
+                    digests.extend(hash(&[], &data, *out_len));
+                    let key = selftest_seq(*out_len, *out_len as u32);
+                    digests.extend(hash(&key, &data, *out_len));
+                }
+            }
+            assert_eq!(hash(&[], &digests, 32), hex(expected));
+        }
+    }
+
+    #[test]
+    fn salt_and_personalization() {
+        // checked with Python's hashlib
+        let blake2b = Blake2::new(Variant::Blake2b, 256)
+            .unwrap()
+            .salted(b"salt")
+            .unwrap()
+            .personalized(b"me")
+            .unwrap();
+        assert_eq!(
+            blake2b.mac(b"key", b"abc", 256).unwrap(),
+            hex("3d0e345a91dda5cc946b49c118d304b237ce5f5203d9523255834a8def438f3d")
+        );
+        let blake2s = Blake2::new(Variant::Blake2s, 128)
+            .unwrap()
+            .salted(b"salt")
+            .unwrap()
+            .personalized(b"me")
+            .unwrap();
+        assert_eq!(
+            blake2s.mac(b"key", b"abc", 128).unwrap(),
+            hex("6ce549b6209a8994c7272010531992c5")
+        );
+
+        assert!(Blake2::new(Variant::Blake2s, 256)
+            .unwrap()
+            .salted(&[0; 9])
+            .is_err());
+        assert!(Blake2::new(Variant::Blake2b, 512)
+            .unwrap()
+            .personalized(&[0; 16])
+            .is_ok());
+    }
+
+    #[test]
+    fn hash_and_mac() {
+        let blake2 = Blake2::new(Variant::Blake2s, 256).unwrap();
+        assert_eq!(blake2.size(), 256);
+        assert_eq!(blake2.block_size(), 512);
+        assert_eq!(
+            blake2.hash("abc")[..],
+            blake2s(&[], b"abc", 32).unwrap()[..]
+        );
+
+        // the keyed known answer of the reference implementation for the empty message
+        let key: Vec<u8> = (0..32).collect();
+        let tag = blake2.mac(&key, &[], 256).unwrap();
+        assert_eq!(
+            tag,
+            hex("48a8997da407876b3d79c0d92325ad3b89cbb754d86ab71aee047ad345fd2c49")
+        );
+        assert_eq!(blake2.mac(&key, &[], 128).unwrap(), tag[..16]);
+        assert!(blake2.verify(&key, &[], &tag).is_ok());
+        assert!(blake2.verify(&key, &[0], &tag).is_err());
+        assert!(blake2.mac(&[0; 33], &[], 256).is_err());
+        assert!(blake2.mac(&key, &[], 264).is_err());
+
+        assert!(Blake2::new(Variant::Blake2s, 264).is_err());
+        assert!(Blake2::new(Variant::Blake2b, 512).is_ok());
+        assert!(Blake2::new(Variant::Blake2b, 12).is_err());
+    }
+
+    #[test]
+    fn keyed_and_lengths() {
+        // the first and last of the keyed known answers of the reference implementation
+        let key: Vec<u8> = (0..64).collect();
+        assert_eq!(
+            blake2b(&key, &[], 64).unwrap(),
+            hex(
+                "10ebb67700b1868efb4417987acf4690ae9d972fb7a590c2f02871799aaa4786
+                 b5e996e8f0f4eb981fc214b005f42d2ff4233499391653df7aefcbc13fc51568"
+            )
+        );
+        let data: Vec<u8> = (0..255).collect();
+        assert_eq!(
+            blake2b(&key, &data, 64).unwrap(),
+            hex(
+                "142709d62e28fcccd0af97fad0f8465b971e82201dc51070faa0372aa43e9248
+                 4be1c1e73ba10906d5d1853db6a4106e0a7bf9800d373d6dee2d46d62ef2a461"
+            )
+        );
+
+        assert_eq!(
+            blake2b(&[], &[], 32).unwrap(),
+            hex("0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8")
+        );
+
+        assert!(blake2b(&[0; 65], &[], 64).is_err());
+        assert!(blake2b(&[], &[], 0).is_err());
+        assert!(blake2b(&[], &[], 65).is_err());
+    }
+}
@@ -0,0 +1,372 @@
+//! The BLAKE3 hash function, by the [BLAKE3 specification](https://github.com/BLAKE3-team/BLAKE3-specs).
+//! The input is split into chunks of 1024 bytes, which are the leaves of a binary tree,
+//! and the subtrees are hashed in parallel. The output can be extended to any length.
+//!
+//! There are three modes, the plain hash, the keyed hash with a 256 bit key,
+//! and key derivation with a context string.
+//!
+//!     # use codes::crypt::blake3::Blake3;
+//!     # use codes::crypt::sha::Hash;
+//!     let digest = Blake3::new().hash("abc");
+//!     assert_eq!(digest[..4], [0x64, 0x37, 0xb3, 0xac]);
+//!
+//!     let key = Blake3::derive_key("example.com 2026-10-19 session keys").xof(b"secret", 64);
+//!     assert_eq!(key.len(), 64);
+
+use super::mac::MAC;
+use super::sha::Hash;
+use super::CryptoError;
+
+const BLOCK_LEN: usize = 64;
+const CHUNK_LEN: usize = 1024;
+
+/// Subtrees with more input than this are hashed in parallel.
+const PARALLEL_LEN: usize = 16 * CHUNK_LEN;
+
+const CHUNK_START: u32 = 1;
+const CHUNK_END: u32 = 2;
+const PARENT: u32 = 4;
+const ROOT: u32 = 8;
+const KEYED_HASH: u32 = 16;
+const DERIVE_KEY_CONTEXT: u32 = 32;
+const DERIVE_KEY_MATERIAL: u32 = 64;
+
+/// The same as the initial hash value of SHA-256.
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// The message words of a round are the ones of the previous round in this order.
+const MSG_PERMUTATION: [usize; 16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+fn g(v: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, x: u32, y: u32) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(12);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(8);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(7);
+}
+
+/// The full 16 word state, the first 8 words are the new chaining value.
+fn compress(
+    cv: &[u32; 8],
+    block: &[u32; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+) -> [u32; 16] {
+    let mut v = [0u32; 16];
+    v[..8].clone_from_slice(cv);
+    v[8..12].clone_from_slice(&IV[..4]);
+    v[12] = counter as u32;
+    v[13] = (counter >> 32) as u32;
+    v[14] = block_len;
+    v[15] = flags;
+
+    let mut m = *block;
+    for round in 0..7 {
+        g(&mut v, 0, 4, 8, 12, m[0], m[1]);
+        g(&mut v, 1, 5, 9, 13, m[2], m[3]);
+        g(&mut v, 2, 6, 10, 14, m[4], m[5]);
+        g(&mut v, 3, 7, 11, 15, m[6], m[7]);
+        g(&mut v, 0, 5, 10, 15, m[8], m[9]);
+        g(&mut v, 1, 6, 11, 12, m[10], m[11]);
+        g(&mut v, 2, 7, 8, 13, m[12], m[13]);
+        g(&mut v, 3, 4, 9, 14, m[14], m[15]);
+        if round < 6 {
+            let mut permuted = [0u32; 16];
+            for (p, i) in permuted.iter_mut().zip(MSG_PERMUTATION.iter()) {
+                *p = m[*i];
+            }
+            m = permuted;
+        }
+    }
+
+    for i in 0..8 {
+        v[i] ^= v[i + 8];
+        v[i + 8] ^= cv[i];
+    }
+    v
+}
+
+/// A block of at most 64 bytes as little endian words, padded with zeros.
+fn words(bytes: &[u8]) -> [u32; 16] {
+    let mut block = [0u8; BLOCK_LEN];
+    block[..bytes.len()].clone_from_slice(bytes);
+    let mut w = [0u32; 16];
+    for (w, c) in w.iter_mut().zip(block.chunks(4)) {
+        *w = u32::from_le_bytes([c[0], c[1], c[2], c[3]]);
+    }
+    w
+}
+
+/// The last compression of a node, which is left undone until it is known
+/// whether the node is the root.
+struct Output {
+    cv: [u32; 8],
+    block: [u32; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+}
+
+impl Output {
+    fn chaining_value(&self) -> [u32; 8] {
+        let v = compress(
+            &self.cv,
+            &self.block,
+            self.counter,
+            self.block_len,
+            self.flags,
+        );
+        let mut cv = [0u32; 8];
+        cv.clone_from_slice(&v[..8]);
+        cv
+    }
+
+    /// The root node is compressed again for every 64 bytes of output, with the output counter.
+    fn root_bytes(&self, len: usize) -> Vec<u8> {
+        let mut output = Vec::with_capacity(len);
+        for counter in 0..len.div_ceil(BLOCK_LEN) as u64 {
+            let v = compress(
+                &self.cv,
+                &self.block,
+                counter,
+                self.block_len,
+                self.flags | ROOT,
+            );
+            output.extend(v.iter().flat_map(|w| w.to_le_bytes().to_vec()));
+        }
+        output.truncate(len);
+        output
+    }
+}
+
+/// A chunk of at most 1024 bytes, the empty input is a chunk with one empty block.
+fn chunk(key: &[u32; 8], data: &[u8], counter: u64, flags: u32) -> Output {
+    let blocks = data.len().div_ceil(BLOCK_LEN).max(1);
+    let mut cv = *key;
+    let mut start = CHUNK_START;
+    for block in data.chunks(BLOCK_LEN).take(blocks - 1) {
+        let v = compress(&cv, &words(block), counter, BLOCK_LEN as u32, flags | start);
+        cv.clone_from_slice(&v[..8]);
+        start = 0;
+    }
+
+    let last = &data[(blocks - 1) * BLOCK_LEN..];
+    Output {
+        cv,
+        block: words(last),
+        counter,
+        block_len: last.len() as u32,
+        flags: flags | start | CHUNK_END,
+    }
+}
+
+/// The subtree of the data, which starts at the chunk with the counter.
+/// The left subtree has the largest power of two chunks that leaves some data for the right.
+fn subtree(key: &[u32; 8], data: &[u8], counter: u64, flags: u32) -> Output {
+    if data.len() <= CHUNK_LEN {
+        return chunk(key, data, counter, flags);
+    }
+    let full_chunks = (data.len() - 1) / CHUNK_LEN;
+    let left_chunks = 1 << (63 - (full_chunks as u64).leading_zeros());
+    let (left, right) = data.split_at(left_chunks * CHUNK_LEN);
+
+    let left_cv = || subtree(key, left, counter, flags).chaining_value();
+    let right_cv = || subtree(key, right, counter + left_chunks as u64, flags).chaining_value();
+    let (l, r) = if data.len() > PARALLEL_LEN {
+        rayon::join(left_cv, right_cv)
+    } else {
+        (left_cv(), right_cv())
+    };
+
+    let mut block = [0u32; 16];
+    block[..8].clone_from_slice(&l);
+    block[8..].clone_from_slice(&r);
+    Output {
+        cv: *key,
+        block,
+        counter: 0,
+        block_len: BLOCK_LEN as u32,
+        flags: flags | PARENT,
+    }
+}
+
+fn key_words(key: &[u8; 32]) -> [u32; 8] {
+    let mut k = [0u32; 8];
+    k.clone_from_slice(&words(key)[..8]);
+    k
+}
+
+/// BLAKE3 in one of its modes, the output is 256 bits unless it is extended with `xof`.
+pub struct Blake3 {
+    key: [u32; 8],
+    flags: u32,
+}
+
+impl Blake3 {
+    pub fn new() -> Self {
+        Blake3 { key: IV, flags: 0 }
+    }
+
+    /// The keyed hash, which is also a MAC.
+    pub fn keyed(key: &[u8; 32]) -> Self {
+        Blake3 {
+            key: key_words(key),
+            flags: KEYED_HASH,
+        }
+    }
+
+    /// Key derivation, where the input is the key material.
+    /// The context should be hardcoded, globally unique and application specific,
+    /// like the name of the application, a date and what the key is for.
+    pub fn derive_key(context: &str) -> Self {
+        let context_key = subtree(&IV, context.as_bytes(), 0, DERIVE_KEY_CONTEXT).root_bytes(32);
+        let mut key = [0u8; 32];
+        key.clone_from_slice(&context_key);
+        Blake3 {
+            key: key_words(&key),
+            flags: DERIVE_KEY_MATERIAL,
+        }
+    }
+
+    /// The extendable output of any length in bytes.
+    /// A shorter output is a prefix of a longer one.
+    pub fn xof(&self, data: &[u8], out_len: usize) -> Vec<u8> {
+        subtree(&self.key, data, 0, self.flags).root_bytes(out_len)
+    }
+}
+
+impl Default for Blake3 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hash for Blake3 {
+    fn hash<T>(&self, data: T) -> Box<[u8]>
+    where
+        std::vec::Vec<u8>: From<T>,
+    {
+        self.xof(&Vec::from(data), 32).into_boxed_slice()
+    }
+
+    fn size(&self) -> usize {
+        256
+    }
+
+    fn block_size(&self) -> usize {
+        BLOCK_LEN << 3
+    }
+}
+
+/// The keyed hash with a 256 bit key, whatever the mode of `self` is.
+/// A tag shorter than 256 bits is truncated, like with HMAC.
+impl MAC for Blake3 {
+    fn mac(&self, key: &[u8], text: &[u8], tag_len: usize) -> Result<Vec<u8>, CryptoError> {
+        if key.len() != 32 {
+            return Err(CryptoError::InvalidKeyLength(key.len()));
+        }
+        if tag_len == 0 || tag_len > 256 || tag_len & 7 != 0 {
+            return Err(CryptoError::InvalidTagLength(tag_len));
+        }
+        let mut k = [0u8; 32];
+        k.clone_from_slice(key);
+        Ok(Blake3::keyed(&k).xof(text, tag_len >> 3))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::tests::hex;
+
+    /// The official test vectors, test_vectors.json of the reference implementation.
+    const VECTORS: &str = include_str!("test_vectors/blake3.json");
+
+    /// The string value of a field on a line of the JSON file.
+    fn field<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+        let rest = line.trim().strip_prefix(&format!("\"{}\": ", name))?;
+        Some(rest.trim_end_matches(',').trim_matches('"'))
+    }
+
+    /// The input length and the hash, keyed hash and derived key of every case.
+    fn cases() -> Vec<(usize, [Vec<u8>; 3])> {
+        let lines: Vec<&str> = VECTORS.lines().collect();
+        lines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, line)| {
+                let len = field(line, "input_len")?.parse().unwrap();
+                Some((
+                    len,
+                    [
+                        hex(field(lines[i + 1], "hash")?),
+                        hex(field(lines[i + 2], "keyed_hash")?),
+                        hex(field(lines[i + 3], "derive_key")?),
+                    ],
+                ))
+            })
+            .collect()
+    }
+
+    fn input(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_vectors() {
+        let lines: Vec<&str> = VECTORS.lines().collect();
+        let key_str = lines.iter().find_map(|l| field(l, "key")).unwrap();
+        let context = lines
+            .iter()
+            .find_map(|l| field(l, "context_string"))
+            .unwrap();
+        let mut key = [0u8; 32];
+        key.clone_from_slice(key_str.as_bytes());
+
+        // the longest input has 100 chunks, so the subtrees are hashed in parallel
+        let cases = cases();
+        assert_eq!(cases.len(), 22);
+        let modes = [
+            Blake3::new(),
+            Blake3::keyed(&key),
+            Blake3::derive_key(context),
+        ];
+        for (len, outputs) in cases.iter() {
+            let input = input(*len);
+            for (mode, expected) in modes.iter().zip(outputs.iter()) {
+                assert_eq!(&mode.xof(&input, expected.len()), expected, "{}", len);
+                assert_eq!(mode.hash(input.clone())[..], expected[..32]);
+            }
+        }
+    }
+
+    #[test]
+    fn xof() {
+        let blake3 = Blake3::new();
+        let long = blake3.xof(b"message", 1000);
+        for len in &[0, 1, 31, 64, 65, 999] {
+            assert_eq!(blake3.xof(b"message", *len)[..], long[..*len]);
+        }
+    }
+
+    #[test]
+    fn mac() {
+        let key = [7u8; 32];
+        let tag = Blake3::new().mac(&key, b"message", 256).unwrap();
+        assert_eq!(tag, Blake3::keyed(&key).xof(b"message", 32));
+        assert_eq!(Blake3::new().mac(&key, b"message", 128).unwrap(), tag[..16]);
+        assert!(Blake3::new().verify(&key, b"message", &tag).is_ok());
+        assert!(Blake3::new().verify(&key, b"massage", &tag).is_err());
+        assert_eq!(
+            Blake3::new().mac(&key[..16], b"message", 256),
+            Err(CryptoError::InvalidKeyLength(16))
+        );
+        assert!(Blake3::new().mac(&key, b"message", 260).is_err());
+    }
+}
@@ -0,0 +1,411 @@
+//! The Camellia block cipher, by RFC 3713.
+//! It is a Feistel network like DES, with the FL-functions mixed in every sixth round.
+
+use super::BlockCipher;
+
+const SIGMA: [u64; 6] = [
+    0xA09E667F3BCC908B,
+    0xB67AE8584CAA73B2,
+    0xC6EF372FE94F82BE,
+    0x54FF53A5F1D36F1C,
+    0x10E527FADE682D1D,
+    0xB05688C2B3E6C1FD,
+];
+
+/// The three other S-boxes are rotations of this.
+const SBOX1: [u8; 256] = [
+    112, 130, 44, 236, 179, 39, 192, 229, 228, 133, 87, 53, 234, 12, 174, 65, 35, 239, 107, 147,
+    69, 25, 165, 33, 237, 14, 79, 78, 29, 101, 146, 189, 134, 184, 175, 143, 124, 235, 31, 206, 62,
+    48, 220, 95, 94, 197, 11, 26, 166, 225, 57, 202, 213, 71, 93, 61, 217, 1, 90, 214, 81, 86, 108,
+    77, 139, 13, 154, 102, 251, 204, 176, 45, 116, 18, 43, 32, 240, 177, 132, 153, 223, 76, 203,
+    194, 52, 126, 118, 5, 109, 183, 169, 49, 209, 23, 4, 215, 20, 88, 58, 97, 222, 27, 17, 28, 50,
+    15, 156, 22, 83, 24, 242, 34, 254, 68, 207, 178, 195, 181, 122, 145, 36, 8, 232, 168, 96, 252,
+    105, 80, 170, 208, 160, 125, 161, 137, 98, 151, 84, 91, 30, 149, 224, 255, 100, 210, 16, 196,
+    0, 72, 163, 247, 117, 219, 138, 3, 230, 218, 9, 63, 221, 148, 135, 92, 131, 2, 205, 74, 144,
+    51, 115, 103, 246, 243, 157, 127, 191, 226, 82, 155, 216, 38, 200, 55, 198, 59, 129, 150, 111,
+    75, 19, 190, 99, 46, 233, 121, 167, 140, 159, 110, 188, 142, 41, 245, 249, 182, 47, 253, 180,
+    89, 120, 152, 6, 106, 231, 70, 113, 186, 212, 37, 171, 66, 136, 162, 141, 250, 114, 7, 185, 85,
+    248, 238, 172, 10, 54, 73, 42, 104, 60, 56, 241, 164, 64, 40, 211, 123, 187, 201, 67, 193, 21,
+    227, 173, 244, 119, 199, 128, 158,
+];
+
+fn sbox2(x: u8) -> u8 {
+    SBOX1[x as usize].rotate_left(1)
+}
+
+fn sbox3(x: u8) -> u8 {
+    SBOX1[x as usize].rotate_left(7)
+}
+
+fn sbox4(x: u8) -> u8 {
+    SBOX1[x.rotate_left(1) as usize]
+}
+
+pub enum CamelliaKey {
+    CK128([u8; 16]),
+    CK192([u8; 24]),
+    CK256([u8; 32]),
+}
+
+impl CamelliaKey {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            CamelliaKey::CK128(key) => &key[..],
+            CamelliaKey::CK192(key) => &key[..],
+            CamelliaKey::CK256(key) => &key[..],
+        }
+    }
+}
+
+impl<'a> std::convert::TryFrom<super::CipherKey<'a>> for CamelliaKey {
+    type Error = super::CryptoError;
+
+    fn try_from(key: super::CipherKey) -> Result<CamelliaKey, Self::Error> {
+        match key.key.len() {
+            16 => {
+                let mut key_arr = [0u8; 16];
+                key_arr[..].clone_from_slice(key.key);
+                Ok(CamelliaKey::CK128(key_arr))
+            }
+            24 => {
+                let mut key_arr = [0u8; 24];
+                key_arr[..].clone_from_slice(key.key);
+                Ok(CamelliaKey::CK192(key_arr))
+            }
+            32 => {
+                let mut key_arr = [0u8; 32];
+                key_arr[..].clone_from_slice(key.key);
+                Ok(CamelliaKey::CK256(key_arr))
+            }
+            _ => Err(super::CryptoError::InvalidKeyLength(key.key.len())),
+        }
+    }
+}
+
+/// The subkeys, in the order they are used when encrypting.
+struct SubKeys {
+    /// the whitening keys, kw1 to kw4.
+    kw: [u64; 4],
+    /// the round keys, 18 for a 128 bit key and 24 otherwise.
+    k: Vec<u64>,
+    /// the keys of the FL-layers, two for every layer.
+    ke: Vec<u64>,
+}
+
+impl SubKeys {
+    /// Decryption is the same as encryption, with the subkeys in reverse order.
+    fn reversed(&self) -> SubKeys {
+        SubKeys {
+            kw: [self.kw[2], self.kw[3], self.kw[0], self.kw[1]],
+            k: self.k.iter().rev().copied().collect(),
+            ke: self.ke.iter().rev().copied().collect(),
+        }
+    }
+}
+
+pub struct Camellia {
+    subkeys: SubKeys,
+    inverse: SubKeys,
+    mode: super::EncryptionMode,
+    padding: super::Padding,
+    key: CamelliaKey,
+}
+
+impl Camellia {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(key: CamelliaKey) -> Box<dyn BlockCipher> {
+        let subkeys = expand_key(key.bytes());
+        Box::new(Camellia {
+            inverse: subkeys.reversed(),
+            subkeys,
+            mode: super::EncryptionMode::CBC,
+            padding: super::Padding::default(),
+            key,
+        })
+    }
+}
+
+fn f(input: u64, key: u64) -> u64 {
+    let t = (input ^ key).to_be_bytes();
+    let t = [
+        SBOX1[t[0] as usize],
+        sbox2(t[1]),
+        sbox3(t[2]),
+        sbox4(t[3]),
+        sbox2(t[4]),
+        sbox3(t[5]),
+        sbox4(t[6]),
+        SBOX1[t[7] as usize],
+    ];
+    u64::from_be_bytes([
+        t[0] ^ t[2] ^ t[3] ^ t[5] ^ t[6] ^ t[7],
+        t[0] ^ t[1] ^ t[3] ^ t[4] ^ t[6] ^ t[7],
+        t[0] ^ t[1] ^ t[2] ^ t[4] ^ t[5] ^ t[7],
+        t[1] ^ t[2] ^ t[3] ^ t[4] ^ t[5] ^ t[6],
+        t[0] ^ t[1] ^ t[5] ^ t[6] ^ t[7],
+        t[1] ^ t[2] ^ t[4] ^ t[6] ^ t[7],
+        t[2] ^ t[3] ^ t[4] ^ t[5] ^ t[7],
+        t[0] ^ t[3] ^ t[4] ^ t[5] ^ t[6],
+    ])
+}
+
+fn fl(input: u64, key: u64) -> u64 {
+    let (mut x1, mut x2) = ((input >> 32) as u32, input as u32);
+    let (k1, k2) = ((key >> 32) as u32, key as u32);
+    x2 ^= (x1 & k1).rotate_left(1);
+    x1 ^= x2 | k2;
+    ((x1 as u64) << 32) | x2 as u64
+}
+
+fn fl_inv(input: u64, key: u64) -> u64 {
+    let (mut y1, mut y2) = ((input >> 32) as u32, input as u32);
+    let (k1, k2) = ((key >> 32) as u32, key as u32);
+    y1 ^= y2 | k2;
+    y2 ^= (y1 & k1).rotate_left(1);
+    ((y1 as u64) << 32) | y2 as u64
+}
+
+/// Split the key rotated left into the two halves.
+fn halves(key: u128, rotation: u32) -> [u64; 2] {
+    let r = key.rotate_left(rotation);
+    [(r >> 64) as u64, r as u64]
+}
+
+/// The whitening keys are the first key, and the last key rotated 111 bits.
+fn whitening(first: u128, last: u128) -> [u64; 4] {
+    let [kw1, kw2] = halves(first, 0);
+    let [kw3, kw4] = halves(last, 111);
+    [kw1, kw2, kw3, kw4]
+}
+
+fn expand_key(key: &[u8]) -> SubKeys {
+    let mut kl = [0u8; 16];
+    let mut kr = [0u8; 16];
+    kl.clone_from_slice(&key[..16]);
+    match key.len() {
+        24 => {
+            kr[..8].clone_from_slice(&key[16..]);
+            for i in 0..8 {
+                kr[i + 8] = !key[16 + i];
+            }
+        }
+        32 => kr.clone_from_slice(&key[16..]),
+        _ => {}
+    }
+    let kl = u128::from_be_bytes(kl);
+    let kr = u128::from_be_bytes(kr);
+
+    let mut d1 = ((kl ^ kr) >> 64) as u64;
+    let mut d2 = (kl ^ kr) as u64;
+    d2 ^= f(d1, SIGMA[0]);
+    d1 ^= f(d2, SIGMA[1]);
+    d1 ^= (kl >> 64) as u64;
+    d2 ^= kl as u64;
+    d2 ^= f(d1, SIGMA[2]);
+    d1 ^= f(d2, SIGMA[3]);
+    let ka = ((d1 as u128) << 64) | d2 as u128;
+
+    if key.len() == 16 {
+        let k = [
+            halves(ka, 0),
+            halves(kl, 15),
+            halves(ka, 15),
+            halves(kl, 45),
+            [halves(ka, 45)[0], halves(kl, 60)[1]],
+            halves(ka, 60),
+            halves(kl, 94),
+            halves(ka, 94),
+            halves(kl, 111),
+        ];
+        let ke = [halves(ka, 30), halves(kl, 77)];
+        return SubKeys {
+            kw: whitening(kl, ka),
+            k: k.concat(),
+            ke: ke.concat(),
+        };
+    }
+
+    let mut d1 = ((ka ^ kr) >> 64) as u64;
+    let mut d2 = (ka ^ kr) as u64;
+    d2 ^= f(d1, SIGMA[4]);
+    d1 ^= f(d2, SIGMA[5]);
+    let kb = ((d1 as u128) << 64) | d2 as u128;
+
+    let k = [
+        halves(kb, 0),
+        halves(kr, 15),
+        halves(ka, 15),
+        halves(kb, 30),
+        halves(kl, 45),
+        halves(ka, 45),
+        halves(kr, 60),
+        halves(kb, 60),
+        halves(kl, 77),
+        halves(kr, 94),
+        halves(ka, 94),
+        halves(kl, 111),
+    ];
+    let ke = [halves(kr, 30), halves(kl, 60), halves(ka, 77)];
+    SubKeys {
+        kw: whitening(kl, kb),
+        k: k.concat(),
+        ke: ke.concat(),
+    }
+}
+
+impl Camellia {
+    /// The Feistel network, with the subkeys in the order given.
+    fn crypt(&self, block: &mut [u8], kw: [u64; 4], k: &[u64], ke: &[u64]) {
+        let mut b = [0u8; 16];
+        b.clone_from_slice(block);
+        let m = u128::from_be_bytes(b);
+        let mut d1 = (m >> 64) as u64 ^ kw[0];
+        let mut d2 = m as u64 ^ kw[1];
+
+        for (i, pair) in k.chunks(2).enumerate() {
+            // an FL-layer after every six rounds
+            if i > 0 && i % 3 == 0 {
+                d1 = fl(d1, ke[(i / 3 - 1) * 2]);
+                d2 = fl_inv(d2, ke[(i / 3 - 1) * 2 + 1]);
+            }
+            d2 ^= f(d1, pair[0]);
+            d1 ^= f(d2, pair[1]);
+        }
+
+        d2 ^= kw[2];
+        d1 ^= kw[3];
+        let c = ((d2 as u128) << 64) | d1 as u128;
+        block.clone_from_slice(&c.to_be_bytes());
+    }
+}
+
+impl BlockCipher for Camellia {
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let s = &self.subkeys;
+        self.crypt(block, s.kw, &s.k, &s.ke);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let s = &self.inverse;
+        self.crypt(block, s.kw, &s.k, &s.ke);
+    }
+
+    fn block_size(&self) -> usize {
+        16
+    }
+
+    fn change_encryption_mode(&mut self, mode: super::EncryptionMode) {
+        self.mode = mode;
+    }
+
+    fn encryption_mode(&self) -> super::EncryptionMode {
+        self.mode
+    }
+
+    fn change_padding(&mut self, padding: super::Padding) {
+        self.padding = padding;
+    }
+
+    fn padding(&self) -> super::Padding {
+        self.padding
+    }
+
+    fn get_key(&self) -> &[u8] {
+        self.key.bytes()
+    }
+
+    fn set_key(&mut self, key: &[u8]) -> Result<(), super::CryptoError> {
+        use std::convert::TryFrom;
+        self.key = CamelliaKey::try_from(super::CipherKey { key })?;
+        self.subkeys = expand_key(key);
+        self.inverse = self.subkeys.reversed();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::tests::hex;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn rfc3713() {
+        // appendix A
+        let plaintext = hex("0123456789abcdeffedcba9876543210");
+        let vectors = [
+            (
+                "0123456789abcdeffedcba9876543210",
+                "67673138549669730857065648eabe43",
+            ),
+            (
+                "0123456789abcdeffedcba98765432100011223344556677",
+                "b4993401b3e996f84ee5cee7d79b09b9",
+            ),
+            (
+                "0123456789abcdeffedcba987654321000112233445566778899aabbccddeeff",
+                "9acc237dff16d76c20ef7c919e3a7509",
+            ),
+        ];
+
+        for (key, ciphertext) in vectors.iter() {
+            let key = hex(key);
+            let camellia = Camellia::new(
+                CamelliaKey::try_from(crate::crypt::CipherKey { key: &key }).unwrap(),
+            );
+            let mut block = plaintext.clone();
+            camellia.encrypt_block(&mut block);
+            assert_eq!(block, hex(ciphertext));
+            camellia.decrypt_block(&mut block);
+            assert_eq!(block, plaintext);
+        }
+    }
+
+    #[test]
+    fn keys() {
+        let mut camellia = Camellia::new(CamelliaKey::CK128([0; 16]));
+        assert!(camellia.set_key(&[0u8; 20]).is_err());
+        camellia
+            .set_key(&hex("0123456789abcdeffedcba9876543210"))
+            .unwrap();
+        let mut block = hex("0123456789abcdeffedcba9876543210");
+        camellia.encrypt_block(&mut block);
+        assert_eq!(block, hex("67673138549669730857065648eabe43"));
+    }
+
+    #[test]
+    fn ecb() {
+        use crate::crypt::{Cipher, EncryptionMode};
+
+        let mut camellia = Camellia::new(CamelliaKey::CK128([7; 16]));
+        camellia.change_encryption_mode(EncryptionMode::ECB);
+        assert!(matches!(camellia.encryption_mode(), EncryptionMode::ECB));
+
+        // the same block encrypts to the same block, unlike CBC
+        let mut data = vec![0x42u8; 32];
+        camellia.encrypt(&[], &mut data).unwrap();
+        assert_eq!(data.len(), 48);
+        assert_eq!(data[..16], data[16..32]);
+        let mut block = [0x42u8; 16];
+        camellia.encrypt_block(&mut block);
+        assert_eq!(data[..16], block[..]);
+
+        camellia.decrypt(&[], &mut data).unwrap();
+        assert_eq!(data, vec![0x42u8; 32]);
+    }
+
+    #[test]
+    fn cmac() {
+        use crate::crypt::mac::{CMAC, MAC};
+        use crate::crypt::CipherTypes;
+
+        // checked with OpenSSL
+        let key = hex("0123456789abcdeffedcba9876543210");
+        let text = b"Camellia in CMAC and more than a block";
+        let cmac = CMAC::new(CipherTypes::Camellia);
+        assert_eq!(
+            cmac.mac(&key, text, 128).unwrap(),
+            hex("f7eda4f7aa6cb373f46cfba23918ad31")
+        );
+    }
+}
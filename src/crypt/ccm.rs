@@ -0,0 +1,305 @@
+//! Counter with CBC-MAC.
+//! The tag is a CBC-MAC of the nonce, lengths, associated data and plaintext,
+//! and the plaintext and tag are encrypted in counter mode.
+//!
+//!     # use codes::crypt::{aes::{AES, AESKey}, ccm::CCM, AEAD};
+//!     // 64 bit tags and 3 bytes for the message length, so the nonce is 12 bytes
+//!     let ccm = CCM::new(AES::new(AESKey::AES128([0x42; 16])), 64, 3).unwrap();
+//!     let nonce = [7u8; 12];
+//!
+//!     let mut message = b"attack at dawn".to_vec();
+//!     ccm.encrypt(&nonce, b"header", &mut message).unwrap();
+//!     assert_eq!(message.len(), 14 + 8);
+//!
+//!     ccm.decrypt(&nonce, b"header", &mut message).unwrap();
+//!     assert_eq!(message, b"attack at dawn");
+
+use super::{verify_tag, BlockCipher, CryptoError, AEAD};
+
+pub struct CCM {
+    cipher: Box<dyn BlockCipher>,
+    /// in bytes
+    tag_len: usize,
+    /// the number of bytes in the length field
+    q: usize,
+}
+
+impl CCM {
+    /// Create a CCM instance of a 128 bit block cipher.
+    ///
+    ///  - tag_len is the length of the tag in bits, one of 32, 48, ..., 128
+    ///  - length_size is the number of bytes used to encode the message length, from 2 to 8.
+    ///    The nonce is then 15 - length_size bytes.
+    pub fn new(
+        cipher: Box<dyn BlockCipher>,
+        tag_len: usize,
+        length_size: usize,
+    ) -> Result<Self, CryptoError> {
+        if cipher.block_size() != 16 {
+            return Err(CryptoError::InvalidParameter(format!(
+                "CCM needs a 128 bit block cipher, not {} bits",
+                cipher.block_size() << 3
+            )));
+        }
+        if !(32..=128).contains(&tag_len) || tag_len & 15 != 0 {
+            return Err(CryptoError::InvalidTagLength(tag_len));
+        }
+        if !(2..=8).contains(&length_size) {
+            return Err(CryptoError::InvalidParameter(format!(
+                "{} is not a valid CCM length field size",
+                length_size
+            )));
+        }
+
+        Ok(CCM {
+            cipher,
+            tag_len: tag_len >> 3,
+            q: length_size,
+        })
+    }
+
+    /// The length of the nonce in bytes.
+    pub fn nonce_len(&self) -> usize {
+        15 - self.q
+    }
+
+    fn check(&self, nonce: &[u8], len: usize) -> Result<(), CryptoError> {
+        if nonce.len() != self.nonce_len() {
+            return Err(CryptoError::InvalidIvLength {
+                expected: self.nonce_len(),
+                got: nonce.len(),
+            });
+        }
+        if self.q < 8 && len >> (self.q << 3) != 0 {
+            return Err(CryptoError::InvalidDataLength(len));
+        }
+        Ok(())
+    }
+
+    /// The counter block number i.
+    fn counter(&self, nonce: &[u8], i: usize) -> [u8; 16] {
+        let mut block = [0u8; 16];
+        block[0] = (self.q - 1) as u8;
+        block[1..(16 - self.q)].clone_from_slice(nonce);
+        block[(16 - self.q)..].clone_from_slice(&(i as u64).to_be_bytes()[(8 - self.q)..]);
+        block
+    }
+
+    /// The unencrypted tag, CBC-MAC of the formatted input.
+    fn cbc_mac(&self, nonce: &[u8], ad: &[u8], plaintext: &[u8]) -> [u8; 16] {
+        let mut y = [0u8; 16];
+
+        // xor the data into the state, padded with zeros to whole blocks
+        let absorb = |y: &mut [u8; 16], data: &[u8]| {
+            for chunk in data.chunks(16) {
+                for (y, d) in y.iter_mut().zip(chunk.iter()) {
+                    *y ^= d;
+                }
+                self.cipher.encrypt_block(y);
+            }
+        };
+
+        let mut b0 = self.counter(nonce, plaintext.len());
+        b0[0] |= (((self.tag_len - 2) / 2) << 3) as u8;
+        if !ad.is_empty() {
+            b0[0] |= 1 << 6;
+        }
+        absorb(&mut y, &b0);
+
+        if !ad.is_empty() {
+            let mut encoded = if ad.len() < 0xff00 {
+                (ad.len() as u16).to_be_bytes().to_vec()
+            } else if ad.len() >> 32 == 0 {
+                [&[0xff, 0xfe], &(ad.len() as u32).to_be_bytes()[..]].concat()
+            } else {
+                [&[0xff, 0xff], &(ad.len() as u64).to_be_bytes()[..]].concat()
+            };
+            encoded.extend_from_slice(ad);
+            absorb(&mut y, &encoded);
+        }
+
+        absorb(&mut y, plaintext);
+
+        y
+    }
+
+    /// Encrypt the tag with the first counter block.
+    fn encrypt_tag(&self, nonce: &[u8], tag: &mut [u8; 16]) {
+        let mut s0 = self.counter(nonce, 0);
+        self.cipher.encrypt_block(&mut s0);
+        for (t, s) in tag.iter_mut().zip(s0.iter()) {
+            *t ^= s;
+        }
+    }
+}
+
+impl AEAD for CCM {
+    fn encrypt(&self, nonce: &[u8], ad: &[u8], plaintext: &mut Vec<u8>) -> Result<(), CryptoError> {
+        self.check(nonce, plaintext.len())?;
+
+        let mut tag = self.cbc_mac(nonce, ad, plaintext);
+        self.encrypt_tag(nonce, &mut tag);
+
+        self.cipher.ctr(&self.counter(nonce, 1), plaintext);
+        plaintext.extend_from_slice(&tag[..self.tag_len]);
+
+        Ok(())
+    }
+
+    fn decrypt(
+        &self,
+        nonce: &[u8],
+        ad: &[u8],
+        ciphertext: &mut Vec<u8>,
+    ) -> Result<(), CryptoError> {
+        if ciphertext.len() < self.tag_len {
+            return Err(CryptoError::InvalidDataLength(ciphertext.len()));
+        }
+        let len = ciphertext.len() - self.tag_len;
+        self.check(nonce, len)?;
+
+        // the plaintext is needed to compute the tag
+        let counter = self.counter(nonce, 1);
+        self.cipher.ctr(&counter, &mut ciphertext[..len]);
+
+        let mut tag = self.cbc_mac(nonce, ad, &ciphertext[..len]);
+        self.encrypt_tag(nonce, &mut tag);
+
+        if let Err(e) = verify_tag(&tag[..self.tag_len], &ciphertext[len..]) {
+            // do not release the unauthenticated plaintext
+            self.cipher.ctr(&counter, &mut ciphertext[..len]);
+            return Err(e);
+        }
+
+        ciphertext.truncate(len);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::aes::{AESKey, AES};
+    use crate::crypt::tests::hex;
+
+    fn aes() -> Box<dyn BlockCipher> {
+        AES::new(AESKey::AES128([
+            0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x4b, 0x4c, 0x4d,
+            0x4e, 0x4f,
+        ]))
+    }
+
+    fn check(tag_len: usize, nonce: &[u8], ad: &[u8], plaintext: &[u8], expected: &str) {
+        let ccm = CCM::new(aes(), tag_len, 15 - nonce.len()).unwrap();
+        let mut data = plaintext.to_vec();
+        ccm.encrypt(nonce, ad, &mut data).unwrap();
+        assert_eq!(data, hex(expected));
+        ccm.decrypt(nonce, ad, &mut data).unwrap();
+        assert_eq!(data, plaintext);
+    }
+
+    #[test]
+    fn sp800_38c_examples() {
+        // Appendix C of SP 800-38C
+        check(
+            32,
+            &hex("10111213141516"),
+            &hex("0001020304050607"),
+            &hex("20212223"),
+            "7162015b4dac255d",
+        );
+        check(
+            48,
+            &hex("1011121314151617"),
+            &hex("000102030405060708090a0b0c0d0e0f"),
+            &hex("202122232425262728292a2b2c2d2e2f"),
+            "d2a1f0e051ea5f62081a7792073d593d1fc64fbfaccd",
+        );
+        check(
+            64,
+            &hex("101112131415161718191a1b"),
+            &hex("000102030405060708090a0b0c0d0e0f10111213"),
+            &hex("202122232425262728292a2b2c2d2e2f3031323334353637"),
+            "e3b201a9f5b71a7a9b1ceaeccd97e70b6176aad9a4428aa5484392fbc1b09951",
+        );
+
+        // 2^16 bytes of associated data, which needs the longer length encoding
+        let ad: Vec<u8> = (0..0x10000).map(|i| i as u8).collect();
+        check(
+            112,
+            &hex("101112131415161718191a1b1c"),
+            &ad,
+            &hex("202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f"),
+            "69915dad1e84c6376a68c2967e4dab615ae0fd1faec44cc484828529463ccf72
+             b4ac6bec93e8598e7f0dadbcea5b",
+        );
+    }
+
+    #[test]
+    fn tampering() {
+        let ccm = CCM::new(aes(), 128, 2).unwrap();
+        let nonce = [3u8; 13];
+        let mut sealed = b"Lorem ipsum dolor sit amet".to_vec();
+        ccm.encrypt(&nonce, b"ad", &mut sealed).unwrap();
+
+        for i in 0..sealed.len() {
+            let mut tampered = sealed.clone();
+            tampered[i] ^= 0x01;
+            assert_eq!(
+                ccm.decrypt(&nonce, b"ad", &mut tampered),
+                Err(CryptoError::AuthenticationFailure)
+            );
+            // the ciphertext is left as it was
+            tampered[i] ^= 0x01;
+            assert_eq!(tampered, sealed);
+        }
+
+        let mut other = [3u8; 13];
+        other[12] = 4;
+        let mut tampered = sealed.clone();
+        assert_eq!(
+            ccm.decrypt(&other, b"ad", &mut tampered),
+            Err(CryptoError::AuthenticationFailure)
+        );
+        assert_eq!(
+            ccm.decrypt(&nonce, b"AD", &mut tampered),
+            Err(CryptoError::AuthenticationFailure)
+        );
+        assert_eq!(
+            ccm.decrypt(&nonce, b"ad", &mut tampered[..20].to_vec()),
+            Err(CryptoError::AuthenticationFailure)
+        );
+    }
+
+    #[test]
+    fn invalid_parameters() {
+        assert_eq!(
+            CCM::new(aes(), 40, 2).err(),
+            Some(CryptoError::InvalidTagLength(40))
+        );
+        assert_eq!(
+            CCM::new(aes(), 144, 2).err(),
+            Some(CryptoError::InvalidTagLength(144))
+        );
+        assert!(CCM::new(aes(), 128, 1).is_err());
+        assert!(CCM::new(aes(), 128, 9).is_err());
+
+        let ccm = CCM::new(aes(), 64, 2).unwrap();
+        assert_eq!(
+            ccm.encrypt(&[0; 12], &[], &mut vec![0; 4]),
+            Err(CryptoError::InvalidIvLength {
+                expected: 13,
+                got: 12
+            })
+        );
+        // the message length does not fit in two bytes
+        assert_eq!(
+            ccm.encrypt(&[0; 13], &[], &mut vec![0; 0x10000]),
+            Err(CryptoError::InvalidDataLength(0x10000))
+        );
+        assert_eq!(
+            ccm.decrypt(&[0; 13], &[], &mut vec![0; 7]),
+            Err(CryptoError::InvalidDataLength(7))
+        );
+    }
+}
@@ -1,9 +1,10 @@
 //! The ChaCha20 cihper, based on the Salsa20 cipher.
 //! This is a stream cipher.
+//! The reduced round variants ChaCha12 and ChaCha8 are faster, but with a smaller security margin.
 
 use rayon::prelude::*;
 
-use super::Cipher;
+use super::{Cipher, CryptoError};
 
 macro_rules! min {
     ($a:expr, $b:expr) => {{
@@ -86,6 +87,20 @@ fn printblock(block: &[u32; 16]) {
     }
 }
 
+/// Do the rounds, two at a time.
+fn double_rounds(state: &mut [u32; 16], rounds: usize) {
+    for _ in 0..(rounds >> 1) {
+        quarter_round!(state, 0, 4, 8, 12);
+        quarter_round!(state, 1, 5, 9, 13);
+        quarter_round!(state, 2, 6, 10, 14);
+        quarter_round!(state, 3, 7, 11, 15);
+        quarter_round!(state, 0, 5, 10, 15);
+        quarter_round!(state, 1, 6, 11, 12);
+        quarter_round!(state, 2, 7, 8, 13);
+        quarter_round!(state, 3, 4, 9, 14);
+    }
+}
+
 fn hchacha(key: &[u8; 32], nonce: &[u8; 16]) -> [u8; 32] {
     let mut n = [0u8; 8];
     for (b, i) in n.iter_mut().zip(nonce[8..16].iter()) {
@@ -101,16 +116,7 @@ fn hchacha(key: &[u8; 32], nonce: &[u8; 16]) -> [u8; 32] {
         block[i + 12] = u32::from_le_bytes(tmp);
     }
 
-    for _ in 0..10 {
-        quarter_round!(block, 0, 4, 8, 12);
-        quarter_round!(block, 1, 5, 9, 13);
-        quarter_round!(block, 2, 6, 10, 14);
-        quarter_round!(block, 3, 7, 11, 15);
-        quarter_round!(block, 0, 5, 10, 15);
-        quarter_round!(block, 1, 6, 11, 12);
-        quarter_round!(block, 2, 7, 8, 13);
-        quarter_round!(block, 3, 4, 9, 14);
-    }
+    double_rounds(&mut block, 20);
 
     let mut res = [0u8; 32];
 
@@ -137,23 +143,18 @@ fn hchacha(key: &[u8; 32], nonce: &[u8; 16]) -> [u8; 32] {
 /// the first argument instead of putting the result in a box. This is to save on heap allocations.
 /// The in-block should be initialized, except for the counter.
 pub fn chacha20_block(in_block: &[u32; 16], out_block: &mut [u8], counter: &[u32; 2]) {
+    chacha_block(in_block, out_block, counter, 20)
+}
+
+/// The same as `chacha20_block`, with another (even) number of rounds.
+pub fn chacha_block(in_block: &[u32; 16], out_block: &mut [u8], counter: &[u32; 2], rounds: usize) {
     let mut state = *in_block;
     state[12..14].clone_from_slice(&counter[..]);
 
     // The init state is a copy of state, not a pointer to the same location.
     let mut init_state = state;
 
-    // do the 20 rounds (10 double rounds)
-    for _ in 0..10 {
-        quarter_round!(init_state, 0, 4, 8, 12);
-        quarter_round!(init_state, 1, 5, 9, 13);
-        quarter_round!(init_state, 2, 6, 10, 14);
-        quarter_round!(init_state, 3, 7, 11, 15);
-        quarter_round!(init_state, 0, 5, 10, 15);
-        quarter_round!(init_state, 1, 6, 11, 12);
-        quarter_round!(init_state, 2, 7, 8, 13);
-        quarter_round!(init_state, 3, 4, 9, 14);
-    }
+    double_rounds(&mut init_state, rounds);
 
     debug_assert_ne!(init_state, state);
 
@@ -175,6 +176,167 @@ pub fn chacha20_block(in_block: &[u32; 16], out_block: &mut [u8], counter: &[u32
     }
 }
 
+/// How the last four words of the state are split between the block counter and the nonce.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Layout {
+    /// A 64 bit counter and a 64 bit nonce, as in the original ChaCha.
+    /// Earlier versions put the high word of the counter first, so the keystreams of this
+    /// layout and of `XChaCha20` do not match what they encrypted.
+    Original,
+    /// A 32 bit counter and a 96 bit nonce, as in RFC 8439.
+    IETF,
+}
+
+impl Layout {
+    /// The length of the nonce in bytes.
+    pub fn nonce_len(self) -> usize {
+        match self {
+            Layout::Original => 8,
+            Layout::IETF => 12,
+        }
+    }
+
+    /// The largest block counter.
+    fn max_counter(self) -> u64 {
+        match self {
+            Layout::Original => u64::MAX,
+            Layout::IETF => u32::MAX as u64,
+        }
+    }
+
+    /// Put the nonce in the state.
+    fn initialize_block(self, key: &[u8; 32], nonce: &[u8]) -> [u32; 16] {
+        let mut n = [0u8; 8];
+        match self {
+            Layout::Original => {
+                n.clone_from_slice(nonce);
+                initialize_block(key, &n)
+            }
+            Layout::IETF => {
+                n.clone_from_slice(&nonce[4..12]);
+                let mut block = initialize_block(key, &n);
+                let mut tmp = [0u8; 4];
+                tmp.clone_from_slice(&nonce[..4]);
+                block[13] = u32::from_le_bytes(tmp);
+                block
+            }
+        }
+    }
+
+    /// convert the block number to the two u32 of the counter, the low word first.
+    /// With the IETF layout the second word is the start of the nonce.
+    fn counter(self, block: &[u32; 16], c: u64) -> [u32; 2] {
+        match self {
+            Layout::Original => [c as u32, (c >> 32) as u32],
+            Layout::IETF => [c as u32, block[13]],
+        }
+    }
+}
+
+/// Xor the keystream into the data, starting at block number `first` and a byte offset in it.
+/// The whole blocks are done in parallel.
+fn apply_keystream(
+    block: &[u32; 16],
+    rounds: usize,
+    layout: Layout,
+    first: u64,
+    skip: usize,
+    data: &mut [u8],
+) {
+    let mut first = first;
+
+    // finish the block the offset is in the middle of
+    let head = if skip == 0 {
+        0
+    } else {
+        min!(64 - skip, data.len())
+    };
+    let (head, rest) = data.split_at_mut(head);
+    if !head.is_empty() {
+        let mut keystream = [0u8; 64];
+        chacha_block(block, &mut keystream, &layout.counter(block, first), rounds);
+        for (d, k) in head.iter_mut().zip(keystream[skip..].iter()) {
+            *d ^= k;
+        }
+        first = first.wrapping_add(1);
+    }
+
+    // slice the rest up into blocks
+    // and enumerate them. Then parallellize the operation
+    // and encrypt/decrypt the block.
+    rest.chunks_mut(64)
+        .enumerate()
+        .collect::<Vec<(usize, &mut [u8])>>()
+        .into_par_iter()
+        .for_each(|(n, mut plain_block)| {
+            let c = first.wrapping_add(n as u64);
+            chacha_block(block, &mut plain_block, &layout.counter(block, c), rounds)
+        });
+}
+
+/// The keystream of a single nonce.
+/// Every call to `apply` continues where the last one ended, so a message can be encrypted in
+/// parts. Any part of the keystream can also be used directly with `apply_keystream_at`.
+pub struct KeyStream {
+    /// the initial state, except for the counter.
+    block: [u32; 16],
+    rounds: usize,
+    layout: Layout,
+    /// the block counter at the start of the keystream.
+    initial: u64,
+    /// the number of bytes of the keystream used.
+    offset: u64,
+}
+
+impl KeyStream {
+    fn new(block: [u32; 16], rounds: usize, layout: Layout, initial: u64) -> Self {
+        KeyStream {
+            block,
+            rounds,
+            layout,
+            initial,
+            offset: 0,
+        }
+    }
+
+    /// Encrypt or decrypt the next part of the message.
+    ///
+    /// If the keystream runs out, which can only happen with the 32 bit counter of the
+    /// IETF layout after 256 GiB, the data is left as it is and an error is returned.
+    pub fn apply(&mut self, data: &mut [u8]) -> Result<(), CryptoError> {
+        self.apply_keystream_at(self.offset, data)?;
+        self.offset += data.len() as u64;
+        Ok(())
+    }
+
+    /// Encrypt or decrypt the data at a byte offset in the message.
+    /// This does not change where `apply` continues.
+    pub fn apply_keystream_at(&self, offset: u64, data: &mut [u8]) -> Result<(), CryptoError> {
+        let first = self.initial.checked_add(offset >> 6);
+        let last = offset
+            .checked_add(data.len() as u64)
+            .and_then(|end| self.initial.checked_add(end.saturating_sub(1) >> 6));
+        match (first, last) {
+            (Some(first), Some(last)) if last <= self.layout.max_counter() => {
+                let skip = (offset & 63) as usize;
+                apply_keystream(&self.block, self.rounds, self.layout, first, skip, data);
+                Ok(())
+            }
+            _ => Err(CryptoError::InvalidDataLength(data.len())),
+        }
+    }
+
+    /// Move to a byte offset in the message, so that `apply` continues from there.
+    pub fn seek(&mut self, offset: u64) {
+        self.offset = offset;
+    }
+
+    /// The byte offset where `apply` continues.
+    pub fn position(&self) -> u64 {
+        self.offset
+    }
+}
+
 pub struct XChaCha20 {
     key: Box<[u8; 32]>,
 }
@@ -187,12 +349,14 @@ impl XChaCha20 {
     }
 }
 
-impl Cipher<&mut [u8]> for XChaCha20 {
-    /// Encrypt some text with the ChaCha20 stream cipher.
-    /// The nonce has to be unique for every encryption.
-    fn encrypt(&self, nonce: &[u8], plaintext: &mut [u8]) -> Result<(), String> {
+impl XChaCha20 {
+    /// The keystream of the nonce, for encrypting a message in several parts.
+    pub fn keystream(&self, nonce: &[u8]) -> Result<KeyStream, CryptoError> {
         if nonce.len() != 24 {
-            return Err(format!("nonce len is {} but should be 12.", nonce.len()));
+            return Err(CryptoError::InvalidIvLength {
+                expected: 24,
+                got: nonce.len(),
+            });
         }
         // convert the nonce to an array instead of a slice
         // the input should maybe be an array, but oh well.
@@ -208,92 +372,132 @@ impl Cipher<&mut [u8]> for XChaCha20 {
 
         let new_key = hchacha(&self.key, &hn);
 
-        // the initial state of the cipher,
-        // only missing the counter.
-        // This is shared for all threads encrypting
-        let block = initialize_block(&new_key, &n);
-
-        // slice the plaintext up into blocks
-        // and enumerate them. Then parallellize the operation
-        // and encrypt/decrypt the block.
-        plaintext
-            .chunks_mut(64)
-            .enumerate()
-            .collect::<Vec<(usize, &mut [u8])>>()
-            .into_par_iter()
-            .for_each(|(n, mut plain_block)| {
-                // start counting from 1
-                let c = n + 1;
-                // convert the usize counter to two u32.
-                let counter = [(c >> 32) as u32, (c & ((1 << 32) - 1)) as u32];
-                // encrypt the block
-                chacha20_block(&block, &mut plain_block, &counter)
-            });
+        Ok(KeyStream::new(
+            initialize_block(&new_key, &n),
+            20,
+            Layout::Original,
+            1,
+        ))
+    }
+}
 
-        Ok(())
+impl Cipher<&mut [u8]> for XChaCha20 {
+    /// Encrypt some text with the ChaCha20 stream cipher.
+    /// The nonce has to be unique for every encryption.
+    fn encrypt(&self, nonce: &[u8], plaintext: &mut [u8]) -> Result<(), CryptoError> {
+        self.keystream(nonce)?.apply(plaintext)
     }
 
     /// Decrypt something encrypted with ChaCha20.
     /// This is the same as encrypting it, so no worries
-    fn decrypt(&self, nonce: &[u8], ciphertext: &mut [u8]) -> Result<(), String> {
+    fn decrypt(&self, nonce: &[u8], ciphertext: &mut [u8]) -> Result<(), CryptoError> {
         self.encrypt(nonce, ciphertext)
     }
 }
 
-pub struct ChaCha20 {
+/// ChaCha with `ROUNDS` rounds, use one of `ChaCha20`, `ChaCha12` or `ChaCha8`.
+/// The rounds are done two at a time, so another number has to be positive and even,
+/// which is checked when the code is compiled.
+///
+/// ```compile_fail
+/// # use codes::crypt::chacha20::ChaCha;
+/// let chacha = ChaCha::<7>::new(&[0; 32]);
+/// ```
+pub struct ChaCha<const ROUNDS: usize> {
     key: Box<[u8; 32]>,
+    layout: Layout,
 }
 
-impl ChaCha20 {
+pub type ChaCha20 = ChaCha<20>;
+pub type ChaCha12 = ChaCha<12>;
+pub type ChaCha8 = ChaCha<8>;
+
+impl<const ROUNDS: usize> ChaCha<ROUNDS> {
+    const VALID_ROUNDS: () = assert!(
+        ROUNDS > 0 && ROUNDS & 1 == 0,
+        "ChaCha needs a positive even number of rounds"
+    );
+
+    /// ChaCha with a 64 bit nonce.
     pub fn new(key: &[u8; 32]) -> Self {
-        ChaCha20 {
+        let () = Self::VALID_ROUNDS;
+        ChaCha {
+            key: Box::new(*key),
+            layout: Layout::Original,
+        }
+    }
+
+    /// ChaCha with a 96 bit nonce and a 32 bit counter, as in RFC 8439.
+    /// A single message can then be at most 256 GiB.
+    pub fn ietf(key: &[u8; 32]) -> Self {
+        let () = Self::VALID_ROUNDS;
+        ChaCha {
             key: Box::new(*key),
+            layout: Layout::IETF,
         }
     }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    pub fn rounds(&self) -> usize {
+        ROUNDS
+    }
 }
 
-impl Cipher<&mut [u8]> for ChaCha20 {
-    /// Encrypt some text with the ChaCha20 stream cipher.
-    /// The nonce has to be unique for every encryption.
-    fn encrypt(&self, nonce: &[u8], plaintext: &mut [u8]) -> Result<(), String> {
-        if nonce.len() != 8 {
-            return Err(format!("nonce len is {} but should be 8.", nonce.len()));
+impl<const ROUNDS: usize> ChaCha<ROUNDS> {
+    /// The keystream of the nonce, for encrypting a message in several parts.
+    /// The block counter starts at 1, like in `encrypt`.
+    pub fn keystream(&self, nonce: &[u8]) -> Result<KeyStream, CryptoError> {
+        self.keystream_from(nonce, 1)
+    }
+
+    /// The keystream of the nonce, with the block counter starting at `counter`.
+    pub fn keystream_from(&self, nonce: &[u8], counter: u64) -> Result<KeyStream, CryptoError> {
+        let expected = self.layout.nonce_len();
+        if nonce.len() != expected {
+            return Err(CryptoError::InvalidIvLength {
+                expected,
+                got: nonce.len(),
+            });
         }
-        // convert the nonce to an array instead of a slice
-        // the input should maybe be an array, but oh well.
-        let mut n = [0u8; 8];
-        for (i, b) in nonce.iter().zip(n.iter_mut()) {
-            *b = *i;
+        if counter > self.layout.max_counter() {
+            return Err(CryptoError::InvalidParameter(format!(
+                "the block counter {} is too large",
+                counter
+            )));
         }
 
-        // the initial state of the cipher,
-        // only missing the counter.
-        // This is shared for all threads encrypting
-        let block = initialize_block(&self.key, &n);
-
-        // slice the plaintext up into blocks
-        // and enumerate them. Then parallellize the operation
-        // and encrypt/decrypt the block.
-        plaintext
-            .chunks_mut(64)
-            .enumerate()
-            .collect::<Vec<(usize, &mut [u8])>>()
-            .into_par_iter()
-            .for_each(|(n, mut plain_block)| {
-                // start counting from 1
-                let c = n + 1;
-                // convert the usize counter to two u32.
-                let counter = [(c >> 32) as u32, (c & ((1 << 32) - 1)) as u32];
-                // encrypt the block
-                chacha20_block(&block, &mut plain_block, &counter)
-            });
+        Ok(KeyStream::new(
+            self.layout.initialize_block(&self.key, nonce),
+            ROUNDS,
+            self.layout,
+            counter,
+        ))
+    }
 
-        Ok(())
+    /// Encrypt or decrypt the data at a byte offset in a message, without the rest of it.
+    pub fn apply_keystream_at(
+        &self,
+        nonce: &[u8],
+        offset: u64,
+        data: &mut [u8],
+    ) -> Result<(), CryptoError> {
+        self.keystream(nonce)?.apply_keystream_at(offset, data)
     }
+}
 
-    /// Decrypt something encrypted with ChaCha20.
+impl<const ROUNDS: usize> Cipher<&mut [u8]> for ChaCha<ROUNDS> {
+    /// Encrypt some text with the ChaCha stream cipher.
+    /// The nonce has to be unique for every encryption.
+    fn encrypt(&self, nonce: &[u8], plaintext: &mut [u8]) -> Result<(), CryptoError> {
+        self.keystream(nonce)?.apply(plaintext)
+    }
+
+    /// Decrypt something encrypted with ChaCha.
     /// This is the same as encrypting it, so no worries
-    fn decrypt(&self, nonce: &[u8], ciphertext: &mut [u8]) -> Result<(), String> {
+    fn decrypt(&self, nonce: &[u8], ciphertext: &mut [u8]) -> Result<(), CryptoError> {
         self.encrypt(nonce, ciphertext)
     }
 }
@@ -341,7 +545,7 @@ mod tests {
     }
 
     #[test]
-    fn test_chacha() -> Result<(), String> {
+    fn test_chacha() -> Result<(), CryptoError> {
         let mut key = [0u8; 32];
         for (k, n) in key.iter_mut().zip(0..0x20) {
             *k = n;
@@ -365,7 +569,7 @@ mod tests {
     }
 
     #[test]
-    fn test_chacha_long() -> Result<(), String> {
+    fn test_chacha_long() -> Result<(), CryptoError> {
         let mut key = [0u8; 32];
         for (k, n) in key.iter_mut().zip(0..0x20) {
             *k = n;
@@ -412,4 +616,292 @@ mod tests {
         ];
         assert_eq!(&new_key, &expected);
     }
+
+    #[test]
+    fn invalid_nonce() {
+        let key = [0u8; 32];
+        let mut text = *b"hello";
+        assert_eq!(
+            ChaCha20::new(&key).encrypt(&[0u8; 12], &mut text),
+            Err(CryptoError::InvalidIvLength {
+                expected: 8,
+                got: 12
+            })
+        );
+        assert_eq!(
+            XChaCha20::new(&key).encrypt(&[0u8; 12], &mut text),
+            Err(CryptoError::InvalidIvLength {
+                expected: 24,
+                got: 12
+            })
+        );
+    }
+
+    #[test]
+    fn ietf_rfc8439() -> Result<(), CryptoError> {
+        // section 2.4.2
+        let mut key = [0u8; 32];
+        for (k, i) in key.iter_mut().zip(0..32) {
+            *k = i;
+        }
+        let nonce = crate::crypt::tests::hex("000000000000004a00000000");
+        let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you only one \
+tip for the future, sunscreen would be it.";
+        let expected = crate::crypt::tests::hex(
+            "6e2e359a2568f98041ba0728dd0d6981e97e7aec1d4360c20a27afccfd9fae0b
+            f91b65c5524733ab8f593dabcd62b3571639d624e65152ab8f530c359f0861d8
+            07ca0dbf500d6a6156a38e088a22b65e52bc514d16ccf806818ce91ab7793736
+            5af90bbf74a35be6b40b8eedf2785e42874d",
+        );
+
+        let chacha20 = ChaCha20::ietf(&key);
+        let mut text = plaintext.to_vec();
+        chacha20.encrypt(&nonce, &mut text)?;
+        assert_eq!(text, expected);
+
+        // the same with an explicit counter, in several parts
+        let mut keystream = chacha20.keystream_from(&nonce, 1)?;
+        let mut text = plaintext.to_vec();
+        let (a, b) = text.split_at_mut(70);
+        keystream.apply(a)?;
+        keystream.apply(b)?;
+        assert_eq!(text, expected);
+        assert_eq!(keystream.position(), 114);
+
+        Ok(())
+    }
+
+    #[test]
+    fn random_access() -> Result<(), CryptoError> {
+        let key = [7u8; 32];
+        let nonce = [1u8; 8];
+        let plaintext: Vec<u8> = (0..1000).map(|i| (i * 3) as u8).collect();
+
+        let chacha20 = ChaCha20::new(&key);
+        let mut expected = plaintext.clone();
+        chacha20.encrypt(&nonce, &mut expected)?;
+
+        for (start, end) in &[
+            (0, 1000),
+            (1, 2),
+            (63, 65),
+            (64, 128),
+            (100, 999),
+            (640, 1000),
+        ] {
+            let mut part = plaintext[*start..*end].to_vec();
+            chacha20.apply_keystream_at(&nonce, *start as u64, &mut part)?;
+            assert_eq!(part[..], expected[*start..*end]);
+        }
+
+        // continue from the middle
+        let mut keystream = chacha20.keystream(&nonce)?;
+        keystream.seek(500);
+        let mut part = plaintext[500..].to_vec();
+        keystream.apply(&mut part)?;
+        assert_eq!(part[..], expected[500..]);
+
+        // the counter of block n is the one of block 0 with a counter n more
+        let mut part = plaintext[128..].to_vec();
+        chacha20.keystream_from(&nonce, 3)?.apply(&mut part)?;
+        assert_eq!(part[..], expected[128..]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn ietf_counter_limit() -> Result<(), CryptoError> {
+        let key = [0u8; 32];
+        let nonce = [0u8; 12];
+        let chacha20 = ChaCha20::ietf(&key);
+
+        // the last block of the keystream can be used, but not more
+        let keystream = chacha20.keystream_from(&nonce, u32::MAX as u64)?;
+        assert!(keystream.apply_keystream_at(0, &mut [0u8; 64]).is_ok());
+        assert_eq!(
+            keystream.apply_keystream_at(1, &mut [0u8; 64]),
+            Err(CryptoError::InvalidDataLength(64))
+        );
+        assert!(chacha20.keystream_from(&nonce, 1 << 32).is_err());
+
+        // apply stops at the end of the keystream, and does not move
+        let mut keystream = chacha20.keystream_from(&nonce, u32::MAX as u64)?;
+        let mut data = [1u8; 65];
+        assert_eq!(
+            keystream.apply(&mut data),
+            Err(CryptoError::InvalidDataLength(65))
+        );
+        assert_eq!(data, [1u8; 65]);
+        assert_eq!(keystream.position(), 0);
+        assert_eq!(
+            chacha20.encrypt(&[0u8; 8], &mut [0u8; 4]),
+            Err(CryptoError::InvalidIvLength {
+                expected: 12,
+                got: 8
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn ietf_message_limit() -> Result<(), CryptoError> {
+        // encrypt starts at block 1, so 2^32 - 1 blocks are left for the message
+        let limit = (1u64 << 38) - 64;
+        let chacha20 = ChaCha20::ietf(&[0u8; 32]);
+        let nonce = [0u8; 12];
+
+        let mut data = [1u8; 17];
+        chacha20.apply_keystream_at(&nonce, limit - 17, &mut data)?;
+        assert_ne!(data, [1u8; 17]);
+
+        let mut data = [1u8; 17];
+        assert_eq!(
+            chacha20.apply_keystream_at(&nonce, limit - 16, &mut data),
+            Err(CryptoError::InvalidDataLength(17))
+        );
+        assert_eq!(
+            chacha20.apply_keystream_at(&nonce, limit, &mut data[..1]),
+            Err(CryptoError::InvalidDataLength(1))
+        );
+        assert_eq!(data, [1u8; 17]);
+
+        // apply continues from a position, in the same way as encrypt
+        let mut keystream = chacha20.keystream(&nonce)?;
+        keystream.seek(limit - 16);
+        assert!(keystream.apply(&mut data).is_err());
+        assert_eq!(keystream.position(), limit - 16);
+        keystream.apply(&mut data[..16])?;
+        assert_eq!(keystream.position(), limit);
+        Ok(())
+    }
+
+    /// The first two blocks of the keystream, with the counter from 0 as in the test vectors.
+    /// `encrypt` starts at the second block.
+    fn original_keystream<const ROUNDS: usize>(key: &str, iv: &str) -> Vec<u8> {
+        let mut k = [0u8; 32];
+        k.clone_from_slice(&crate::crypt::tests::hex(key));
+        let iv = crate::crypt::tests::hex(iv);
+        let chacha = ChaCha::<ROUNDS>::new(&k);
+
+        let mut stream = vec![0u8; 128];
+        chacha
+            .keystream_from(&iv, 0)
+            .unwrap()
+            .apply(&mut stream)
+            .unwrap();
+        let mut encrypted = vec![0u8; 64];
+        chacha.encrypt(&iv, &mut encrypted).unwrap();
+        assert_eq!(encrypted[..], stream[64..]);
+        stream
+    }
+
+    #[test]
+    fn reduced_rounds() {
+        use crate::crypt::tests::hex;
+
+        // from draft-strombergson-chacha-test-vectors, TC1 and TC8
+        let zero = "0000000000000000000000000000000000000000000000000000000000000000";
+        assert_eq!(
+            original_keystream::<8>(zero, "0000000000000000"),
+            hex(
+                "3e00ef2f895f40d67f5bb8e81f09a5a12c840ec3ce9a7f3b181be188ef711a1e
+                 984ce172b9216f419f445367456d5619314a42a3da86b001387bfdb80e0cfe42
+                 d2aefa0deaa5c151bf0adb6c01f2a5adc0fd581259f9a2aadcf20f8fd566a26b
+                 5032ec38bbc5da98ee0c6f568b872a65a08abf251deb21bb4b56e5d8821e68aa"
+            )
+        );
+        assert_eq!(
+            original_keystream::<12>(zero, "0000000000000000"),
+            hex(
+                "9bf49a6a0755f953811fce125f2683d50429c3bb49e074147e0089a52eae155f
+                 0564f879d27ae3c02ce82834acfa8c793a629f2ca0de6919610be82f411326be
+                 0bd58841203e74fe86fc71338ce0173dc628ebb719bdcbcc151585214cc089b4
+                 42258dcda14cf111c602b8971b8cc843e91e46ca905151c02744a6b017e69316"
+            )
+        );
+        assert_eq!(
+            original_keystream::<20>(zero, "0000000000000000"),
+            hex(
+                "76b8e0ada0f13d90405d6ae55386bd28bdd219b8a08ded1aa836efcc8b770dc7
+                 da41597c5157488d7724e03fb8d84a376a43b8f41518a11cc387b669b2ee6586
+                 9f07e7be5551387a98ba977c732d080dcb0f29a048e3656912c6533e32ee7aed
+                 29b721769ce64e43d57133b074d839d531ed1f28510afb45ace10a1f4b794d6f"
+            )
+        );
+
+        let key = "00112233445566778899aabbccddeeffffeeddccbbaa99887766554433221100";
+        assert_eq!(
+            original_keystream::<8>(key, "0f1e2d3c4b5a6978"),
+            hex(
+                "db43ad9d1e842d1272e4530e276b3f568f8859b3f7cf6d9d2c74fa53808cb515
+                 7a8ebf46ad3dcc4b6c7dadde131784b0120e0e22f6d5f9ffa7407d4a21b695d9
+                 c5dd30bf55612fab9bdd118920c19816470c7f5dcd42325dbbed8c57a56281c1
+                 44cb0f03e81b3004624e0650a1ce5afaf9a7cd8163f6dbd72602257dd96e471e"
+            )
+        );
+        assert_eq!(
+            original_keystream::<12>(key, "0f1e2d3c4b5a6978"),
+            hex(
+                "7ed12a3a63912ae941ba6d4c0d5e862e568b0e5589346935505f064b8c2698db
+                 f7d850667d8e67be639f3b4f6a16f92e65ea80f6c7429445da1fc2c1b9365040
+                 e32e50c4106f3b3da1ce7ccb1e7140b153493c0f3ad9a9bcff077ec4596f1d0f
+                 29bf9cbaa502820f732af5a93c49eee33d1c4f12af3b4297af91fe41ea9e94a2"
+            )
+        );
+    }
+
+    #[test]
+    fn original_layout() -> Result<(), CryptoError> {
+        use crate::crypt::tests::hex;
+
+        // the counter carries into its high word, from crypto_stream_chacha20_xor_ic of libsodium
+        let mut stream = [0u8; 128];
+        ChaCha20::new(&[5; 32])
+            .keystream_from(&[1; 8], u32::MAX as u64)?
+            .apply(&mut stream)?;
+        assert_eq!(
+            stream[..],
+            hex(
+                "ad01cb291f191628485f72f8cf7a31a2c64828ac2f8cc35485a6499be34503b0
+                 31b179caf5abbd2605490d69d59d4ea55408955fbec48179bb6e2f6084ffdfb5
+                 97b665d9dddde62a001423113efa166b41df12fd23b5b966ea8b152ffd56edc9
+                 7e909a3ef7cb480d00660b4e15b70f41819bf3f71c6dee8ca63d42596d85a9c4"
+            )[..]
+        );
+
+        // and crypto_stream_xchacha20_xor_ic with the counter at 1
+        let mut stream = [0u8; 64];
+        XChaCha20::new(&[5; 32]).encrypt(&[2; 24], &mut stream)?;
+        assert_eq!(
+            stream[..],
+            hex(
+                "7f175c65d463c18e36b66e355052469bbb77bd0c761919d9bfd612ad096ef1c7
+                 539580b14d988f6b2e12364ae8bc17eda52564ffb9625bf4d8d9c3a66cd8884c"
+            )[..]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn reduced_rounds_cipher() -> Result<(), CryptoError> {
+        let key = [5u8; 32];
+        let plaintext = *b"the same interface as ChaCha20, only faster";
+
+        let mut text8 = plaintext;
+        ChaCha8::new(&key).encrypt(&[1; 8], &mut text8)?;
+        let mut text12 = plaintext;
+        ChaCha12::new(&key).encrypt(&[1; 8], &mut text12)?;
+        let mut text20 = plaintext;
+        ChaCha20::new(&key).encrypt(&[1; 8], &mut text20)?;
+        assert_ne!(text8, text12);
+        assert_ne!(text12, text20);
+
+        ChaCha8::new(&key).decrypt(&[1; 8], &mut text8)?;
+        assert_eq!(text8, plaintext);
+        assert_eq!(ChaCha12::new(&key).rounds(), 12);
+
+        Ok(())
+    }
 }
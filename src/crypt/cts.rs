@@ -0,0 +1,379 @@
+//! CBC with ciphertext stealing.
+//! The last partial block is padded with ciphertext from the previous block instead of padding,
+//! so the ciphertext is exactly as long as the plaintext.
+//! The plaintext has to be at least one block.
+//!
+//!     # use codes::crypt::{aes::{AES, AESKey}, cts::CiphertextStealing};
+//!     let aes = AES::new(AESKey::AES128([0x42; 16]));
+//!     let iv = [0u8; 16];
+//!     let plaintext = b"Not a multiple of the block size".to_vec();
+//!     let mut message = plaintext[..30].to_vec();
+//!
+//!     aes.cts_encrypt(&iv, &mut message, CiphertextStealing::CS3).unwrap();
+//!     assert_eq!(message.len(), 30);
+//!
+//!     aes.cts_decrypt(&iv, &mut message, CiphertextStealing::CS3).unwrap();
+//!     assert_eq!(message, plaintext[..30]);
+
+use super::{BlockCipher, CryptoError};
+
+/// The order of the last two blocks.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CiphertextStealing {
+    /// The partial block comes before the last block.
+    CS1,
+    /// Like CS3, but identical to CBC when the plaintext is a whole number of blocks.
+    CS2,
+    /// The last two blocks are always swapped, this is the variant used by Kerberos.
+    CS3,
+}
+
+impl dyn BlockCipher {
+    /// Encrypt in CBC mode with ciphertext stealing.
+    pub fn cts_encrypt(
+        &self,
+        iv: &[u8],
+        plaintext: &mut [u8],
+        variant: CiphertextStealing,
+    ) -> Result<(), CryptoError> {
+        let bs = self.block_size();
+        if plaintext.len() < bs {
+            return Err(CryptoError::InvalidDataLength(plaintext.len()));
+        }
+
+        let d = plaintext.len() % bs;
+        let full = plaintext.len() - d;
+        self.cbc_encrypt(iv, &mut plaintext[..full])?;
+
+        // the start of the second to last block
+        let last = full - bs;
+        if d == 0 {
+            if variant == CiphertextStealing::CS3 && last > 0 {
+                let (head, tail) = plaintext.split_at_mut(last);
+                head[(last - bs)..].swap_with_slice(tail);
+            }
+            return Ok(());
+        }
+
+        // the partial block is padded with zeros and chained as usual
+        let mut block = plaintext[last..full].to_vec();
+        for (b, p) in block.iter_mut().zip(plaintext[full..].iter()) {
+            *b ^= p;
+        }
+        self.encrypt_block(&mut block);
+
+        // only d bytes of the second to last block is kept
+        match variant {
+            CiphertextStealing::CS1 => plaintext[(last + d)..].clone_from_slice(&block),
+            CiphertextStealing::CS2 | CiphertextStealing::CS3 => {
+                let stolen = plaintext[last..(last + d)].to_vec();
+                plaintext[last..full].clone_from_slice(&block);
+                plaintext[full..].clone_from_slice(&stolen);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decrypt a ciphertext encrypted with `cts_encrypt` with the same variant.
+    pub fn cts_decrypt(
+        &self,
+        iv: &[u8],
+        ciphertext: &mut [u8],
+        variant: CiphertextStealing,
+    ) -> Result<(), CryptoError> {
+        let bs = self.block_size();
+        // the blocks are reordered before the CBC decryption, so check the iv first
+        if iv.len() != bs {
+            return Err(CryptoError::InvalidIvLength {
+                expected: bs,
+                got: iv.len(),
+            });
+        }
+        if ciphertext.len() < bs {
+            return Err(CryptoError::InvalidDataLength(ciphertext.len()));
+        }
+
+        let d = ciphertext.len() % bs;
+        let full = ciphertext.len() - d;
+        let last = full - bs;
+        if d == 0 {
+            if variant == CiphertextStealing::CS3 && last > 0 {
+                let (head, tail) = ciphertext.split_at_mut(last);
+                head[(last - bs)..].swap_with_slice(tail);
+            }
+            return self.cbc_decrypt(iv, ciphertext);
+        }
+
+        let (stolen, mut block) = match variant {
+            CiphertextStealing::CS1 => (
+                ciphertext[last..(last + d)].to_vec(),
+                ciphertext[(last + d)..].to_vec(),
+            ),
+            CiphertextStealing::CS2 | CiphertextStealing::CS3 => {
+                (ciphertext[full..].to_vec(), ciphertext[last..full].to_vec())
+            }
+        };
+
+        // the decrypted last block is the partial plaintext and the rest of the
+        // second to last ciphertext block
+        self.decrypt_block(&mut block);
+        for (b, s) in block.iter_mut().zip(stolen.iter()) {
+            *b ^= s;
+        }
+
+        ciphertext[last..(last + d)].clone_from_slice(&stolen);
+        ciphertext[(last + d)..full].clone_from_slice(&block[d..]);
+        ciphertext[full..].clone_from_slice(&block[..d]);
+
+        self.cbc_decrypt(iv, &mut ciphertext[..full])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::aes::{AESKey, AES};
+    use crate::crypt::tests::hex;
+    use crate::crypt::twofish::{Twofish, TwofishKey};
+
+    const PLAINTEXT: &[u8] = b"I would like the General Gau's Chicken, please, and wonton soup.";
+
+    fn check(variant: CiphertextStealing, vectors: &[(usize, &str)]) {
+        let aes = AES::new(AESKey::AES128(*b"chicken teriyaki"));
+        let iv = [0u8; 16];
+        for (len, expected) in vectors {
+            let mut message = PLAINTEXT[..*len].to_vec();
+            aes.cts_encrypt(&iv, &mut message, variant).unwrap();
+            assert_eq!(message, hex(expected), "{:?} {}", variant, len);
+
+            aes.cts_decrypt(&iv, &mut message, variant).unwrap();
+            assert_eq!(message, PLAINTEXT[..*len], "{:?} {}", variant, len);
+        }
+    }
+
+    #[test]
+    fn cs3_rfc3962() {
+        // The AES test vectors from RFC 3962, appendix B
+        check(
+            CiphertextStealing::CS3,
+            &[
+                (17, "c6353568f2bf8cb4d8a580362da7ff7f97"),
+                (
+                    31,
+                    "fc00783e0efdb2c1d445d4c8eff7ed2297687268d6ecccc0c07b25e25ecfe5",
+                ),
+                (
+                    32,
+                    "39312523a78662d5be7fcbcc98ebf5a897687268d6ecccc0c07b25e25ecfe584",
+                ),
+                (
+                    47,
+                    "97687268d6ecccc0c07b25e25ecfe584b3fffd940c16a18c1b5549d2f838029e
+                     39312523a78662d5be7fcbcc98ebf5",
+                ),
+                (
+                    48,
+                    "97687268d6ecccc0c07b25e25ecfe5849dad8bbb96c4cdc03bc103e1a194bbd8
+                     39312523a78662d5be7fcbcc98ebf5a8",
+                ),
+                (
+                    64,
+                    "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8
+                     4807efe836ee89a526730dbc2f7bc8409dad8bbb96c4cdc03bc103e1a194bbd8",
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn cs1_and_cs2() {
+        // the same plaintexts, by the AES-128-CBC-CTS of OpenSSL 3 with the cts_mode
+        // parameter, which also gives the CS3 vectors above
+        check(
+            CiphertextStealing::CS1,
+            &[
+                (17, "97c6353568f2bf8cb4d8a580362da7ff7f"),
+                (
+                    31,
+                    "97687268d6ecccc0c07b25e25ecfe5fc00783e0efdb2c1d445d4c8eff7ed22",
+                ),
+                (
+                    32,
+                    "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8",
+                ),
+                (
+                    47,
+                    "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5
+                     b3fffd940c16a18c1b5549d2f838029e",
+                ),
+                (
+                    48,
+                    "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8
+                     9dad8bbb96c4cdc03bc103e1a194bbd8",
+                ),
+                (
+                    64,
+                    "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8
+                     9dad8bbb96c4cdc03bc103e1a194bbd84807efe836ee89a526730dbc2f7bc840",
+                ),
+            ],
+        );
+        check(
+            CiphertextStealing::CS2,
+            &[
+                (17, "c6353568f2bf8cb4d8a580362da7ff7f97"),
+                (
+                    31,
+                    "fc00783e0efdb2c1d445d4c8eff7ed2297687268d6ecccc0c07b25e25ecfe5",
+                ),
+                (
+                    32,
+                    "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8",
+                ),
+                (
+                    47,
+                    "97687268d6ecccc0c07b25e25ecfe584b3fffd940c16a18c1b5549d2f838029e
+                     39312523a78662d5be7fcbcc98ebf5",
+                ),
+                (
+                    48,
+                    "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8
+                     9dad8bbb96c4cdc03bc103e1a194bbd8",
+                ),
+                (
+                    64,
+                    "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8
+                     9dad8bbb96c4cdc03bc103e1a194bbd84807efe836ee89a526730dbc2f7bc840",
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn openssl_aes256() {
+        // AES-256-CBC-CTS of OpenSSL 3, with a counting key, iv and plaintext
+        let mut key = [0u8; 32];
+        key.iter_mut().zip(0..).for_each(|(k, i)| *k = i);
+        let aes = AES::new(AESKey::AES256(key));
+        let iv: Vec<u8> = (0..16).rev().collect();
+        let plaintext: Vec<u8> = (0..48).collect();
+        let vectors = [
+            (
+                CiphertextStealing::CS1,
+                23,
+                "e2e0f32d8382895057963325753cbb1bd81cea35beb4f2",
+            ),
+            (
+                CiphertextStealing::CS1,
+                40,
+                "e2e0f32d838289bdd02141678f4923f55121edd5acbafa2ecd0be54e234d101c
+                 061d63c368ea6813",
+            ),
+            (
+                CiphertextStealing::CS2,
+                23,
+                "5057963325753cbb1bd81cea35beb4f2e2e0f32d838289",
+            ),
+            (
+                CiphertextStealing::CS2,
+                48,
+                "e2e0f32d838289bdd02141678f4923f55121edd5acbafa2e8575466cd61abceb
+                 5f17ed8704b757e9493c80eff1ccd8a4",
+            ),
+            (
+                CiphertextStealing::CS3,
+                40,
+                "e2e0f32d838289bdd02141678f4923f5cd0be54e234d101c061d63c368ea6813
+                 5121edd5acbafa2e",
+            ),
+            (
+                CiphertextStealing::CS3,
+                48,
+                "e2e0f32d838289bdd02141678f4923f55f17ed8704b757e9493c80eff1ccd8a4
+                 5121edd5acbafa2e8575466cd61abceb",
+            ),
+        ];
+        for (variant, len, expected) in vectors.iter() {
+            let mut message = plaintext[..*len].to_vec();
+            aes.cts_encrypt(&iv, &mut message, *variant).unwrap();
+            assert_eq!(message, hex(expected), "{:?} {}", variant, len);
+            aes.cts_decrypt(&iv, &mut message, *variant).unwrap();
+            assert_eq!(message, plaintext[..*len]);
+        }
+    }
+
+    #[test]
+    fn single_block() {
+        // one block is just the block cipher applied to the plaintext xor the iv
+        let aes = AES::new(AESKey::AES128(*b"chicken teriyaki"));
+        let iv: Vec<u8> = (0..16).collect();
+        let mut block: Vec<u8> = PLAINTEXT[..16]
+            .iter()
+            .zip(iv.iter())
+            .map(|(p, i)| p ^ i)
+            .collect();
+        aes.encrypt_block(&mut block);
+        for variant in &[
+            CiphertextStealing::CS1,
+            CiphertextStealing::CS2,
+            CiphertextStealing::CS3,
+        ] {
+            let mut message = PLAINTEXT[..16].to_vec();
+            aes.cts_encrypt(&iv, &mut message, *variant).unwrap();
+            assert_eq!(message, block);
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let twofish = Twofish::new(TwofishKey::TK256([3; 32]));
+        let iv = [9u8; 16];
+        let plaintext: Vec<u8> = (0..100).collect();
+        for variant in &[
+            CiphertextStealing::CS1,
+            CiphertextStealing::CS2,
+            CiphertextStealing::CS3,
+        ] {
+            for len in 16..100 {
+                let mut message = plaintext[..len].to_vec();
+                twofish.cts_encrypt(&iv, &mut message, *variant).unwrap();
+                assert_eq!(message.len(), len);
+                assert_ne!(message, plaintext[..len]);
+                twofish.cts_decrypt(&iv, &mut message, *variant).unwrap();
+                assert_eq!(message, plaintext[..len]);
+            }
+        }
+    }
+
+    #[test]
+    fn too_short() {
+        let aes = AES::new(AESKey::AES128([0; 16]));
+        for len in 0..16 {
+            let mut message = vec![0u8; len];
+            assert_eq!(
+                aes.cts_encrypt(&[0; 16], &mut message, CiphertextStealing::CS3),
+                Err(CryptoError::InvalidDataLength(len))
+            );
+            assert_eq!(
+                aes.cts_decrypt(&[0; 16], &mut message, CiphertextStealing::CS1),
+                Err(CryptoError::InvalidDataLength(len))
+            );
+        }
+
+        // a wrong iv is found before the blocks are reordered
+        let mut message: Vec<u8> = (0..48).collect();
+        for variant in &[CiphertextStealing::CS3, CiphertextStealing::CS1] {
+            for len in &[48, 40] {
+                assert_eq!(
+                    aes.cts_decrypt(&[0; 8], &mut message[..*len], *variant),
+                    Err(CryptoError::InvalidIvLength {
+                        expected: 16,
+                        got: 8
+                    })
+                );
+                assert_eq!(message, (0..48).collect::<Vec<u8>>());
+            }
+        }
+    }
+}
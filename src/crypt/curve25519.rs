@@ -0,0 +1,287 @@
+//! X25519 Diffie-Hellman on the Montgomery form of Curve25519, by RFC 7748.
+//!
+//!     # use codes::crypt::curve25519::{x25519, x25519_base};
+//!     let alice = [1u8; 32];
+//!     let bob = [2u8; 32];
+//!     // both get the same shared secret from the public key of the other
+//!     assert_eq!(
+//!         x25519(&alice, &x25519_base(&bob)),
+//!         x25519(&bob, &x25519_base(&alice))
+//!     );
+//!
+//! The field elements are five limbs of 51 bits, and the ladder does not branch on the scalar.
+
+/// The u-coordinate of the base point.
+pub const BASEPOINT: [u8; 32] = [
+    9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+const MASK: u64 = (1 << 51) - 1;
+
+/// An element of the field of integers modulo 2^255 - 19.
+#[derive(Copy, Clone)]
+struct Fe([u64; 5]);
+
+impl Fe {
+    const ZERO: Fe = Fe([0; 5]);
+    const ONE: Fe = Fe([1, 0, 0, 0, 0]);
+
+    /// Load a little endian number, ignoring the top bit.
+    fn from_bytes(bytes: &[u8; 32]) -> Self {
+        let mut w = [0u64; 4];
+        for (w, b) in w.iter_mut().zip(bytes.chunks(8)) {
+            let mut tmp = [0u8; 8];
+            tmp.clone_from_slice(b);
+            *w = u64::from_le_bytes(tmp);
+        }
+        Fe([
+            w[0] & MASK,
+            ((w[0] >> 51) | (w[1] << 13)) & MASK,
+            ((w[1] >> 38) | (w[2] << 26)) & MASK,
+            ((w[2] >> 25) | (w[3] << 39)) & MASK,
+            (w[3] >> 12) & MASK,
+        ])
+    }
+
+    /// The fully reduced little endian number.
+    fn to_bytes(self) -> [u8; 32] {
+        let mut h = self.carry().0;
+
+        // find out if h >= p, by adding 19 and seeing if it overflows 2^255
+        let mut q = (h[0] + 19) >> 51;
+        for l in h.iter().skip(1) {
+            q = (l + q) >> 51;
+        }
+        h[0] += 19 * q;
+        for i in 0..4 {
+            h[i + 1] += h[i] >> 51;
+            h[i] &= MASK;
+        }
+        h[4] &= MASK;
+
+        let w = [
+            h[0] | (h[1] << 51),
+            (h[1] >> 13) | (h[2] << 38),
+            (h[2] >> 26) | (h[3] << 25),
+            (h[3] >> 39) | (h[4] << 12),
+        ];
+        let mut bytes = [0u8; 32];
+        for (b, w) in bytes.chunks_mut(8).zip(w.iter()) {
+            b.clone_from_slice(&w.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Carry the limbs down to 51 bits (and a little more in the lowest).
+    fn carry(self) -> Self {
+        let mut h = self.0;
+        for i in 0..4 {
+            h[i + 1] += h[i] >> 51;
+            h[i] &= MASK;
+        }
+        h[0] += 19 * (h[4] >> 51);
+        h[4] &= MASK;
+        Fe(h)
+    }
+
+    fn add(self, other: Fe) -> Fe {
+        let mut h = self.0;
+        for (a, b) in h.iter_mut().zip(other.0.iter()) {
+            *a += b;
+        }
+        Fe(h).carry()
+    }
+
+    /// Add 4p before subtracting, so the limbs do not underflow.
+    fn sub(self, other: Fe) -> Fe {
+        let mut h = self.0;
+        h[0] += 0x1f_ffff_ffff_ffb4 - other.0[0];
+        for (a, b) in h.iter_mut().zip(other.0.iter()).skip(1) {
+            *a += 0x1f_ffff_ffff_fffc - b;
+        }
+        Fe(h).carry()
+    }
+
+    fn mul(self, other: Fe) -> Fe {
+        let a = self.0.map(|x| x as u128);
+        let b = other.0.map(|x| x as u128);
+        // the limbs that wrap around 2^255 are multiplied by 19
+        let b19 = b.map(|x| x * 19);
+
+        let r = [
+            a[0] * b[0] + a[1] * b19[4] + a[2] * b19[3] + a[3] * b19[2] + a[4] * b19[1],
+            a[0] * b[1] + a[1] * b[0] + a[2] * b19[4] + a[3] * b19[3] + a[4] * b19[2],
+            a[0] * b[2] + a[1] * b[1] + a[2] * b[0] + a[3] * b19[4] + a[4] * b19[3],
+            a[0] * b[3] + a[1] * b[2] + a[2] * b[1] + a[3] * b[0] + a[4] * b19[4],
+            a[0] * b[4] + a[1] * b[3] + a[2] * b[2] + a[3] * b[1] + a[4] * b[0],
+        ];
+
+        let mut h = [0u64; 5];
+        let mut c = 0u128;
+        for (h, r) in h.iter_mut().zip(r.iter()) {
+            let t = r + c;
+            *h = (t as u64) & MASK;
+            c = t >> 51;
+        }
+        h[0] += (c as u64) * 19;
+        Fe(h).carry()
+    }
+
+    fn square(self) -> Fe {
+        self.mul(self)
+    }
+
+    fn mul_small(self, n: u64) -> Fe {
+        let mut h = [0u64; 5];
+        let mut c = 0u128;
+        for (h, a) in h.iter_mut().zip(self.0.iter()) {
+            let t = (*a as u128) * (n as u128) + c;
+            *h = (t as u64) & MASK;
+            c = t >> 51;
+        }
+        h[0] += (c as u64) * 19;
+        Fe(h).carry()
+    }
+
+    /// The inverse by Fermat's little theorem, a^(p-2).
+    fn invert(self) -> Fe {
+        // p - 2 = 2^255 - 21, all bits set except bit 2 and 4
+        let mut res = Fe::ONE;
+        for bit in (0..255).rev() {
+            res = res.square();
+            if bit != 2 && bit != 4 {
+                res = res.mul(self);
+            }
+        }
+        res
+    }
+
+    /// Swap the elements if `swap` is 1, without branching.
+    fn cswap(a: &mut Fe, b: &mut Fe, swap: u64) {
+        let mask = 0u64.wrapping_sub(swap);
+        for (a, b) in a.0.iter_mut().zip(b.0.iter_mut()) {
+            let t = mask & (*a ^ *b);
+            *a ^= t;
+            *b ^= t;
+        }
+    }
+}
+
+/// Multiply the point with u-coordinate `u` with the scalar `k`.
+/// The scalar is clamped as in RFC 7748, so any 32 random bytes is a valid secret key.
+pub fn x25519(k: &[u8; 32], u: &[u8; 32]) -> [u8; 32] {
+    let mut k = *k;
+    k[0] &= 248;
+    k[31] &= 127;
+    k[31] |= 64;
+
+    let x1 = Fe::from_bytes(u);
+    let (mut x2, mut z2) = (Fe::ONE, Fe::ZERO);
+    let (mut x3, mut z3) = (x1, Fe::ONE);
+    let mut swap = 0;
+
+    // the Montgomery ladder
+    for t in (0..255).rev() {
+        let bit = ((k[t >> 3] >> (t & 7)) & 1) as u64;
+        swap ^= bit;
+        Fe::cswap(&mut x2, &mut x3, swap);
+        Fe::cswap(&mut z2, &mut z3, swap);
+        swap = bit;
+
+        let a = x2.add(z2);
+        let aa = a.square();
+        let b = x2.sub(z2);
+        let bb = b.square();
+        let e = aa.sub(bb);
+        let c = x3.add(z3);
+        let d = x3.sub(z3);
+        let da = d.mul(a);
+        let cb = c.mul(b);
+        x3 = da.add(cb).square();
+        z3 = x1.mul(da.sub(cb).square());
+        x2 = aa.mul(bb);
+        z2 = e.mul(aa.add(e.mul_small(121665)));
+    }
+    Fe::cswap(&mut x2, &mut x3, swap);
+    Fe::cswap(&mut z2, &mut z3, swap);
+
+    x2.mul(z2.invert()).to_bytes()
+}
+
+/// The public key of a secret key.
+pub fn x25519_base(k: &[u8; 32]) -> [u8; 32] {
+    x25519(k, &BASEPOINT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::tests::hex;
+
+    fn array(s: &str) -> [u8; 32] {
+        let mut a = [0u8; 32];
+        a.clone_from_slice(&hex(s));
+        a
+    }
+
+    #[test]
+    fn rfc7748_vectors() {
+        // section 5.2
+        let k = array("a546e36bf0527c9d3b16154b82465edd62144c0ac1fc5a18506a2244ba449ac4");
+        let u = array("e6db6867583030db3594c1a424b15f7c726624ec26b3353b10a903a6d0ab1c4c");
+        assert_eq!(
+            x25519(&k, &u),
+            array("c3da55379de9c6908e94ea4df28d084f32eccf03491c71f754b4075577a28552")
+        );
+
+        // the top bit of u is ignored
+        let k = array("4b66e9d4d1b4673c5ad22691957d6af5c11b6421e0ea01d42ca4169e7918ba0d");
+        let u = array("e5210f12786811d3f4b7959d0538ae2c31dbe7106fc03c3efc4cd549c715a493");
+        assert_eq!(
+            x25519(&k, &u),
+            array("95cbde9476e8907d7aade45cb4b873f88b595a68799fa152e6f8f7647aac7957")
+        );
+    }
+
+    #[test]
+    fn rfc7748_iterated() {
+        let mut k = BASEPOINT;
+        let mut u = BASEPOINT;
+        for i in 0..1000 {
+            let res = x25519(&k, &u);
+            u = k;
+            k = res;
+            if i == 0 {
+                assert_eq!(
+                    k,
+                    array("422c8e7a6227d7bca1350b3e2bb7279f7897b87bb6854b783c60e80311ae3079")
+                );
+            }
+        }
+        assert_eq!(
+            k,
+            array("684cf59ba83309552800ef566f2f4d3c1c3887c49360e3875f2eb94d99532c51")
+        );
+    }
+
+    #[test]
+    fn rfc7748_diffie_hellman() {
+        // section 6.1
+        let alice = array("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a");
+        let bob = array("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb");
+        let alice_public = x25519_base(&alice);
+        let bob_public = x25519_base(&bob);
+        assert_eq!(
+            alice_public,
+            array("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a")
+        );
+        assert_eq!(
+            bob_public,
+            array("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f")
+        );
+
+        let shared = array("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742");
+        assert_eq!(x25519(&alice, &bob_public), shared);
+        assert_eq!(x25519(&bob, &alice_public), shared);
+    }
+}
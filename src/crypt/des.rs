@@ -0,0 +1,422 @@
+//! DES and Triple-DES (TDEA), by FIPS 46-3 and NIST SP 800-67.
+//! DES has a 56 bit key and both have 64 bit blocks, so they are only here to read old data.
+//! The permutations are done a bit at a time from the tables of the standard,
+//! where the bits are numbered from 1 at the most significant bit.
+
+use super::BlockCipher;
+
+/// The initial permutation.
+const IP: [u8; 64] = [
+    58, 50, 42, 34, 26, 18, 10, 2, 60, 52, 44, 36, 28, 20, 12, 4, 62, 54, 46, 38, 30, 22, 14, 6,
+    64, 56, 48, 40, 32, 24, 16, 8, 57, 49, 41, 33, 25, 17, 9, 1, 59, 51, 43, 35, 27, 19, 11, 3, 61,
+    53, 45, 37, 29, 21, 13, 5, 63, 55, 47, 39, 31, 23, 15, 7,
+];
+
+/// The final permutation, the inverse of IP.
+const FP: [u8; 64] = [
+    40, 8, 48, 16, 56, 24, 64, 32, 39, 7, 47, 15, 55, 23, 63, 31, 38, 6, 46, 14, 54, 22, 62, 30,
+    37, 5, 45, 13, 53, 21, 61, 29, 36, 4, 44, 12, 52, 20, 60, 28, 35, 3, 43, 11, 51, 19, 59, 27,
+    34, 2, 42, 10, 50, 18, 58, 26, 33, 1, 41, 9, 49, 17, 57, 25,
+];
+
+/// Expands the 32 bit half block to the 48 bits of a subkey.
+const E: [u8; 48] = [
+    32, 1, 2, 3, 4, 5, 4, 5, 6, 7, 8, 9, 8, 9, 10, 11, 12, 13, 12, 13, 14, 15, 16, 17, 16, 17, 18,
+    19, 20, 21, 20, 21, 22, 23, 24, 25, 24, 25, 26, 27, 28, 29, 28, 29, 30, 31, 32, 1,
+];
+
+/// The permutation after the S-boxes.
+const P: [u8; 32] = [
+    16, 7, 20, 21, 29, 12, 28, 17, 1, 15, 23, 26, 5, 18, 31, 10, 2, 8, 24, 14, 32, 27, 3, 9, 19,
+    13, 30, 6, 22, 11, 4, 25,
+];
+
+/// Selects the 56 key bits, leaving out the parity bits.
+const PC1: [u8; 56] = [
+    57, 49, 41, 33, 25, 17, 9, 1, 58, 50, 42, 34, 26, 18, 10, 2, 59, 51, 43, 35, 27, 19, 11, 3, 60,
+    52, 44, 36, 63, 55, 47, 39, 31, 23, 15, 7, 62, 54, 46, 38, 30, 22, 14, 6, 61, 53, 45, 37, 29,
+    21, 13, 5, 28, 20, 12, 4,
+];
+
+/// Selects the 48 bits of a subkey from the two 28 bit halves.
+const PC2: [u8; 48] = [
+    14, 17, 11, 24, 1, 5, 3, 28, 15, 6, 21, 10, 23, 19, 12, 4, 26, 8, 16, 7, 27, 20, 13, 2, 41, 52,
+    31, 37, 47, 55, 30, 40, 51, 45, 33, 48, 44, 49, 39, 56, 34, 53, 46, 42, 50, 36, 29, 32,
+];
+
+/// The left rotations of the key halves before each round.
+const SHIFTS: [u32; 16] = [1, 1, 2, 2, 2, 2, 2, 2, 1, 2, 2, 2, 2, 2, 2, 1];
+
+/// The S-boxes, indexed by the outer bits as the row and the inner four as the column.
+const SBOX: [[u8; 64]; 8] = [
+    [
+        14, 4, 13, 1, 2, 15, 11, 8, 3, 10, 6, 12, 5, 9, 0, 7, 0, 15, 7, 4, 14, 2, 13, 1, 10, 6, 12,
+        11, 9, 5, 3, 8, 4, 1, 14, 8, 13, 6, 2, 11, 15, 12, 9, 7, 3, 10, 5, 0, 15, 12, 8, 2, 4, 9,
+        1, 7, 5, 11, 3, 14, 10, 0, 6, 13,
+    ],
+    [
+        15, 1, 8, 14, 6, 11, 3, 4, 9, 7, 2, 13, 12, 0, 5, 10, 3, 13, 4, 7, 15, 2, 8, 14, 12, 0, 1,
+        10, 6, 9, 11, 5, 0, 14, 7, 11, 10, 4, 13, 1, 5, 8, 12, 6, 9, 3, 2, 15, 13, 8, 10, 1, 3, 15,
+        4, 2, 11, 6, 7, 12, 0, 5, 14, 9,
+    ],
+    [
+        10, 0, 9, 14, 6, 3, 15, 5, 1, 13, 12, 7, 11, 4, 2, 8, 13, 7, 0, 9, 3, 4, 6, 10, 2, 8, 5,
+        14, 12, 11, 15, 1, 13, 6, 4, 9, 8, 15, 3, 0, 11, 1, 2, 12, 5, 10, 14, 7, 1, 10, 13, 0, 6,
+        9, 8, 7, 4, 15, 14, 3, 11, 5, 2, 12,
+    ],
+    [
+        7, 13, 14, 3, 0, 6, 9, 10, 1, 2, 8, 5, 11, 12, 4, 15, 13, 8, 11, 5, 6, 15, 0, 3, 4, 7, 2,
+        12, 1, 10, 14, 9, 10, 6, 9, 0, 12, 11, 7, 13, 15, 1, 3, 14, 5, 2, 8, 4, 3, 15, 0, 6, 10, 1,
+        13, 8, 9, 4, 5, 11, 12, 7, 2, 14,
+    ],
+    [
+        2, 12, 4, 1, 7, 10, 11, 6, 8, 5, 3, 15, 13, 0, 14, 9, 14, 11, 2, 12, 4, 7, 13, 1, 5, 0, 15,
+        10, 3, 9, 8, 6, 4, 2, 1, 11, 10, 13, 7, 8, 15, 9, 12, 5, 6, 3, 0, 14, 11, 8, 12, 7, 1, 14,
+        2, 13, 6, 15, 0, 9, 10, 4, 5, 3,
+    ],
+    [
+        12, 1, 10, 15, 9, 2, 6, 8, 0, 13, 3, 4, 14, 7, 5, 11, 10, 15, 4, 2, 7, 12, 9, 5, 6, 1, 13,
+        14, 0, 11, 3, 8, 9, 14, 15, 5, 2, 8, 12, 3, 7, 0, 4, 10, 1, 13, 11, 6, 4, 3, 2, 12, 9, 5,
+        15, 10, 11, 14, 1, 7, 6, 0, 8, 13,
+    ],
+    [
+        4, 11, 2, 14, 15, 0, 8, 13, 3, 12, 9, 7, 5, 10, 6, 1, 13, 0, 11, 7, 4, 9, 1, 10, 14, 3, 5,
+        12, 2, 15, 8, 6, 1, 4, 11, 13, 12, 3, 7, 14, 10, 15, 6, 8, 0, 5, 9, 2, 6, 11, 13, 8, 1, 4,
+        10, 7, 9, 5, 0, 15, 14, 2, 3, 12,
+    ],
+    [
+        13, 2, 8, 4, 6, 15, 11, 1, 10, 9, 3, 14, 5, 0, 12, 7, 1, 15, 13, 8, 10, 3, 7, 4, 12, 5, 6,
+        11, 0, 14, 9, 2, 7, 11, 4, 1, 9, 12, 14, 2, 0, 6, 10, 13, 15, 3, 5, 8, 2, 1, 14, 7, 4, 10,
+        8, 13, 15, 12, 9, 0, 3, 5, 6, 11,
+    ],
+];
+
+/// Permute the `width` lowest bits of the input by the table.
+fn permute(input: u64, width: u8, table: &[u8]) -> u64 {
+    table
+        .iter()
+        .fold(0, |out, p| (out << 1) | ((input >> (width - p)) & 1))
+}
+
+/// The sixteen 48 bit round keys of one DES key.
+#[derive(Clone, Copy)]
+struct Schedule([u64; 16]);
+
+impl Schedule {
+    fn new(key: &[u8]) -> Self {
+        let mut k = [0u8; 8];
+        k.clone_from_slice(key);
+        let cd = permute(u64::from_be_bytes(k), 64, &PC1);
+        let (mut c, mut d) = ((cd >> 28) as u32, cd as u32 & 0x0fff_ffff);
+
+        let mut subkeys = [0u64; 16];
+        for (subkey, shift) in subkeys.iter_mut().zip(SHIFTS.iter()) {
+            c = ((c << shift) | (c >> (28 - shift))) & 0x0fff_ffff;
+            d = ((d << shift) | (d >> (28 - shift))) & 0x0fff_ffff;
+            *subkey = permute(((c as u64) << 28) | d as u64, 56, &PC2);
+        }
+        Schedule(subkeys)
+    }
+
+    fn encrypt(&self, block: u64) -> u64 {
+        crypt(block, self.0.iter())
+    }
+
+    fn decrypt(&self, block: u64) -> u64 {
+        crypt(block, self.0.iter().rev())
+    }
+}
+
+/// The round function.
+fn f(r: u32, subkey: u64) -> u32 {
+    let x = permute(r as u64, 32, &E) ^ subkey;
+    let s = SBOX.iter().enumerate().fold(0, |s, (i, sbox)| {
+        let six = (x >> (42 - 6 * i)) & 0x3f;
+        let idx = ((six & 0x20) | ((six & 1) << 4) | ((six >> 1) & 0xf)) as usize;
+        (s << 4) | sbox[idx] as u64
+    });
+    permute(s, 32, &P) as u32
+}
+
+/// The Feistel network, with the subkeys in the order given.
+fn crypt<'a>(block: u64, subkeys: impl Iterator<Item = &'a u64>) -> u64 {
+    let lr = permute(block, 64, &IP);
+    let (mut l, mut r) = ((lr >> 32) as u32, lr as u32);
+    for k in subkeys {
+        let next = l ^ f(r, *k);
+        l = r;
+        r = next;
+    }
+    // the halves are not swapped after the last round
+    permute(((r as u64) << 32) | l as u64, 64, &FP)
+}
+
+fn load(block: &[u8]) -> u64 {
+    let mut b = [0u8; 8];
+    b.clone_from_slice(block);
+    u64::from_be_bytes(b)
+}
+
+/// A DES key of 64 bits, where the parity bits are ignored.
+pub struct DESKey(pub [u8; 8]);
+
+impl<'a> std::convert::TryFrom<super::CipherKey<'a>> for DESKey {
+    type Error = super::CryptoError;
+
+    fn try_from(key: super::CipherKey) -> Result<DESKey, Self::Error> {
+        if key.key.len() != 8 {
+            return Err(super::CryptoError::InvalidKeyLength(key.key.len()));
+        }
+        let mut key_arr = [0u8; 8];
+        key_arr[..].clone_from_slice(key.key);
+        Ok(DESKey(key_arr))
+    }
+}
+
+pub struct DES {
+    schedule: Schedule,
+    padding: super::Padding,
+    key: DESKey,
+}
+
+impl DES {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(key: DESKey) -> Box<dyn BlockCipher> {
+        Box::new(DES {
+            schedule: Schedule::new(&key.0),
+            padding: super::Padding::default(),
+            key,
+        })
+    }
+}
+
+impl BlockCipher for DES {
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let c = self.schedule.encrypt(load(block));
+        block.clone_from_slice(&c.to_be_bytes());
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let p = self.schedule.decrypt(load(block));
+        block.clone_from_slice(&p.to_be_bytes());
+    }
+
+    fn block_size(&self) -> usize {
+        8
+    }
+
+    fn change_padding(&mut self, padding: super::Padding) {
+        self.padding = padding;
+    }
+
+    fn padding(&self) -> super::Padding {
+        self.padding
+    }
+
+    fn get_key(&self) -> &[u8] {
+        &self.key.0
+    }
+
+    fn set_key(&mut self, key: &[u8]) -> Result<(), super::CryptoError> {
+        use std::convert::TryFrom;
+        self.key = DESKey::try_from(super::CipherKey { key })?;
+        self.schedule = Schedule::new(key);
+        Ok(())
+    }
+}
+
+/// The keying options of SP 800-67, where the two key option reuses the first key as the third.
+pub enum TDEAKey {
+    TwoKey([u8; 16]),
+    ThreeKey([u8; 24]),
+}
+
+impl TDEAKey {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            TDEAKey::TwoKey(key) => &key[..],
+            TDEAKey::ThreeKey(key) => &key[..],
+        }
+    }
+
+    fn schedules(&self) -> [Schedule; 3] {
+        let key = self.bytes();
+        let k1 = Schedule::new(&key[..8]);
+        let k2 = Schedule::new(&key[8..16]);
+        match self {
+            TDEAKey::TwoKey(_) => [k1, k2, k1],
+            TDEAKey::ThreeKey(_) => [k1, k2, Schedule::new(&key[16..])],
+        }
+    }
+}
+
+impl<'a> std::convert::TryFrom<super::CipherKey<'a>> for TDEAKey {
+    type Error = super::CryptoError;
+
+    fn try_from(key: super::CipherKey) -> Result<TDEAKey, Self::Error> {
+        match key.key.len() {
+            16 => {
+                let mut key_arr = [0u8; 16];
+                key_arr[..].clone_from_slice(key.key);
+                Ok(TDEAKey::TwoKey(key_arr))
+            }
+            24 => {
+                let mut key_arr = [0u8; 24];
+                key_arr[..].clone_from_slice(key.key);
+                Ok(TDEAKey::ThreeKey(key_arr))
+            }
+            _ => Err(super::CryptoError::InvalidKeyLength(key.key.len())),
+        }
+    }
+}
+
+/// Triple-DES, encryption with the first key, decryption with the second
+/// and encryption with the third.
+pub struct TDEA {
+    schedules: [Schedule; 3],
+    padding: super::Padding,
+    key: TDEAKey,
+}
+
+impl TDEA {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(key: TDEAKey) -> Box<dyn BlockCipher> {
+        Box::new(TDEA {
+            schedules: key.schedules(),
+            padding: super::Padding::default(),
+            key,
+        })
+    }
+}
+
+impl BlockCipher for TDEA {
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let [k1, k2, k3] = &self.schedules;
+        let c = k3.encrypt(k2.decrypt(k1.encrypt(load(block))));
+        block.clone_from_slice(&c.to_be_bytes());
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let [k1, k2, k3] = &self.schedules;
+        let p = k1.decrypt(k2.encrypt(k3.decrypt(load(block))));
+        block.clone_from_slice(&p.to_be_bytes());
+    }
+
+    fn block_size(&self) -> usize {
+        8
+    }
+
+    fn change_padding(&mut self, padding: super::Padding) {
+        self.padding = padding;
+    }
+
+    fn padding(&self) -> super::Padding {
+        self.padding
+    }
+
+    fn get_key(&self) -> &[u8] {
+        self.key.bytes()
+    }
+
+    fn set_key(&mut self, key: &[u8]) -> Result<(), super::CryptoError> {
+        use std::convert::TryFrom;
+        self.key = TDEAKey::try_from(super::CipherKey { key })?;
+        self.schedules = self.key.schedules();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::tests::hex;
+    use crate::crypt::{Cipher, Padding};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn des() {
+        let key = hex("133457799bbcdff1");
+        let des = DES::new(DESKey::try_from(crate::crypt::CipherKey { key: &key }).unwrap());
+        let mut block = hex("0123456789abcdef");
+        des.encrypt_block(&mut block);
+        assert_eq!(block, hex("85e813540f0ab405"));
+        des.decrypt_block(&mut block);
+        assert_eq!(block, hex("0123456789abcdef"));
+    }
+
+    #[test]
+    fn tdea() {
+        // the example of SP 800-67
+        let key = hex("0123456789abcdef 23456789abcdef01 456789abcdef0123");
+        let tdea = TDEA::new(TDEAKey::try_from(crate::crypt::CipherKey { key: &key }).unwrap());
+        let plaintext = b"The qufck brown fox jump".to_vec();
+        let mut message = plaintext.clone();
+        tdea.ecb_encrypt(&[], &mut message).unwrap();
+        assert_eq!(
+            message,
+            hex("a826fd8ce53b855fcce21c8112256fe668d5c05dd9b6b900")
+        );
+        tdea.ecb_decrypt(&[], &mut message).unwrap();
+        assert_eq!(message, plaintext);
+    }
+
+    #[test]
+    fn two_key() {
+        // the same as three keys where the third is the first
+        let key = hex("0123456789abcdef23456789abcdef01");
+        let mut three = key.clone();
+        three.extend_from_slice(&key[..8]);
+        let two_key = TDEA::new(TDEAKey::try_from(crate::crypt::CipherKey { key: &key }).unwrap());
+        let three_key =
+            TDEA::new(TDEAKey::try_from(crate::crypt::CipherKey { key: &three }).unwrap());
+
+        let mut a = hex("0011223344556677");
+        let mut b = a.clone();
+        two_key.encrypt_block(&mut a);
+        three_key.encrypt_block(&mut b);
+        assert_eq!(a, b);
+        assert_eq!(two_key.get_key(), &key[..]);
+    }
+
+    #[test]
+    fn cbc() {
+        // openssl enc -des-ede3-cbc -K 0123456789abcdeffedcba987654321089abcdef01234567 -iv 0001020304050607
+        let key = hex("0123456789abcdeffedcba987654321089abcdef01234567");
+        let mut tdea = TDEA::new(TDEAKey::try_from(crate::crypt::CipherKey { key: &key }).unwrap());
+        tdea.change_padding(Padding::PKCS7);
+        let iv = hex("0001020304050607");
+
+        let mut message = b"Lorem ipsum dolor sit amet.".to_vec();
+        tdea.encrypt(&iv, &mut message).unwrap();
+        assert_eq!(
+            message,
+            hex("cf390e2c21e24a391d971ab5edf00255b45fd8e86753b6b8bde7bf3ec865f840")
+        );
+        tdea.decrypt(&iv, &mut message).unwrap();
+        assert_eq!(message, b"Lorem ipsum dolor sit amet.");
+
+        assert!(tdea.decrypt(&hex("00010203"), &mut message).is_err());
+    }
+
+    #[test]
+    fn cmac() {
+        use crate::crypt::mac::{CMAC, MAC};
+        use crate::crypt::CipherTypes;
+
+        // the three key examples of SP 800-38B, D.4
+        let key = hex("8aa83bf8cbda10620bc1bf19fbb6cd58bc313d4a371ca8b5");
+        let cmac = CMAC::new(CipherTypes::TDEA);
+        let vectors = [
+            ("", "b7a688e122ffaf95"),
+            ("6bc1bee22e409f96e93d7e117393172a", "286d394673448197"),
+            (
+                "6bc1bee22e409f96e93d7e117393172aae2d8a57",
+                "743ddbe0ce2dc2ed",
+            ),
+        ];
+        for (text, tag) in vectors.iter() {
+            assert_eq!(cmac.mac(&key, &hex(text), 64).unwrap(), hex(tag));
+        }
+        assert!(cmac.mac(&key, b"", 128).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+//! The EAX mode, CTR encryption and an OMAC (CMAC) of the nonce, header and ciphertext.
+//! The nonce can be of any length.
+//!
+//!     # use codes::crypt::{eax::EAX, twofish::{Twofish, TwofishKey}, AEAD};
+//!     let eax = EAX::new(Twofish::new(TwofishKey::TK256([0x42; 32])), 128).unwrap();
+//!
+//!     let mut message = b"attack at dawn".to_vec();
+//!     eax.encrypt(b"nonce", b"header", &mut message).unwrap();
+//!     assert_eq!(message.len(), 14 + 16);
+//!
+//!     eax.decrypt(b"nonce", b"header", &mut message).unwrap();
+//!     assert_eq!(message, b"attack at dawn");
+
+use super::{mac::CMAC, verify_tag, BlockCipher, CryptoError, AEAD};
+
+pub struct EAX {
+    cipher: Box<dyn BlockCipher>,
+    /// in bytes
+    tag_len: usize,
+}
+
+impl EAX {
+    /// Create an EAX instance of a 128 bit block cipher, the tag length is in bits.
+    pub fn new(cipher: Box<dyn BlockCipher>, tag_len: usize) -> Result<Self, CryptoError> {
+        if cipher.block_size() != 16 {
+            return Err(CryptoError::InvalidParameter(format!(
+                "EAX needs a 128 bit block cipher, not {} bits",
+                cipher.block_size() << 3
+            )));
+        }
+        if tag_len == 0 || tag_len > 128 || tag_len & 7 != 0 {
+            return Err(CryptoError::InvalidTagLength(tag_len));
+        }
+
+        Ok(EAX {
+            cipher,
+            tag_len: tag_len >> 3,
+        })
+    }
+
+    /// OMAC with the tweak t, the CMAC of the block [t] followed by the data.
+    fn omac(&self, t: u8, data: &[u8]) -> Vec<u8> {
+        let mut text = vec![0u8; 16];
+        text[15] = t;
+        text.extend_from_slice(data);
+        CMAC::tag(&*self.cipher, &text)
+    }
+
+    fn tag(&self, n: &[u8], ad: &[u8], ciphertext: &[u8]) -> Vec<u8> {
+        let h = self.omac(1, ad);
+        let c = self.omac(2, ciphertext);
+        n.iter()
+            .zip(h.iter())
+            .zip(c.iter())
+            .map(|((n, h), c)| n ^ h ^ c)
+            .take(self.tag_len)
+            .collect()
+    }
+}
+
+impl AEAD for EAX {
+    fn encrypt(&self, nonce: &[u8], ad: &[u8], plaintext: &mut Vec<u8>) -> Result<(), CryptoError> {
+        let n = self.omac(0, nonce);
+        self.cipher.ctr(&n, plaintext);
+        let mut tag = self.tag(&n, ad, plaintext);
+        plaintext.append(&mut tag);
+        Ok(())
+    }
+
+    fn decrypt(
+        &self,
+        nonce: &[u8],
+        ad: &[u8],
+        ciphertext: &mut Vec<u8>,
+    ) -> Result<(), CryptoError> {
+        if ciphertext.len() < self.tag_len {
+            return Err(CryptoError::InvalidDataLength(ciphertext.len()));
+        }
+        let len = ciphertext.len() - self.tag_len;
+
+        // the tag is of the ciphertext, so it is verified before decrypting
+        let n = self.omac(0, nonce);
+        verify_tag(&self.tag(&n, ad, &ciphertext[..len]), &ciphertext[len..])?;
+
+        ciphertext.truncate(len);
+        self.cipher.ctr(&n, ciphertext);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::aes::{AESKey, AES};
+    use crate::crypt::tests::hex;
+
+    fn aes(key: &str) -> Box<dyn BlockCipher> {
+        let mut k = [0u8; 16];
+        k.clone_from_slice(&hex(key));
+        AES::new(AESKey::AES128(k))
+    }
+
+    #[test]
+    fn paper_vectors() {
+        // (key, nonce, header, message, ciphertext and tag) from appendix E of the EAX paper
+        let vectors = [
+            (
+                "233952DEE4D5ED5F9B9C6D6FF80FF478",
+                "62EC67F9C3A4A407FCB2A8C49031A8B3",
+                "6BFB914FD07EAE6B",
+                "",
+                "E037830E8389F27B025A2D6527E79D01",
+            ),
+            (
+                "91945D3F4DCBEE0BF45EF52255F095A4",
+                "BECAF043B0A23D843194BA972C66DEBD",
+                "FA3BFD4806EB53FA",
+                "F7FB",
+                "19DD5C4C9331049D0BDAB0277408F67967E5",
+            ),
+            (
+                "01F74AD64077F2E704C0F60ADA3DD523",
+                "70C3DB4F0D26368400A10ED05D2BFF5E",
+                "234A3463C1264AC6",
+                "1A47CB4933",
+                "D851D5BAE03A59F238A23E39199DC9266626C40F80",
+            ),
+            (
+                "D07CF6CBB7F313BDDE66B727AFD3C5E8",
+                "8408DFFF3C1A2B1292DC199E46B7D617",
+                "33CCE2EABFF5A79D",
+                "481C9E39B1",
+                "632A9D131AD4C168A4225D8E1FF755939974A7BEDE",
+            ),
+            (
+                "8395FCF1E95BEBD697BD010BC766AAC3",
+                "22E7ADD93CFC6393C57EC0B3C17D6B44",
+                "126735FCC320D25A",
+                "CA40D7446E545FFAED3BD12A740A659FFBBB3CEAB7",
+                "CB8920F87A6C75CFF39627B56E3ED197C552D295A7CFC46AFC253B4652B1AF3795B124AB6E",
+            ),
+        ];
+
+        for (key, nonce, header, message, expected) in vectors.iter() {
+            let eax = EAX::new(aes(key), 128).unwrap();
+            let mut data = hex(message);
+            eax.encrypt(&hex(nonce), &hex(header), &mut data).unwrap();
+            assert_eq!(data, hex(expected));
+            eax.decrypt(&hex(nonce), &hex(header), &mut data).unwrap();
+            assert_eq!(data, hex(message));
+        }
+    }
+
+    #[test]
+    fn tampering() {
+        let eax = EAX::new(aes("000102030405060708090a0b0c0d0e0f"), 96).unwrap();
+        let mut sealed = b"Lorem ipsum dolor sit amet".to_vec();
+        eax.encrypt(b"nonce", b"ad", &mut sealed).unwrap();
+        assert_eq!(sealed.len(), 26 + 12);
+
+        for i in 0..sealed.len() {
+            let mut tampered = sealed.clone();
+            tampered[i] ^= 0x80;
+            assert_eq!(
+                eax.decrypt(b"nonce", b"ad", &mut tampered),
+                Err(CryptoError::AuthenticationFailure)
+            );
+            tampered[i] ^= 0x80;
+            assert_eq!(tampered, sealed);
+        }
+
+        let mut tampered = sealed.clone();
+        assert_eq!(
+            eax.decrypt(b"nonce!", b"ad", &mut tampered),
+            Err(CryptoError::AuthenticationFailure)
+        );
+        assert_eq!(
+            eax.decrypt(b"nonce", b"", &mut tampered),
+            Err(CryptoError::AuthenticationFailure)
+        );
+        assert_eq!(
+            eax.decrypt(b"nonce", b"ad", &mut vec![0; 11]),
+            Err(CryptoError::InvalidDataLength(11))
+        );
+        assert_eq!(
+            EAX::new(aes("000102030405060708090a0b0c0d0e0f"), 129).err(),
+            Some(CryptoError::InvalidTagLength(129))
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use std::fmt;
+
+/// The error returned by everything fallible in the cryptography module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CryptoError {
+    /// The key has a length (in bytes) the algorithm does not support.
+    InvalidKeyLength(usize),
+    /// The input vector or nonce has the wrong length (in bytes).
+    InvalidIvLength { expected: usize, got: usize },
+    /// The length of the data (in bytes) is not supported,
+    /// e.g. a ciphertext that is not a whole number of blocks.
+    InvalidDataLength(usize),
+    /// The padding of the decrypted message is malformed.
+    BadPadding,
+    /// The authentication tag did not match.
+    AuthenticationFailure,
+    /// The tag length (in bits) is not supported.
+    InvalidTagLength(usize),
+    /// The input is not correctly encoded.
+    MalformedEncoding(String),
+    /// A parameter of a mode or algorithm is not supported.
+    InvalidParameter(String),
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use CryptoError::*;
+        match self {
+            InvalidKeyLength(len) => write!(f, "{} is not a valid key length", len),
+            InvalidIvLength { expected, got } => write!(
+                f,
+                "input vector is wrong length, expected {}, got {}",
+                expected, got
+            ),
+            InvalidDataLength(len) => write!(f, "{} is not a valid data length", len),
+            BadPadding => write!(f, "the padding is malformed"),
+            AuthenticationFailure => write!(f, "the authentication tag did not match"),
+            InvalidTagLength(len) => write!(f, "{} is not a valid tag length", len),
+            MalformedEncoding(reason) => write!(f, "malformed encoding: {}", reason),
+            InvalidParameter(reason) => write!(f, "invalid parameter: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for CryptoError {}
@@ -0,0 +1,357 @@
+//! AES-GCM-SIV, a nonce misuse resistant AEAD.
+//! A key for every nonce is derived from the key, and the tag is the encrypted POLYVAL of the
+//! associated data and the plaintext. The tag is then the initial counter of the encryption.
+//! Reusing a nonce only reveals if the same message was encrypted twice.
+//!
+//!     # use codes::crypt::{gcm_siv::GCMSIV, AEAD};
+//!     let gcm_siv = GCMSIV::new(&[0x42; 32]).unwrap();
+//!     let nonce = [0u8; 12];
+//!
+//!     let mut message = b"attack at dawn".to_vec();
+//!     gcm_siv.encrypt(&nonce, b"header", &mut message).unwrap();
+//!     assert_eq!(message.len(), 14 + 16);
+//!
+//!     gcm_siv.decrypt(&nonce, b"header", &mut message).unwrap();
+//!     assert_eq!(message, b"attack at dawn");
+
+use super::aes::{AESKey, AES};
+use super::{verify_tag, BlockCipher, CipherKey, CipherTypes, CryptoError, AEAD};
+
+/// The maximum length of the plaintext and associated data in bytes.
+const MAX_LEN: usize = 1 << 36;
+
+/// x^128 = x^127 + x^126 + x^121 + 1
+const REDUCTION: u128 = (1 << 127) | (1 << 126) | (1 << 121) | 1;
+
+/// Multiplication in the POLYVAL field, without the x^-128 factor.
+/// Bit i of the little endian number is the coefficient of x^i.
+fn field_mul(mut a: u128, b: u128) -> u128 {
+    let mut product = 0;
+    for i in 0..128 {
+        if b >> i & 1 == 1 {
+            product ^= a;
+        }
+        let carry = a >> 127;
+        a <<= 1;
+        if carry == 1 {
+            a ^= REDUCTION;
+        }
+    }
+    product
+}
+
+/// Divide by x in the POLYVAL field.
+fn div_x(a: u128) -> u128 {
+    if a & 1 == 1 {
+        ((a ^ REDUCTION) >> 1) | (1 << 127)
+    } else {
+        a >> 1
+    }
+}
+
+/// The POLYVAL universal hash function.
+pub struct Polyval {
+    /// The key times x^-128, so every step is a single multiplication.
+    h: u128,
+    s: u128,
+}
+
+impl Polyval {
+    pub fn new(key: &[u8; 16]) -> Self {
+        let h = (0..128).fold(u128::from_le_bytes(*key), |h, _| div_x(h));
+        Polyval { h, s: 0 }
+    }
+
+    /// Hash the data, padded with zeros to whole blocks.
+    pub fn update(&mut self, data: &[u8]) {
+        for chunk in data.chunks(16) {
+            let mut block = [0u8; 16];
+            block[..chunk.len()].clone_from_slice(chunk);
+            self.s = field_mul(self.s ^ u128::from_le_bytes(block), self.h);
+        }
+    }
+
+    pub fn finish(&self) -> [u8; 16] {
+        self.s.to_le_bytes()
+    }
+}
+
+pub struct GCMSIV {
+    cipher: Box<dyn BlockCipher>,
+}
+
+impl GCMSIV {
+    /// AES-GCM-SIV with a 16 or 32 byte key.
+    pub fn new(key: &[u8]) -> Result<Self, CryptoError> {
+        if key.len() != 16 && key.len() != 32 {
+            return Err(CryptoError::InvalidKeyLength(key.len()));
+        }
+        Ok(GCMSIV {
+            cipher: CipherTypes::AES.new(CipherKey { key })?,
+        })
+    }
+
+    /// Derive the authentication key and the encryption key of the nonce.
+    fn derive_keys(&self, nonce: &[u8]) -> ([u8; 16], Box<dyn BlockCipher>) {
+        let halves = 2 + self.cipher.get_key().len() / 8;
+        let key: Vec<u8> = (0..halves)
+            .flat_map(|i| {
+                let mut block = [0u8; 16];
+                block[..4].clone_from_slice(&(i as u32).to_le_bytes());
+                block[4..].clone_from_slice(nonce);
+                self.cipher.encrypt_block(&mut block);
+                block[..8].to_vec()
+            })
+            .collect();
+
+        let mut auth_key = [0u8; 16];
+        auth_key.clone_from_slice(&key[..16]);
+        // the encryption key is as long as the key, 16 or 32 bytes
+        let enc = if key.len() == 32 {
+            let mut enc_key = [0u8; 16];
+            enc_key.clone_from_slice(&key[16..]);
+            AES::new(AESKey::AES128(enc_key))
+        } else {
+            let mut enc_key = [0u8; 32];
+            enc_key.clone_from_slice(&key[16..]);
+            AES::new(AESKey::AES256(enc_key))
+        };
+        (auth_key, enc)
+    }
+
+    fn tag(
+        &self,
+        auth_key: &[u8; 16],
+        enc: &dyn BlockCipher,
+        nonce: &[u8],
+        ad: &[u8],
+        plaintext: &[u8],
+    ) -> [u8; 16] {
+        let mut polyval = Polyval::new(auth_key);
+        polyval.update(ad);
+        polyval.update(plaintext);
+        let mut lengths = [0u8; 16];
+        lengths[..8].clone_from_slice(&((ad.len() as u64) << 3).to_le_bytes());
+        lengths[8..].clone_from_slice(&((plaintext.len() as u64) << 3).to_le_bytes());
+        polyval.update(&lengths);
+
+        let mut tag = polyval.finish();
+        for (t, n) in tag.iter_mut().zip(nonce.iter()) {
+            *t ^= n;
+        }
+        tag[15] &= 0x7f;
+        enc.encrypt_block(&mut tag);
+        tag
+    }
+
+    /// Counter mode with a 32 bit little endian counter in the first four bytes,
+    /// starting at the tag with the top bit set.
+    fn ctr(enc: &dyn BlockCipher, tag: &[u8; 16], data: &mut [u8]) {
+        let mut counter = *tag;
+        counter[15] |= 0x80;
+        let mut n = u32::from_le_bytes([counter[0], counter[1], counter[2], counter[3]]);
+
+        for chunk in data.chunks_mut(16) {
+            let mut keystream = counter;
+            keystream[..4].clone_from_slice(&n.to_le_bytes());
+            enc.encrypt_block(&mut keystream);
+            for (d, k) in chunk.iter_mut().zip(keystream.iter()) {
+                *d ^= k;
+            }
+            n = n.wrapping_add(1);
+        }
+    }
+
+    fn check(nonce: &[u8], ad: &[u8], len: usize) -> Result<(), CryptoError> {
+        if nonce.len() != 12 {
+            return Err(CryptoError::InvalidIvLength {
+                expected: 12,
+                got: nonce.len(),
+            });
+        }
+        if ad.len() > MAX_LEN {
+            return Err(CryptoError::InvalidDataLength(ad.len()));
+        }
+        if len > MAX_LEN {
+            return Err(CryptoError::InvalidDataLength(len));
+        }
+        Ok(())
+    }
+}
+
+impl AEAD for GCMSIV {
+    fn encrypt(&self, nonce: &[u8], ad: &[u8], plaintext: &mut Vec<u8>) -> Result<(), CryptoError> {
+        Self::check(nonce, ad, plaintext.len())?;
+
+        let (auth_key, enc) = self.derive_keys(nonce);
+        let tag = self.tag(&auth_key, &*enc, nonce, ad, plaintext);
+        Self::ctr(&*enc, &tag, plaintext);
+        plaintext.extend_from_slice(&tag);
+        Ok(())
+    }
+
+    fn decrypt(
+        &self,
+        nonce: &[u8],
+        ad: &[u8],
+        ciphertext: &mut Vec<u8>,
+    ) -> Result<(), CryptoError> {
+        if ciphertext.len() < 16 {
+            return Err(CryptoError::InvalidDataLength(ciphertext.len()));
+        }
+        let len = ciphertext.len() - 16;
+        Self::check(nonce, ad, len)?;
+
+        let (auth_key, enc) = self.derive_keys(nonce);
+        let mut tag = [0u8; 16];
+        tag.clone_from_slice(&ciphertext[len..]);
+        Self::ctr(&*enc, &tag, &mut ciphertext[..len]);
+
+        let expected = self.tag(&auth_key, &*enc, nonce, ad, &ciphertext[..len]);
+        if let Err(e) = verify_tag(&expected, &tag) {
+            // do not release the unauthenticated plaintext
+            Self::ctr(&*enc, &tag, &mut ciphertext[..len]);
+            return Err(e);
+        }
+
+        ciphertext.truncate(len);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::tests::hex;
+
+    fn check(key: &str, nonce: &str, ad: &str, plaintext: &str, expected: &str) {
+        let gcm_siv = GCMSIV::new(&hex(key)).unwrap();
+        let mut data = hex(plaintext);
+        gcm_siv.encrypt(&hex(nonce), &hex(ad), &mut data).unwrap();
+        assert_eq!(data, hex(expected));
+        gcm_siv.decrypt(&hex(nonce), &hex(ad), &mut data).unwrap();
+        assert_eq!(data, hex(plaintext));
+    }
+
+    #[test]
+    fn polyval() {
+        // RFC 8452 appendix A
+        let mut key = [0u8; 16];
+        key.clone_from_slice(&hex("25629347589242761d31f826ba4b757b"));
+        let mut polyval = Polyval::new(&key);
+        polyval.update(&hex(
+            "4f4f95668c83dfb6401762bb2d01a262 d1a24ddd2721d006bbe45f20d3c9f362",
+        ));
+        assert_eq!(
+            polyval.finish()[..],
+            hex("f7a3b47b846119fae5b7866cf5e5b77e")[..]
+        );
+    }
+
+    #[test]
+    fn rfc8452_aes128() {
+        let key = "01000000000000000000000000000000";
+        let nonce = "030000000000000000000000";
+        check(key, nonce, "", "", "dc20e2d83f25705bb49e439eca56de25");
+        check(
+            key,
+            nonce,
+            "",
+            "0100000000000000",
+            "b5d839330ac7b786578782fff6013b815b287c22493a364c",
+        );
+        check(
+            key,
+            nonce,
+            "",
+            "01000000000000000000000000000000",
+            "743f7c8077ab25f8624e2e948579cf77303aaf90f6fe21199c6068577437a0c4",
+        );
+        check(
+            key,
+            nonce,
+            "01",
+            "01000000000000000000000000000000 02000000000000000000000000000000",
+            "9b12b6f5e3014e4620b29f37daf67dfe 2b358c8157d8cb9e3f35381a256fd4db
+             b5e7a1a1c0529a16215d3c7ef4e1361c",
+        );
+        check(
+            key,
+            nonce,
+            "010000000000000000000000",
+            "02000000",
+            "a8fe3e8707eb1f84fb28f8cb73de8e99e2f48a14",
+        );
+        check(
+            "ee8e1ed9ff2540ae8f2ba9f50bc2f27c",
+            "752abad3e0afb5f434dc4310",
+            "6578616d706c65",
+            "48656c6c6f20776f726c64",
+            "5d349ead175ef6b1def6fd4fbcdeb7e4793f4a1d7e4faa70100af1",
+        );
+    }
+
+    #[test]
+    fn rfc8452_aes256() {
+        let key = "01000000000000000000000000000000 00000000000000000000000000000000";
+        let nonce = "030000000000000000000000";
+        check(key, nonce, "", "", "07f5f4169bbf55a8400cd47ea6fd400f");
+        check(
+            key,
+            nonce,
+            "01",
+            "01000000000000000000000000000000 02000000000000000000000000000000",
+            "5d95eed3fae6512db0c50cd9c48131df 0f798d7f80146e6f34ffc30fff48de78
+             56c6cf502bf77c7df7ef598cf8470b91",
+        );
+
+        // the 32 bit counter wraps around
+        check(
+            &"00".repeat(32),
+            &"00".repeat(12),
+            "",
+            "00000000000000000000000000000000 4db923dc793ee6497c76dcc03a98e108",
+            "f3f80f2cf0cb2dd9c5984fcda908456c c537703b5ba70324a6793a7bf218d3ea
+             ffffffff000000000000000000000000",
+        );
+    }
+
+    #[test]
+    fn tampering() {
+        let gcm_siv = GCMSIV::new(&[9; 16]).unwrap();
+        let nonce = [1u8; 12];
+        let mut sealed = b"Lorem ipsum dolor sit amet".to_vec();
+        gcm_siv.encrypt(&nonce, b"ad", &mut sealed).unwrap();
+
+        for i in 0..sealed.len() {
+            let mut tampered = sealed.clone();
+            tampered[i] ^= 0x20;
+            assert_eq!(
+                gcm_siv.decrypt(&nonce, b"ad", &mut tampered),
+                Err(CryptoError::AuthenticationFailure)
+            );
+            tampered[i] ^= 0x20;
+            assert_eq!(tampered, sealed);
+        }
+
+        assert_eq!(
+            gcm_siv.decrypt(&[2; 12], b"ad", &mut sealed.clone()),
+            Err(CryptoError::AuthenticationFailure)
+        );
+        assert_eq!(
+            gcm_siv.decrypt(&nonce, b"da", &mut sealed.clone()),
+            Err(CryptoError::AuthenticationFailure)
+        );
+        assert_eq!(
+            gcm_siv.encrypt(&[0; 16], b"", &mut vec![]),
+            Err(CryptoError::InvalidIvLength {
+                expected: 12,
+                got: 16
+            })
+        );
+        assert_eq!(
+            GCMSIV::new(&[0; 24]).err(),
+            Some(CryptoError::InvalidKeyLength(24))
+        );
+    }
+}
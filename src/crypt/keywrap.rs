@@ -0,0 +1,306 @@
+//! Key wrapping (KW) and key wrapping with padding (KWP).
+//! Encrypts keys under a key encryption key (KEK) with an integrity check,
+//! so unwrapping with the wrong KEK or a modified wrapped key fails.
+//!
+//!     # use codes::crypt::aes::{AES, AESKey};
+//!     let kek = AES::new(AESKey::AES256([0x42; 32]));
+//!     let key = [7u8; 32];
+//!
+//!     let wrapped = kek.key_wrap(&key).unwrap();
+//!     assert_eq!(wrapped.len(), 40);
+//!     assert_eq!(kek.key_unwrap(&wrapped).unwrap(), key);
+//!
+//!     // any length with padding
+//!     let wrapped = kek.key_wrap_padded(b"odd length").unwrap();
+//!     assert_eq!(kek.key_unwrap_padded(&wrapped).unwrap(), b"odd length");
+
+use super::{verify_tag, BlockCipher, CryptoError};
+
+/// The default initial value of KW.
+const IV: [u8; 8] = [0xa6; 8];
+
+/// The start of the alternative initial value of KWP, followed by the length.
+const AIV: [u8; 4] = [0xa6, 0x59, 0x59, 0xa6];
+
+impl dyn BlockCipher {
+    fn check_kw(&self) -> Result<(), CryptoError> {
+        if self.block_size() != 16 {
+            return Err(CryptoError::InvalidParameter(format!(
+                "key wrap needs a 128 bit block cipher, not {} bits",
+                self.block_size() << 3
+            )));
+        }
+        Ok(())
+    }
+
+    /// The wrapping function W, the semiblocks are wrapped in place.
+    fn w(&self, iv: &[u8; 8], semiblocks: &mut [u8]) -> [u8; 8] {
+        let n = semiblocks.len() / 8;
+        let mut a = *iv;
+        let mut block = [0u8; 16];
+        for j in 0..6 {
+            for (i, r) in semiblocks.chunks_mut(8).enumerate() {
+                block[..8].clone_from_slice(&a);
+                block[8..].clone_from_slice(r);
+                self.encrypt_block(&mut block);
+
+                let t = ((n * j + i + 1) as u64).to_be_bytes();
+                for ((a, b), t) in a.iter_mut().zip(block[..8].iter()).zip(t.iter()) {
+                    *a = b ^ t;
+                }
+                r.clone_from_slice(&block[8..]);
+            }
+        }
+        a
+    }
+
+    /// The unwrapping function W^-1, returns the integrity check value.
+    fn w_inv(&self, wrapped_iv: &[u8], semiblocks: &mut [u8]) -> [u8; 8] {
+        let n = semiblocks.len() / 8;
+        let mut a = [0u8; 8];
+        a.clone_from_slice(wrapped_iv);
+        let mut block = [0u8; 16];
+        for j in (0..6).rev() {
+            for (i, r) in semiblocks.chunks_mut(8).enumerate().rev() {
+                let t = ((n * j + i + 1) as u64).to_be_bytes();
+                for ((b, a), t) in block[..8].iter_mut().zip(a.iter()).zip(t.iter()) {
+                    *b = a ^ t;
+                }
+                block[8..].clone_from_slice(r);
+                self.decrypt_block(&mut block);
+
+                a.clone_from_slice(&block[..8]);
+                r.clone_from_slice(&block[8..]);
+            }
+        }
+        a
+    }
+
+    /// Wrap a key with KW, the key has to be a multiple of 8 bytes and at least 16 bytes.
+    /// The wrapped key is 8 bytes longer.
+    pub fn key_wrap(&self, key: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.check_kw()?;
+        if key.len() < 16 || !key.len().is_multiple_of(8) {
+            return Err(CryptoError::InvalidDataLength(key.len()));
+        }
+
+        let mut wrapped = vec![0u8; 8];
+        wrapped.extend_from_slice(key);
+        let a = self.w(&IV, &mut wrapped[8..]);
+        wrapped[..8].clone_from_slice(&a);
+        Ok(wrapped)
+    }
+
+    /// Unwrap a key wrapped with `key_wrap`.
+    /// Returns `CryptoError::AuthenticationFailure` if the integrity check fails, e.g. with the
+    /// wrong key encryption key.
+    pub fn key_unwrap(&self, wrapped: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.check_kw()?;
+        if wrapped.len() < 24 || !wrapped.len().is_multiple_of(8) {
+            return Err(CryptoError::InvalidDataLength(wrapped.len()));
+        }
+
+        let mut key = wrapped[8..].to_vec();
+        let a = self.w_inv(&wrapped[..8], &mut key);
+        verify_tag(&a, &IV)?;
+        Ok(key)
+    }
+
+    /// Wrap a key of any (non-zero) length with KWP.
+    /// The key is padded with zeros to a multiple of 8 bytes, and the wrapped key is 8 bytes
+    /// longer than that.
+    pub fn key_wrap_padded(&self, key: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.check_kw()?;
+        if key.is_empty() || key.len() >> 32 != 0 {
+            return Err(CryptoError::InvalidDataLength(key.len()));
+        }
+
+        let mut iv = [0u8; 8];
+        iv[..4].clone_from_slice(&AIV);
+        iv[4..].clone_from_slice(&(key.len() as u32).to_be_bytes());
+
+        let mut wrapped = iv.to_vec();
+        wrapped.extend_from_slice(key);
+        wrapped.resize(8 + key.len().div_ceil(8) * 8, 0);
+
+        if wrapped.len() == 16 {
+            // a single block is just encrypted
+            self.encrypt_block(&mut wrapped);
+        } else {
+            let a = self.w(&iv, &mut wrapped[8..]);
+            wrapped[..8].clone_from_slice(&a);
+        }
+        Ok(wrapped)
+    }
+
+    /// Unwrap a key wrapped with `key_wrap_padded`.
+    /// Returns `CryptoError::AuthenticationFailure` if the integrity check fails, e.g. with the
+    /// wrong key encryption key.
+    pub fn key_unwrap_padded(&self, wrapped: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.check_kw()?;
+        if wrapped.len() < 16 || !wrapped.len().is_multiple_of(8) {
+            return Err(CryptoError::InvalidDataLength(wrapped.len()));
+        }
+
+        let (a, mut key) = if wrapped.len() == 16 {
+            let mut block = wrapped.to_vec();
+            self.decrypt_block(&mut block);
+            let key = block.split_off(8);
+            (block, key)
+        } else {
+            let mut key = wrapped[8..].to_vec();
+            let a = self.w_inv(&wrapped[..8], &mut key);
+            (a.to_vec(), key)
+        };
+
+        // the alternative initial value, the length and the padding are all checked
+        let mut len = [0u8; 4];
+        len.clone_from_slice(&a[4..]);
+        let len = u32::from_be_bytes(len) as usize;
+        let valid_len = len <= key.len() && len + 8 > key.len();
+        if verify_tag(&a[..4], &AIV).is_err() || !valid_len || key[len..].iter().any(|b| *b != 0) {
+            return Err(CryptoError::AuthenticationFailure);
+        }
+
+        key.truncate(len);
+        Ok(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::aes::{AESKey, AES};
+    use crate::crypt::tests::hex;
+    use crate::crypt::CipherKey;
+    use crate::crypt::CipherTypes;
+
+    fn kek(key: &[u8]) -> Box<dyn BlockCipher> {
+        CipherTypes::AES.new(CipherKey { key }).unwrap()
+    }
+
+    #[test]
+    fn rfc3394_vectors() {
+        let key = hex("00112233445566778899AABBCCDDEEFF 000102030405060708090A0B0C0D0E0F");
+        for (kek_len, key_len, expected) in &[
+            (16, 16, "1FA68B0A8112B447 AEF34BD8FB5A7B82 9D3E862371D2CFE5"),
+            (24, 16, "96778B25AE6CA435 F92B5B97C050AED2 468AB8A17AD84E5D"),
+            (32, 16, "64E8C3F9CE0F5BA2 63E9777905818A2A 93C8191E7D6E8AE7"),
+            (
+                24,
+                24,
+                "031D33264E15D332 68F24EC260743EDC E1C6C7DDEE725A93 6BA814915C6762D2",
+            ),
+            (
+                32,
+                24,
+                "A8F9BC1612C68B3F F6E6F4FBE30E71E4 769C8B80A32CB895 8CD5D17D6B254DA1",
+            ),
+            (
+                32,
+                32,
+                "28C9F404C4B810F4 CBCCB35CFB87F826 3F5786E2D80ED326 CBC7F0E71A99F43B
+                 FB988B9B7A02DD21",
+            ),
+        ] {
+            let kek = kek(&(0..*kek_len).collect::<Vec<u8>>());
+            let wrapped = kek.key_wrap(&key[..*key_len]).unwrap();
+            assert_eq!(wrapped, hex(expected));
+            assert_eq!(kek.key_unwrap(&wrapped).unwrap(), key[..*key_len]);
+        }
+    }
+
+    #[test]
+    fn rfc5649_vectors() {
+        let kek = kek(&hex("5840df6e29b02af1ab493b705bf16ea1ae8338f4dcc176a8"));
+
+        let key = hex("c37b7e6492584340bed12207808941155068f738");
+        let wrapped = kek.key_wrap_padded(&key).unwrap();
+        assert_eq!(
+            wrapped,
+            hex("138bdeaa9b8fa7fc61f97742e72248ee5ae6ae5360d1ae6a5f54f373fa543b6a")
+        );
+        assert_eq!(kek.key_unwrap_padded(&wrapped).unwrap(), key);
+
+        let key = hex("466f7250617369");
+        let wrapped = kek.key_wrap_padded(&key).unwrap();
+        assert_eq!(wrapped, hex("afbeb0f07dfbf5419200f2ccb50bb24f"));
+        assert_eq!(kek.key_unwrap_padded(&wrapped).unwrap(), key);
+    }
+
+    #[test]
+    fn wrong_kek() {
+        let kek = AES::new(AESKey::AES128([1; 16]));
+        let other = AES::new(AESKey::AES128([2; 16]));
+        let key: Vec<u8> = (0..32).collect();
+
+        let wrapped = kek.key_wrap(&key).unwrap();
+        assert_eq!(
+            other.key_unwrap(&wrapped),
+            Err(CryptoError::AuthenticationFailure)
+        );
+
+        for len in 1..40 {
+            let wrapped = kek.key_wrap_padded(&key[..len.min(32)]).unwrap();
+            assert_eq!(
+                other.key_unwrap_padded(&wrapped),
+                Err(CryptoError::AuthenticationFailure)
+            );
+        }
+    }
+
+    #[test]
+    fn tampering() {
+        let kek = AES::new(AESKey::AES192([3; 24]));
+        let key: Vec<u8> = (0..24).collect();
+
+        let wrapped = kek.key_wrap(&key).unwrap();
+        for i in 0..wrapped.len() {
+            let mut tampered = wrapped.clone();
+            tampered[i] ^= 1;
+            assert_eq!(
+                kek.key_unwrap(&tampered),
+                Err(CryptoError::AuthenticationFailure)
+            );
+        }
+
+        let wrapped = kek.key_wrap_padded(&key[..21]).unwrap();
+        for i in 0..wrapped.len() {
+            let mut tampered = wrapped.clone();
+            tampered[i] ^= 1;
+            assert_eq!(
+                kek.key_unwrap_padded(&tampered),
+                Err(CryptoError::AuthenticationFailure)
+            );
+        }
+
+        // a KW wrapped key is not a valid KWP wrapped key, and the other way around
+        assert!(kek.key_unwrap_padded(&kek.key_wrap(&key).unwrap()).is_err());
+        assert!(kek.key_unwrap(&kek.key_wrap_padded(&key).unwrap()).is_err());
+    }
+
+    #[test]
+    fn invalid_lengths() {
+        let kek = AES::new(AESKey::AES128([0; 16]));
+        assert_eq!(
+            kek.key_wrap(&[0; 8]),
+            Err(CryptoError::InvalidDataLength(8))
+        );
+        assert_eq!(
+            kek.key_wrap(&[0; 20]),
+            Err(CryptoError::InvalidDataLength(20))
+        );
+        assert_eq!(
+            kek.key_unwrap(&[0; 16]),
+            Err(CryptoError::InvalidDataLength(16))
+        );
+        assert_eq!(
+            kek.key_wrap_padded(&[]),
+            Err(CryptoError::InvalidDataLength(0))
+        );
+        assert_eq!(
+            kek.key_unwrap_padded(&[0; 20]),
+            Err(CryptoError::InvalidDataLength(20))
+        );
+    }
+}
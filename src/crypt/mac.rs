@@ -1,8 +1,15 @@
 use super::sha::*;
-use super::{CipherKey, CipherTypes};
+use super::{BlockCipher, CipherKey, CipherTypes, CryptoError};
 
 pub trait MAC {
-    fn mac(&self, key: &[u8], text: &[u8], tag_len: usize) -> Vec<u8>;
+    /// Compute the tag of the text, tag len is in bits.
+    fn mac(&self, key: &[u8], text: &[u8], tag_len: usize) -> Result<Vec<u8>, CryptoError>;
+
+    /// Verify the tag of the text, the tag length is the length of the given tag.
+    fn verify(&self, key: &[u8], text: &[u8], tag: &[u8]) -> Result<(), CryptoError> {
+        let expected = self.mac(key, text, tag.len() << 3)?;
+        super::verify_tag(&expected, tag)
+    }
 }
 
 pub struct CMAC {
@@ -14,91 +21,90 @@ impl CMAC {
         Self { cipher }
     }
 
-    fn subk(&self, cipher: &dyn super::BlockCipher) -> (Box<[u8]>, Box<[u8]>) {
-        let mut rb = [0u8; 16];
-        rb[15] = 0b10000111;
-        let mut block = [0u8; 16];
-        // self.cipher.encrypt_block(&mut block);
-        cipher.encrypt_block(&mut block);
-        let mut k1 = block;
+    fn subk(cipher: &dyn BlockCipher) -> (Vec<u8>, Vec<u8>) {
+        let mut k1 = vec![0u8; cipher.block_size()];
+        cipher.encrypt_block(&mut k1);
+        double(&mut k1);
+        let mut k2 = k1.clone();
+        double(&mut k2);
 
-        let mut carry_over = 0;
-        for i in (0..k1.len()).rev() {
-            let next_carry_over = k1[i] >> 7;
+        (k1, k2)
+    }
+}
 
-            k1[i] <<= 1;
-            k1[i] |= carry_over;
+/// Multiply by x in GF(2^64) or GF(2^128), where the block is a big endian number.
+fn double(block: &mut [u8]) {
+    // the low terms of x^64 + x^4 + x^3 + x + 1 and x^128 + x^7 + x^2 + x + 1, from SP 800-38B
+    let rb = if block.len() == 8 { 0b11011 } else { 0b10000111 };
+    let msb = block[0] >> 7;
 
-            carry_over = next_carry_over;
-        }
+    let mut carry_over = 0;
+    for b in block.iter_mut().rev() {
+        let next_carry_over = *b >> 7;
 
-        if block[0] >> 7 == 1 {
-            for (k, b) in k1.iter_mut().zip(rb.iter()) {
-                *k ^= *b;
-            }
-        }
+        *b <<= 1;
+        *b |= carry_over;
 
-        let mut k2 = k1;
+        carry_over = next_carry_over;
+    }
 
-        let mut carry_over = 0;
-        for i in (0..k2.len()).rev() {
-            let next_carry_over = k2[i] >> 7;
+    if msb == 1 {
+        block[block.len() - 1] ^= rb;
+    }
+}
 
-            k2[i] <<= 1;
-            k2[i] |= carry_over;
+/// Multiply by x in GF(2^128), where the block is a big endian number.
+pub(crate) fn dbl(block: &[u8; 16]) -> [u8; 16] {
+    let mut doubled = *block;
+    double(&mut doubled);
+    doubled
+}
 
-            carry_over = next_carry_over;
-        }
+impl CMAC {
+    /// The full block tag of the text with an already initialized cipher.
+    pub(crate) fn tag(cipher: &dyn BlockCipher, text: &[u8]) -> Vec<u8> {
+        let bs = cipher.block_size();
+        let (k1, k2) = Self::subk(cipher);
+
+        // the empty text is one incomplete block
+        let length = text.len().div_ceil(bs).max(1);
+
+        let mut cn_1 = vec![0u8; bs];
+        for n in 0..length {
+            let m = &text[n * bs..text.len().min((n + 1) * bs)];
+            for (c, m) in cn_1.iter_mut().zip(m.iter()) {
+                *c ^= *m;
+            }
 
-        if k1[0] >> 7 == 1 {
-            for (k, b) in k2.iter_mut().zip(rb.iter()) {
-                *k ^= *b;
+            if n + 1 == length {
+                if m.len() < bs {
+                    cn_1[m.len()] ^= 1 << 7;
+                }
+                for (c, k) in cn_1
+                    .iter_mut()
+                    .zip(if m.len() < bs { &k2 } else { &k1 }.iter())
+                {
+                    *c ^= *k;
+                }
             }
+
+            cipher.encrypt_block(&mut cn_1);
         }
 
-        (Box::new(k1), Box::new(k2))
+        cn_1
     }
 }
 
 impl MAC for CMAC {
     /// tag len should be in bits
-    fn mac(&self, key: &[u8], text: &[u8], tag_len: usize) -> Vec<u8> {
-        let mut tmp_block = [0u8; 1];
-        tmp_block[0] = 1 << 7;
-        let cipher = self.cipher.new(CipherKey { key }).unwrap();
-        let pt = if text.is_empty() { &tmp_block } else { text };
-
-        let length = (pt.len() >> 4) + if pt.len() & 15 == 0 { 0 } else { 1 };
-
-        let subkeys = self.subk(&*cipher);
-
-        Vec::from(
-            &pt.chunks(16)
-                .enumerate()
-                .fold([0u8; 16], |mut cn_1, (n, m)| {
-                    let mut tmp = [0u8; 16];
-                    tmp[..m.len()].clone_from_slice(&m[..]);
-                    if length == n + 1 {
-                        if m.len() < 16 && !text.is_empty() {
-                            tmp[m.len()] = 1 << 7;
-                        }
-                        for (m, b) in tmp
-                            .iter_mut()
-                            .zip(if m.len() < 16 { &subkeys.1 } else { &subkeys.0 }.iter())
-                        {
-                            *m ^= *b;
-                        }
-                    }
-
-                    for (m, b) in cn_1.iter_mut().zip(tmp.iter()) {
-                        *m ^= *b;
-                    }
-
-                    (&cipher).encrypt_block(&mut cn_1);
-
-                    cn_1
-                })[..(tag_len >> 3)],
-        )
+    fn mac(&self, key: &[u8], text: &[u8], tag_len: usize) -> Result<Vec<u8>, CryptoError> {
+        let cipher = self.cipher.new(CipherKey { key })?;
+        if tag_len == 0 || tag_len > cipher.block_size() << 3 || tag_len & 7 != 0 {
+            return Err(CryptoError::InvalidTagLength(tag_len));
+        }
+        let mut tag = Self::tag(&*cipher, text);
+        tag.truncate(tag_len >> 3);
+        Ok(tag)
     }
 }
 
@@ -122,12 +128,11 @@ impl HMAC {
 
 impl MAC for HMAC {
     // Tag len is in bits
-    fn mac(&self, key: &[u8], text: &[u8], tag_len: usize) -> Vec<u8> {
+    fn mac(&self, key: &[u8], text: &[u8], tag_len: usize) -> Result<Vec<u8>, CryptoError> {
         let mut k_0 = key.to_owned();
-        debug_assert!(
-            tag_len <= self.hash.size(),
-            "tag length exceeds hash length"
-        );
+        if tag_len == 0 || tag_len > self.hash.size() || tag_len & 7 != 0 {
+            return Err(CryptoError::InvalidTagLength(tag_len));
+        }
 
         if k_0.len() > self.hash.block_size() >> 3 {
             let res: Box<[u8]> = self.hash.hash(k_0);
@@ -171,7 +176,113 @@ impl MAC for HMAC {
 
         let mac = self.hash.hash(kxoropad);
 
-        mac[0..(tag_len >> 3)].to_vec()
+        Ok(mac[0..(tag_len >> 3)].to_vec())
+    }
+}
+
+/// The one-time authenticator Poly1305, by [RFC 8439](https://tools.ietf.org/html/rfc8439).
+/// A key must never authenticate more than one message, so it is usually derived from a stream
+/// cipher and the nonce of the message. The key is 256 bits, and the tag is 128 bits.
+pub struct Poly1305;
+
+impl Poly1305 {
+    /// Compute the tag of the text.
+    pub fn tag(key: &[u8; 32], text: &[u8]) -> [u8; 16] {
+        const M44: u64 = (1 << 44) - 1;
+        const M42: u64 = (1 << 42) - 1;
+
+        let le = |b: &[u8]| {
+            let mut tmp = [0u8; 8];
+            tmp.clone_from_slice(b);
+            u64::from_le_bytes(tmp)
+        };
+
+        // r is clamped, and split into limbs of 44, 44 and 42 bits
+        let (t0, t1) = (le(&key[0..8]), le(&key[8..16]));
+        let r0 = (t0 & 0xffc0fffffff) as u128;
+        let r1 = (((t0 >> 44) | (t1 << 20)) & 0xfffffc0ffff) as u128;
+        let r2 = ((t1 >> 24) & 0x00ffffffc0f) as u128;
+        // 2^130 = 5 (mod p), and the limbs are shifted 2 bits extra
+        let (s1, s2) = (r1 * 20, r2 * 20);
+
+        let mut h = [0u64; 3];
+        for chunk in text.chunks(16) {
+            // the last block is padded with a one and zeros, a full block has the one at 2^128
+            let mut block = [0u8; 16];
+            block[..chunk.len()].clone_from_slice(chunk);
+            let hibit = if chunk.len() == 16 {
+                1 << 40
+            } else {
+                block[chunk.len()] = 1;
+                0
+            };
+
+            let (t0, t1) = (le(&block[0..8]), le(&block[8..16]));
+            h[0] += t0 & M44;
+            h[1] += ((t0 >> 44) | (t1 << 20)) & M44;
+            h[2] += ((t1 >> 24) & M42) | hibit;
+
+            // h *= r (mod 2^130 - 5)
+            let (h0, h1, h2) = (h[0] as u128, h[1] as u128, h[2] as u128);
+            let d0 = h0 * r0 + h1 * s2 + h2 * s1;
+            let d1 = h0 * r1 + h1 * r0 + h2 * s2 + (d0 >> 44);
+            let d2 = h0 * r2 + h1 * r1 + h2 * r0 + (d1 >> 44);
+            h[0] = (d0 as u64) & M44;
+            h[1] = (d1 as u64) & M44;
+            h[2] = (d2 as u64) & M42;
+            h[0] += ((d2 >> 42) as u64) * 5;
+            h[1] += h[0] >> 44;
+            h[0] &= M44;
+        }
+
+        // carry fully
+        for _ in 0..2 {
+            h[2] += h[1] >> 44;
+            h[1] &= M44;
+            h[0] += (h[2] >> 42) * 5;
+            h[2] &= M42;
+            h[1] += h[0] >> 44;
+            h[0] &= M44;
+        }
+
+        // g = h - p, used if it is not negative
+        let mut g = [0u64; 3];
+        g[0] = h[0] + 5;
+        g[1] = h[1] + (g[0] >> 44);
+        g[0] &= M44;
+        g[2] = (h[2] + (g[1] >> 44)).wrapping_sub(1 << 42);
+        g[1] &= M44;
+        let mask = (g[2] >> 63).wrapping_sub(1);
+        for (h, g) in h.iter_mut().zip(g.iter()) {
+            *h = (*h & !mask) | (g & mask);
+        }
+
+        // add s
+        let (t0, t1) = (le(&key[16..24]), le(&key[24..32]));
+        h[0] += t0 & M44;
+        h[1] += (((t0 >> 44) | (t1 << 20)) & M44) + (h[0] >> 44);
+        h[0] &= M44;
+        h[2] += ((t1 >> 24) & M42) + (h[1] >> 44);
+        h[1] &= M44;
+
+        let mut tag = [0u8; 16];
+        tag[..8].clone_from_slice(&(h[0] | (h[1] << 44)).to_le_bytes());
+        tag[8..].clone_from_slice(&((h[1] >> 20) | (h[2] << 24)).to_le_bytes());
+        tag
+    }
+}
+
+impl MAC for Poly1305 {
+    fn mac(&self, key: &[u8], text: &[u8], tag_len: usize) -> Result<Vec<u8>, CryptoError> {
+        if key.len() != 32 {
+            return Err(CryptoError::InvalidKeyLength(key.len()));
+        }
+        if tag_len != 128 {
+            return Err(CryptoError::InvalidTagLength(tag_len));
+        }
+        let mut k = [0u8; 32];
+        k.clone_from_slice(key);
+        Ok(Self::tag(&k, text).to_vec())
     }
 }
 
@@ -184,7 +295,7 @@ mod tests {
         let input = b"Sample message for keylen=blocklen".to_vec();
         let key: Vec<u8> = (0..0x80).collect();
         let hmac = HMAC::default();
-        let mac = hmac.mac(&key[..], &input, 512);
+        let mac = hmac.mac(&key[..], &input, 512).unwrap();
         let exp = [
             0xFC, 0x25, 0xE2, 0x40, 0x65, 0x8C, 0xA7, 0x85, 0xB7, 0xA8, 0x11, 0xA8, 0xD3, 0xF7,
             0xB4, 0xCA, 0x48, 0xCF, 0xA2, 0x6A, 0x8A, 0x36, 0x6B, 0xF2, 0xCD, 0x1F, 0x83, 0x6B,
@@ -200,7 +311,7 @@ mod tests {
         let input = b"Sample message for keylen<blocklen".to_vec();
         let key: Vec<u8> = (0..0x40).collect();
         let hmac = HMAC::default();
-        let mac = hmac.mac(&key[..], &input, 512);
+        let mac = hmac.mac(&key[..], &input, 512).unwrap();
         let exp = [
             0xFD, 0x44, 0xC1, 0x8B, 0xDA, 0x0B, 0xB0, 0xA6, 0xCE, 0x0E, 0x82, 0xB0, 0x31, 0xBF,
             0x28, 0x18, 0xF6, 0x53, 0x9B, 0xD5, 0x6E, 0xC0, 0x0B, 0xDC, 0x10, 0xA8, 0xA2, 0xD7,
@@ -216,7 +327,7 @@ mod tests {
         let input = b"Sample message for keylen=blocklen".to_vec();
         let key: Vec<u8> = (0..0xC8).collect();
         let hmac = HMAC::default();
-        let mac = hmac.mac(&key[..], &input, 512);
+        let mac = hmac.mac(&key[..], &input, 512).unwrap();
         let exp = [
             0xD9, 0x3E, 0xC8, 0xD2, 0xDE, 0x1A, 0xD2, 0xA9, 0x95, 0x7C, 0xB9, 0xB8, 0x3F, 0x14,
             0xE7, 0x6A, 0xD6, 0xB5, 0xE0, 0xCC, 0xE2, 0x85, 0x07, 0x9A, 0x12, 0x7D, 0x3B, 0x14,
@@ -232,7 +343,7 @@ mod tests {
         let input = b"Sample message for keylen<blocklen, with truncated tag".to_vec();
         let key: Vec<u8> = (0..0x31).collect();
         let hmac = HMAC::default();
-        let mac = hmac.mac(&key[..], &input, 256);
+        let mac = hmac.mac(&key[..], &input, 256).unwrap();
         let exp = [
             0x00, 0xF3, 0xE9, 0xA7, 0x7B, 0xB0, 0xF0, 0x6D, 0xE1, 0x5F, 0x16, 0x06, 0x03, 0xE4,
             0x2B, 0x50, 0x28, 0x75, 0x88, 0x08, 0x59, 0x66, 0x64, 0xC0, 0x3E, 0x1A, 0xB8, 0xFB,
@@ -253,7 +364,7 @@ mod tests {
         ];
         let message = [];
         let cmac = CMAC::new(CipherTypes::AES);
-        let mac = cmac.mac(&key[..], &message[..], 128);
+        let mac = cmac.mac(&key[..], &message[..], 128).unwrap();
         assert_eq!(mac, expected);
     }
 
@@ -272,7 +383,7 @@ mod tests {
             0x17, 0x2A,
         ];
         let cmac = CMAC::new(CipherTypes::AES);
-        let mac = cmac.mac(&key[..], &message[..], 128);
+        let mac = cmac.mac(&key[..], &message[..], 128).unwrap();
         assert_eq!(mac, expected);
     }
 
@@ -291,7 +402,7 @@ mod tests {
             0x17, 0x2A, 0xAE, 0x2D, 0x8A, 0x57,
         ];
         let cmac = CMAC::new(CipherTypes::AES);
-        let mac = cmac.mac(&key[..], &message[..], 128);
+        let mac = cmac.mac(&key[..], &message[..], 128).unwrap();
         assert_eq!(mac, expected);
     }
 
@@ -313,7 +424,7 @@ mod tests {
             0xAD, 0x2B, 0x41, 0x7B, 0xE6, 0x6C, 0x37, 0x10,
         ];
         let cmac = CMAC::new(CipherTypes::AES);
-        let mac = cmac.mac(&key[..], &message[..], 128);
+        let mac = cmac.mac(&key[..], &message[..], 128).unwrap();
         assert_eq!(mac, expected);
     }
 
@@ -329,7 +440,7 @@ mod tests {
         ];
         let message = [];
         let cmac = CMAC::new(CipherTypes::AES);
-        let mac = cmac.mac(&key[..], &message[..], 128);
+        let mac = cmac.mac(&key[..], &message[..], 128).unwrap();
         assert_eq!(mac, expected);
     }
 
@@ -348,7 +459,7 @@ mod tests {
             0x17, 0x2A,
         ];
         let cmac = CMAC::new(CipherTypes::AES);
-        let mac = cmac.mac(&key[..], &message[..], 128);
+        let mac = cmac.mac(&key[..], &message[..], 128).unwrap();
         assert_eq!(mac, expected);
     }
 
@@ -367,7 +478,7 @@ mod tests {
             0x17, 0x2A, 0xAE, 0x2D, 0x8A, 0x57,
         ];
         let cmac = CMAC::new(CipherTypes::AES);
-        let mac = cmac.mac(&key[..], &message[..], 128);
+        let mac = cmac.mac(&key[..], &message[..], 128).unwrap();
         assert_eq!(mac, expected);
     }
 
@@ -389,7 +500,7 @@ mod tests {
             0xAD, 0x2B, 0x41, 0x7B, 0xE6, 0x6C, 0x37, 0x10,
         ];
         let cmac = CMAC::new(CipherTypes::AES);
-        let mac = cmac.mac(&key[..], &message[..], 128);
+        let mac = cmac.mac(&key[..], &message[..], 128).unwrap();
         assert_eq!(mac, expected);
     }
 
@@ -406,7 +517,7 @@ mod tests {
         ];
         let message = [];
         let cmac = CMAC::new(CipherTypes::AES);
-        let mac = cmac.mac(&key[..], &message[..], 128);
+        let mac = cmac.mac(&key[..], &message[..], 128).unwrap();
         assert_eq!(mac, expected);
     }
 
@@ -426,7 +537,7 @@ mod tests {
             0x17, 0x2A,
         ];
         let cmac = CMAC::new(CipherTypes::AES);
-        let mac = cmac.mac(&key[..], &message[..], 128);
+        let mac = cmac.mac(&key[..], &message[..], 128).unwrap();
         assert_eq!(mac, expected);
     }
 
@@ -446,7 +557,7 @@ mod tests {
             0x17, 0x2A, 0xAE, 0x2D, 0x8A, 0x57,
         ];
         let cmac = CMAC::new(CipherTypes::AES);
-        let mac = cmac.mac(&key[..], &message[..], 128);
+        let mac = cmac.mac(&key[..], &message[..], 128).unwrap();
         assert_eq!(mac, expected);
     }
 
@@ -469,7 +580,188 @@ mod tests {
             0xAD, 0x2B, 0x41, 0x7B, 0xE6, 0x6C, 0x37, 0x10,
         ];
         let cmac = CMAC::new(CipherTypes::AES);
-        let mac = cmac.mac(&key[..], &message[..], 128);
+        let mac = cmac.mac(&key[..], &message[..], 128).unwrap();
         assert_eq!(mac, expected);
     }
+
+    #[test]
+    fn invalid_tag_length() {
+        let hmac = HMAC::default();
+        assert_eq!(
+            hmac.mac(b"key", b"text", 520),
+            Err(CryptoError::InvalidTagLength(520))
+        );
+        assert_eq!(
+            hmac.mac(b"key", b"text", 0),
+            Err(CryptoError::InvalidTagLength(0))
+        );
+        let cmac = CMAC::new(CipherTypes::AES);
+        assert_eq!(
+            cmac.mac(&[0u8; 16], b"text", 136),
+            Err(CryptoError::InvalidTagLength(136))
+        );
+        assert_eq!(
+            cmac.mac(&[0u8; 16], b"text", 100),
+            Err(CryptoError::InvalidTagLength(100))
+        );
+    }
+
+    #[test]
+    fn cmac_invalid_key() {
+        let cmac = CMAC::new(CipherTypes::Twofish);
+        assert_eq!(
+            cmac.mac(&[0u8; 15], b"text", 128),
+            Err(CryptoError::InvalidKeyLength(15))
+        );
+    }
+
+    #[test]
+    fn verify() {
+        let hmac = HMAC::default();
+        let tag = hmac.mac(b"key", b"text", 256).unwrap();
+        assert_eq!(hmac.verify(b"key", b"text", &tag), Ok(()));
+        assert_eq!(
+            hmac.verify(b"key", b"texT", &tag),
+            Err(CryptoError::AuthenticationFailure)
+        );
+        assert_eq!(
+            hmac.verify(b"kei", b"text", &tag),
+            Err(CryptoError::AuthenticationFailure)
+        );
+    }
+
+    #[test]
+    fn hmac_legacy() {
+        use crate::crypt::tests::hex;
+
+        // RFC 2202 for MD5 and SHA-1, and RFC 2286 for RIPEMD-160
+        let vectors = [
+            (
+                HMAC::new(HashAlg::Md5),
+                vec![0x0b; 16],
+                &b"Hi There"[..],
+                "9294727a3638bb1c13f48ef8158bfc9d",
+            ),
+            (
+                HMAC::new(HashAlg::Md5),
+                b"Jefe".to_vec(),
+                b"what do ya want for nothing?",
+                "750c783e6ab0b503eaa86e310a5db738",
+            ),
+            (
+                HMAC::new(HashAlg::Sha1),
+                vec![0x0b; 20],
+                b"Hi There",
+                "b617318655057264e28bc0b6fb378c8ef146be00",
+            ),
+            (
+                HMAC::new(HashAlg::Sha1),
+                b"Jefe".to_vec(),
+                b"what do ya want for nothing?",
+                "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79",
+            ),
+            (
+                HMAC::new(HashAlg::Sha1),
+                vec![0xaa; 80],
+                b"Test Using Larger Than Block-Size Key - Hash Key First",
+                "aa4ae5e15272d00e95705637ce8a3b55ed402112",
+            ),
+            (
+                HMAC::new(HashAlg::Ripemd160),
+                vec![0x0b; 20],
+                b"Hi There",
+                "24cb4bd67d20fc1a5d2ed7732dcc39377f0a5668",
+            ),
+            (
+                HMAC::new(HashAlg::Ripemd160),
+                b"Jefe".to_vec(),
+                b"what do ya want for nothing?",
+                "dda6c0213a485a9e24f4742064a7f033b43c4069",
+            ),
+        ];
+        for (hmac, key, text, tag) in vectors.iter() {
+            let tag = hex(tag);
+            assert_eq!(hmac.mac(key, text, tag.len() << 3).unwrap(), tag);
+        }
+
+        // the first HOTP value of RFC 4226, which TOTP uses with the time as the counter
+        let hmac = HMAC::new(HashAlg::Sha1);
+        assert_eq!(
+            hmac.mac(b"12345678901234567890", &0u64.to_be_bytes(), 160)
+                .unwrap(),
+            hex("cc93cf18508d94934c64b65d8ba7667fb7cde4b0")
+        );
+        assert_eq!(
+            hmac.mac(b"key", b"text", 168),
+            Err(CryptoError::InvalidTagLength(168))
+        );
+    }
+
+    #[test]
+    fn poly1305_rfc8439() {
+        use crate::crypt::tests::hex;
+
+        // section 2.5.2
+        let key = hex("85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b");
+        let text = b"Cryptographic Forum Research Group";
+        let tag = Poly1305.mac(&key, text, 128).unwrap();
+        assert_eq!(tag, hex("a8061dc1305136c6c22b8baf0c0127a9"));
+        assert!(Poly1305.verify(&key, text, &tag).is_ok());
+        assert!(Poly1305.verify(&key, b"Cryptographic Forum Research Grou", &tag).is_err());
+
+        // appendix A.3, vectors 5 to 11 test the final reduction
+        let vectors = [
+            (
+                "0200000000000000000000000000000000000000000000000000000000000000",
+                "ffffffffffffffffffffffffffffffff",
+                "03000000000000000000000000000000",
+            ),
+            (
+                "02000000000000000000000000000000ffffffffffffffffffffffffffffffff",
+                "02000000000000000000000000000000",
+                "03000000000000000000000000000000",
+            ),
+            (
+                "0100000000000000000000000000000000000000000000000000000000000000",
+                "fffffffffffffffffffffffffffffffff0ffffffffffffffffffffffffffffff
+                 11000000000000000000000000000000",
+                "05000000000000000000000000000000",
+            ),
+            (
+                "0100000000000000000000000000000000000000000000000000000000000000",
+                "fffffffffffffffffffffffffffffffffbfefefefefefefefefefefefefefefe
+                 01010101010101010101010101010101",
+                "00000000000000000000000000000000",
+            ),
+            (
+                "0200000000000000000000000000000000000000000000000000000000000000",
+                "fdffffffffffffffffffffffffffffff",
+                "faffffffffffffffffffffffffffffff",
+            ),
+            (
+                "0100000000000000040000000000000000000000000000000000000000000000",
+                "e33594d7505e43b900000000000000003394d7505e4379cd0100000000000000
+                 0000000000000000000000000000000001000000000000000000000000000000",
+                "14000000000000005500000000000000",
+            ),
+            (
+                "0100000000000000040000000000000000000000000000000000000000000000",
+                "e33594d7505e43b900000000000000003394d7505e4379cd0100000000000000
+                 00000000000000000000000000000000",
+                "13000000000000000000000000000000",
+            ),
+        ];
+        for (key, text, tag) in vectors.iter() {
+            assert_eq!(Poly1305.mac(&hex(key), &hex(text), 128).unwrap(), hex(tag));
+        }
+
+        assert_eq!(
+            Poly1305.mac(&key, text, 64),
+            Err(CryptoError::InvalidTagLength(64))
+        );
+        assert_eq!(
+            Poly1305.mac(&key[..16], text, 128),
+            Err(CryptoError::InvalidKeyLength(16))
+        );
+    }
 }
@@ -1,16 +1,100 @@
 use std::convert::TryFrom;
 
+mod error;
+pub use error::CryptoError;
+
 pub mod aes;
 pub use aes::{AESKey, AES};
 
 pub mod chacha20;
+
+/// Salsa20 by [Bernstein](https://cr.yp.to/snuffle/spec.pdf),
+/// and XSalsa20 by [Bernstein](https://cr.yp.to/snuffle/xsalsa-20081128.pdf)
+pub mod salsa20;
+
 pub mod twofish;
 
+/// The Threefish tweakable block cipher, by [Skein 1.3](https://www.schneier.com/academic/skein/)
+pub mod threefish;
+
+/// The [Serpent](https://www.cl.cam.ac.uk/~rja14/serpent.html) block cipher
+pub mod serpent;
+
+/// The Camellia block cipher, by [RFC 3713](https://tools.ietf.org/html/rfc3713)
+pub mod camellia;
+
+/// DES and Triple-DES, by [FIPS 46-3](https://csrc.nist.gov/publications/detail/fips/46/3/archive/1999-10-25)
+/// and [NIST SP 800-67](https://csrc.nist.gov/publications/detail/sp/800-67/rev-2/final)
+pub mod des;
+
+/// The [Blowfish](https://www.schneier.com/academic/blowfish/) block cipher
+pub mod blowfish;
+
+/// The Simon and Speck block ciphers, by [Beaulieu et al.](https://eprint.iacr.org/2013/404)
+pub mod simon;
+pub mod speck;
+
+/// ASCON-128 and ASCON-Hash, by the [ASCON v1.2](https://ascon.iaik.tugraz.at/) submission
+pub mod ascon;
+
+/// Ciphertext stealing for CBC, by the
+/// [addendum to NIST SP 800-38A](https://csrc.nist.gov/publications/detail/sp/800-38a/addendum/final)
+pub mod cts;
+
+/// XTS mode for encryption of sectors on a disk,
+/// by [IEEE 1619](https://standards.ieee.org/standard/1619-2018.html) and
+/// [NIST SP 800-38E](https://csrc.nist.gov/publications/detail/sp/800-38e/final)
+pub mod xts;
+
+/// Counter with CBC-MAC, by [NIST SP 800-38C](https://csrc.nist.gov/publications/detail/sp/800-38c/final)
+pub mod ccm;
+
+/// The EAX mode, by [Bellare, Rogaway and Wagner](https://web.cs.ucdavis.edu/~rogaway/papers/eax.pdf)
+pub mod eax;
+
+/// Synthetic initialization vector mode, by [RFC 5297](https://tools.ietf.org/html/rfc5297)
+pub mod siv;
+
+/// AES-GCM-SIV, by [RFC 8452](https://tools.ietf.org/html/rfc8452)
+pub mod gcm_siv;
+
+/// Key wrapping, by [RFC 3394](https://tools.ietf.org/html/rfc3394) and
+/// with padding by [RFC 5649](https://tools.ietf.org/html/rfc5649)
+pub mod keywrap;
+
+/// The offset codebook mode, by [RFC 7253](https://tools.ietf.org/html/rfc7253)
+pub mod ocb;
+
+/// Encryption and decryption of data streams with `std::io`.
+pub mod stream;
+
+/// X25519 key exchange, by [RFC 7748](https://tools.ietf.org/html/rfc7748)
+pub mod curve25519;
+
+/// The secretbox and box of [NaCl](https://nacl.cr.yp.to/) and
+/// [libsodium](https://doc.libsodium.org/public-key_cryptography/sealed_boxes)
+pub mod nacl;
+
 /// Naive textbook implementation of RSA.
 pub mod rsa;
 
 pub mod sha;
 
+/// MD5, by [RFC 1321](https://tools.ietf.org/html/rfc1321)
+pub mod md5;
+
+/// RIPEMD-160, by [Dobbertin, Bosselaers and Preneel](https://homes.esat.kuleuven.be/~bosselae/ripemd160.html)
+pub mod ripemd;
+
+/// The BLAKE2 hash functions, by [RFC 7693](https://tools.ietf.org/html/rfc7693)
+pub mod blake2;
+
+/// The Skein hash function, by [Skein 1.3](https://www.schneier.com/academic/skein/)
+pub mod skein;
+
+/// The BLAKE3 hash function, by the [BLAKE3 specification](https://github.com/BLAKE3-team/BLAKE3-specs)
+pub mod blake3;
+
 /// Message authentication codes.
 /// The keyed-hash MAC (HMAC) is implemented by [NIST FIPS 198-1](https://csrc.nist.gov/publications/detail/fips/198/1/final)
 pub mod mac;
@@ -23,6 +107,92 @@ pub enum EncryptionMode {
     ECB, //< Electronic Codebook mode, You should rather use CBC or something
 }
 
+/// The padding added to the plaintext before encryption in CBC or ECB mode.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Padding {
+    /// [RFC 5652](https://tools.ietf.org/html/rfc5652#section-6.3), n bytes with the value n.
+    /// Compatible with e.g. OpenSSL.
+    PKCS7,
+    /// ISO/IEC 7816-4, a 0x80 byte followed by zeros.
+    ISO7816,
+    /// ANSI X9.23, zeros followed by a byte with the number of padded bytes.
+    ANSIX923,
+    /// Zeros up to the next block, nothing if the plaintext already fills the last block.
+    /// Trailing zeros of the plaintext are lost, so only use it for data that cannot end with 0.
+    Zero,
+    /// The scheme of `pad`: 0x80, zeros and the number of padded bytes as a 4 byte
+    /// little endian number.
+    #[default]
+    Length,
+}
+
+impl Padding {
+    /// Pad the bytes to a multiple of the block size.
+    pub fn pad(&self, bytes: &mut Vec<u8>, bs: usize) {
+        // the number of padded bytes, always at least one
+        let n = bs - bytes.len() % bs;
+        let len = bytes.len();
+        match self {
+            Padding::PKCS7 => bytes.resize(len + n, n as u8),
+            Padding::ISO7816 => {
+                bytes.push(0x80);
+                bytes.resize(len + n, 0);
+            }
+            Padding::ANSIX923 => {
+                bytes.resize(len + n - 1, 0);
+                bytes.push(n as u8);
+            }
+            Padding::Zero => bytes.resize(len + n % bs, 0),
+            Padding::Length => pad(bytes, bs),
+        }
+    }
+
+    /// Remove the padding, or return an error if it is malformed.
+    pub fn strip(&self, bytes: &mut Vec<u8>, bs: usize) -> Result<(), CryptoError> {
+        if !bytes.len().is_multiple_of(bs) {
+            return Err(CryptoError::InvalidDataLength(bytes.len()));
+        }
+
+        let last = *bytes.last().unwrap_or(&0) as usize;
+        let n = match self {
+            Padding::PKCS7 | Padding::ANSIX923 => {
+                if last == 0 || last > bs || last > bytes.len() {
+                    return Err(CryptoError::BadPadding);
+                }
+                let fill = &bytes[(bytes.len() - last)..(bytes.len() - 1)];
+                let expected = if let Padding::PKCS7 = self { last as u8 } else { 0 };
+                if fill.iter().any(|b| *b != expected) {
+                    return Err(CryptoError::BadPadding);
+                }
+                last
+            }
+            Padding::ISO7816 => {
+                // the marker has to be in the last block
+                let zeros = bytes.iter().rev().take(bs).take_while(|b| **b == 0).count();
+                if zeros == bs || bytes.len() <= zeros || bytes[bytes.len() - zeros - 1] != 0x80 {
+                    return Err(CryptoError::BadPadding);
+                }
+                zeros + 1
+            }
+            Padding::Zero => bytes.iter().rev().take(bs).take_while(|b| **b == 0).count(),
+            Padding::Length => {
+                // the marker and the zeros are at most a block, the length field comes after
+                let mut end = [0u8; 4];
+                if let Some(field) = bytes.len().checked_sub(4) {
+                    end.clone_from_slice(&bytes[field..]);
+                }
+                if u32::from_le_bytes(end) as usize > bs + 5 {
+                    return Err(CryptoError::BadPadding);
+                }
+                return strip_padding(bytes);
+            }
+        };
+
+        bytes.truncate(bytes.len() - n);
+        Ok(())
+    }
+}
+
 pub struct CipherKey<'a> {
     key: &'a [u8],
 }
@@ -30,10 +200,15 @@ pub struct CipherKey<'a> {
 pub enum CipherTypes {
     AES,
     Twofish,
+    Serpent,
+    Camellia,
+    DES,
+    TDEA,
+    Blowfish,
 }
 
 impl CipherTypes {
-    fn new(&self, key: CipherKey) -> Result<Box<dyn BlockCipher>, String> {
+    fn new(&self, key: CipherKey) -> Result<Box<dyn BlockCipher>, CryptoError> {
         match self {
             CipherTypes::AES => {
                 let key = aes::AESKey::try_from(key)?;
@@ -43,18 +218,68 @@ impl CipherTypes {
                 let key = twofish::TwofishKey::try_from(key)?;
                 Ok(twofish::Twofish::new(key))
             }
+            CipherTypes::Serpent => {
+                let key = serpent::SerpentKey::try_from(key)?;
+                Ok(serpent::Serpent::new(key))
+            }
+            CipherTypes::Camellia => {
+                let key = camellia::CamelliaKey::try_from(key)?;
+                Ok(camellia::Camellia::new(key))
+            }
+            CipherTypes::DES => {
+                let key = des::DESKey::try_from(key)?;
+                Ok(des::DES::new(key))
+            }
+            CipherTypes::TDEA => {
+                let key = des::TDEAKey::try_from(key)?;
+                Ok(des::TDEA::new(key))
+            }
+            CipherTypes::Blowfish => {
+                let key = blowfish::BlowfishKey::try_from(key)?;
+                Ok(blowfish::Blowfish::new(key))
+            }
         }
     }
 }
 
 pub trait Cipher<T> {
-    fn encrypt(&self, iv: &[u8], plaintext: T) -> Result<(), String>;
+    fn encrypt(&self, iv: &[u8], plaintext: T) -> Result<(), CryptoError>;
+
+    fn decrypt(&self, iv: &[u8], ciphertext: T) -> Result<(), CryptoError>;
+}
+
+/// Authenticated encryption with associated data.
+/// The tag is added to the ciphertext (appended, except for SIV where it is in front),
+/// and the associated data is authenticated but not encrypted.
+pub trait AEAD {
+    /// Encrypt the plaintext and append the tag.
+    /// The nonce has to be unique for every encryption with the same key.
+    fn encrypt(&self, nonce: &[u8], ad: &[u8], plaintext: &mut Vec<u8>) -> Result<(), CryptoError>;
+
+    /// Verify the tag and decrypt. If the tag does not match, the ciphertext is left untouched
+    /// and `CryptoError::AuthenticationFailure` is returned.
+    fn decrypt(&self, nonce: &[u8], ad: &[u8], ciphertext: &mut Vec<u8>)
+        -> Result<(), CryptoError>;
+}
 
-    fn decrypt(&self, iv: &[u8], ciphertext: T) -> Result<(), String>;
+/// Compare the tags without leaking where the first difference is.
+pub(crate) fn verify_tag(expected: &[u8], tag: &[u8]) -> Result<(), CryptoError> {
+    if expected.len() == tag.len()
+        && expected
+            .iter()
+            .zip(tag.iter())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+    {
+        Ok(())
+    } else {
+        Err(CryptoError::AuthenticationFailure)
+    }
 }
 
 /// Any block cipher implementingthis trait may be used with the implementation of CBC.
-pub trait BlockCipher {
+/// It has to be `Send + Sync` so modes can encrypt blocks in parallel.
+pub trait BlockCipher: Send + Sync {
     fn encrypt_block(&self, block: &mut [u8]);
     fn decrypt_block(&self, block: &mut [u8]);
 
@@ -69,20 +294,48 @@ pub trait BlockCipher {
     /// Change the encryption mode.
     fn change_encryption_mode(&mut self, _mode: EncryptionMode) {}
 
+    /// The padding used when encrypting a `Vec<u8>`.
+    fn padding(&self) -> Padding {
+        Padding::Length
+    }
+
+    /// Change the padding.
+    fn change_padding(&mut self, _padding: Padding) {}
+
     fn get_key(&self) -> &[u8];
-    fn set_key(&mut self, key: &[u8]) -> Result<(), String>;
+    fn set_key(&mut self, key: &[u8]) -> Result<(), CryptoError>;
 }
 
 impl dyn BlockCipher {
+    /// Counter mode, the counter block is incremented as a big endian number.
+    /// This is the same for encryption and decryption.
+    pub(crate) fn ctr(&self, counter: &[u8], data: &mut [u8]) {
+        let mut counter = counter.to_vec();
+        let mut keystream = vec![0u8; counter.len()];
+        for chunk in data.chunks_mut(counter.len()) {
+            keystream.clone_from_slice(&counter);
+            self.encrypt_block(&mut keystream);
+            for (d, k) in chunk.iter_mut().zip(keystream.iter()) {
+                *d ^= k;
+            }
+
+            for c in counter.iter_mut().rev() {
+                *c = c.wrapping_add(1);
+                if *c != 0 {
+                    break;
+                }
+            }
+        }
+    }
+
     /// Encrypt bytes in CBC mode.
     /// It will always add padding.
-    fn cbc_encrypt(&self, iv: &[u8], plaintext: &mut [u8]) -> Result<(), String> {
+    fn cbc_encrypt(&self, iv: &[u8], plaintext: &mut [u8]) -> Result<(), CryptoError> {
         if iv.len() != self.block_size() {
-            return Err(format!(
-                "input vector is wrong length, expected {}, got {}",
-                self.block_size(),
-                iv.len()
-            ));
+            return Err(CryptoError::InvalidIvLength {
+                expected: self.block_size(),
+                got: iv.len(),
+            });
         }
         let bs = self.block_size();
         let prev_block: &mut [u8] = &mut iv.to_vec();
@@ -107,22 +360,17 @@ impl dyn BlockCipher {
     }
 
     /// Decrypt bytes that was encrypted in CBC mode
-    fn cbc_decrypt(&self, iv: &[u8], ciphertext: &mut [u8]) -> Result<(), String> {
+    fn cbc_decrypt(&self, iv: &[u8], ciphertext: &mut [u8]) -> Result<(), CryptoError> {
         let bs = self.block_size();
 
         if iv.len() != bs {
-            return Err(format!(
-                "input vector is wrong length, expected {}, got {}",
-                bs,
-                iv.len()
-            ));
+            return Err(CryptoError::InvalidIvLength {
+                expected: bs,
+                got: iv.len(),
+            });
         }
         if ciphertext.len() % bs != 0 {
-            return Err(format!(
-                "ciphertext length ({}) should be a multiple of the blocksize ({})",
-                ciphertext.len(),
-                bs,
-            ));
+            return Err(CryptoError::InvalidDataLength(ciphertext.len()));
         }
         let prev_block: &mut [u8] = &mut iv.to_vec();
 
@@ -149,7 +397,7 @@ impl dyn BlockCipher {
         Ok(())
     }
 
-    fn ecb_encrypt(&self, _: &[u8], plaintext: &mut [u8]) -> Result<(), String> {
+    fn ecb_encrypt(&self, _: &[u8], plaintext: &mut [u8]) -> Result<(), CryptoError> {
         let bs = self.block_size();
 
         // pad(plaintext, bs);
@@ -161,15 +409,11 @@ impl dyn BlockCipher {
         Ok(())
     }
 
-    fn ecb_decrypt(&self, _: &[u8], ciphertext: &mut [u8]) -> Result<(), String> {
+    fn ecb_decrypt(&self, _: &[u8], ciphertext: &mut [u8]) -> Result<(), CryptoError> {
         let bs = self.block_size();
 
         if ciphertext.len() % bs != 0 {
-            return Err(format!(
-                "ciphertext length ({}) should be a multiple of the blocksize ({})",
-                ciphertext.len(),
-                bs,
-            ));
+            return Err(CryptoError::InvalidDataLength(ciphertext.len()));
         }
 
         for i in 0..(ciphertext.len() / bs) {
@@ -203,22 +447,37 @@ pub fn pad(bytes: &mut Vec<u8>, bs: usize) {
     bytes.append(&mut one.chain(zeros).chain(end_num.iter()).cloned().collect());
 }
 
-pub fn strip_padding(bytes: &mut Vec<u8>) {
+pub fn strip_padding(bytes: &mut Vec<u8>) -> Result<(), CryptoError> {
     let mut end = [0u8; 4];
-    let offset = bytes.len() - std::mem::size_of::<u32>();
+    let offset = bytes
+        .len()
+        .checked_sub(std::mem::size_of::<u32>())
+        .ok_or(CryptoError::BadPadding)?;
 
     end[..4].clone_from_slice(&bytes[offset..(4 + offset)]);
 
     // Get the number at the end
     let end = u32::from_le_bytes(end) as usize;
 
+    if end < 5 || end > bytes.len() {
+        return Err(CryptoError::BadPadding);
+    }
+
+    // the padding starts with 0x80 and has only zeros up to the number
+    let padding = &bytes[(bytes.len() - end)..offset];
+    if padding[0] != 1 << 7 || padding[1..].iter().any(|b| *b != 0) {
+        return Err(CryptoError::BadPadding);
+    }
+
     // Drain the padding from the vector
     bytes.drain((bytes.len() - end)..bytes.len());
+
+    Ok(())
 }
 
 impl Cipher<&mut Vec<u8>> for dyn BlockCipher {
-    fn encrypt(&self, iv: &[u8], plaintext: &mut Vec<u8>) -> Result<(), String> {
-        pad(plaintext, self.block_size());
+    fn encrypt(&self, iv: &[u8], plaintext: &mut Vec<u8>) -> Result<(), CryptoError> {
+        self.padding().pad(plaintext, self.block_size());
         use EncryptionMode::*;
         match self.encryption_mode() {
             CBC => self.cbc_encrypt(iv, plaintext),
@@ -226,14 +485,13 @@ impl Cipher<&mut Vec<u8>> for dyn BlockCipher {
         }
     }
 
-    fn decrypt(&self, iv: &[u8], ciphertext: &mut Vec<u8>) -> Result<(), String> {
+    fn decrypt(&self, iv: &[u8], ciphertext: &mut Vec<u8>) -> Result<(), CryptoError> {
         use EncryptionMode::*;
         match self.encryption_mode() {
             CBC => self.cbc_decrypt(iv, ciphertext)?,
             ECB => self.ecb_decrypt(iv, ciphertext)?,
         };
-        strip_padding(ciphertext);
-        Ok(())
+        self.padding().strip(ciphertext, self.block_size())
     }
 }
 
@@ -243,19 +501,19 @@ fn pbkdf2_round<M: mac::MAC>(
     count: usize,
     i: usize,
     mac: &M,
-) -> Box<[u8; 32]> {
+) -> Result<Box<[u8; 32]>, CryptoError> {
     let mut result = Box::new([0u8; 32]);
     let mut k = salt.to_owned();
     k.append(&mut format!("{}", i).into_bytes());
-    let mut tmp_0 = mac.mac(&password.to_owned(), &k, 256);
+    let mut tmp_0 = mac.mac(&password.to_owned(), &k, 256)?;
     for _ in 1..count {
-        let tmp = mac.mac(&password.to_owned(), &tmp_0, 256);
+        let tmp = mac.mac(&password.to_owned(), &tmp_0, 256)?;
         for (i, b) in tmp.iter().enumerate() {
             result[i] ^= b;
         }
         tmp_0 = tmp;
     }
-    result
+    Ok(result)
 }
 
 /// password based key derivation funcrion v. 2.1
@@ -273,13 +531,15 @@ pub fn pbkdf2<M: mac::MAC>(
     c: usize,
     dklen: usize,
     mac: &M,
-) -> Vec<u8> {
-    debug_assert!(dklen <= ((1 << 32) - 1) * 256, "derived key too long");
+) -> Result<Vec<u8>, CryptoError> {
+    if dklen > ((1 << 32) - 1) * 256 {
+        return Err(CryptoError::InvalidKeyLength(dklen >> 3));
+    }
     let l = dklen / 256 + if dklen % 256 != 0 { 1 } else { 0 };
     let mut res = Vec::new();
     let mut counter = 0;
     'outer: for block in (0..l).map(|i| pbkdf2_round(password, salt, c, i, mac)) {
-        for b in block.iter() {
+        for b in block?.iter() {
             if counter * 8 >= dklen {
                 break 'outer;
             }
@@ -288,5 +548,169 @@ pub fn pbkdf2<M: mac::MAC>(
         }
     }
 
-    res
+    Ok(res)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Parse a hex string of a test vector, whitespace is ignored.
+    pub(crate) fn hex(s: &str) -> Vec<u8> {
+        let s: Vec<u8> = s.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+        s.chunks(2)
+            .map(|c| u8::from_str_radix(std::str::from_utf8(c).unwrap(), 16).unwrap())
+            .collect()
+    }
+
+    const ALL: [Padding; 5] = [
+        Padding::PKCS7,
+        Padding::ISO7816,
+        Padding::ANSIX923,
+        Padding::Zero,
+        Padding::Length,
+    ];
+
+    #[test]
+    fn padding_schemes() {
+        let mut bytes = vec![0xaa; 13];
+        Padding::PKCS7.pad(&mut bytes, 16);
+        assert_eq!(bytes[13..], [3, 3, 3]);
+
+        let mut bytes = vec![0xaa; 13];
+        Padding::ISO7816.pad(&mut bytes, 16);
+        assert_eq!(bytes[13..], [0x80, 0, 0]);
+
+        let mut bytes = vec![0xaa; 13];
+        Padding::ANSIX923.pad(&mut bytes, 16);
+        assert_eq!(bytes[13..], [0, 0, 3]);
+
+        let mut bytes = vec![0xaa; 13];
+        Padding::Zero.pad(&mut bytes, 16);
+        assert_eq!(bytes[13..], [0, 0, 0]);
+
+        // a full block is added when the data is aligned, except for zero padding
+        let mut bytes = vec![0xaa; 16];
+        Padding::PKCS7.pad(&mut bytes, 16);
+        assert_eq!(bytes[16..], [16; 16]);
+
+        let mut bytes = vec![0xaa; 16];
+        Padding::Zero.pad(&mut bytes, 16);
+        assert_eq!(bytes.len(), 16);
+    }
+
+    #[test]
+    fn roundtrip() {
+        for padding in ALL.iter() {
+            for bs in &[8, 16] {
+                for len in 0..40 {
+                    let message: Vec<u8> = (1..=len).collect();
+                    let mut bytes = message.clone();
+                    padding.pad(&mut bytes, *bs);
+                    assert_eq!(bytes.len() % bs, 0);
+                    padding.strip(&mut bytes, *bs).unwrap();
+                    assert_eq!(bytes, message, "{:?}, bs {}, len {}", padding, bs, len);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn malformed() {
+        use CryptoError::*;
+
+        assert_eq!(Padding::PKCS7.strip(&mut vec![0; 15], 16), Err(InvalidDataLength(15)));
+        assert_eq!(Padding::PKCS7.strip(&mut Vec::new(), 16), Err(BadPadding));
+        assert_eq!(Padding::ISO7816.strip(&mut Vec::new(), 16), Err(BadPadding));
+        assert_eq!(Padding::Length.strip(&mut Vec::new(), 16), Err(BadPadding));
+
+        let mut bytes = vec![0xaa; 16];
+        bytes[15] = 17;
+        assert_eq!(Padding::PKCS7.strip(&mut bytes, 16), Err(BadPadding));
+        assert_eq!(Padding::ANSIX923.strip(&mut bytes, 16), Err(BadPadding));
+
+        bytes[15] = 0;
+        assert_eq!(Padding::PKCS7.strip(&mut bytes, 16), Err(BadPadding));
+        assert_eq!(Padding::ISO7816.strip(&mut bytes, 16), Err(BadPadding));
+
+        let mut bytes = vec![3; 16];
+        bytes[14] = 4;
+        assert_eq!(Padding::PKCS7.strip(&mut bytes, 16), Err(BadPadding));
+        assert_eq!(Padding::ANSIX923.strip(&mut bytes, 16), Err(BadPadding));
+
+        // the length of the old scheme is larger than the data
+        let mut bytes = vec![0; 16];
+        bytes[12] = 32;
+        assert_eq!(Padding::Length.strip(&mut bytes, 16), Err(BadPadding));
+
+        // a length field of 7 over data that is not the marker and zeros
+        let mut bytes = vec![0xaa; 16];
+        bytes[12..].clone_from_slice(&7u32.to_le_bytes());
+        assert_eq!(Padding::Length.strip(&mut bytes, 16), Err(BadPadding));
+        bytes[9] = 0x80;
+        assert_eq!(Padding::Length.strip(&mut bytes, 16), Err(BadPadding));
+        bytes[10] = 0;
+        bytes[11] = 0;
+        assert_eq!(Padding::Length.strip(&mut bytes.clone(), 16), Ok(()));
+
+        // the marker and zeros are more than a block
+        let mut bytes = vec![0; 32];
+        bytes[10] = 0x80;
+        bytes[28..].clone_from_slice(&22u32.to_le_bytes());
+        assert_eq!(Padding::Length.strip(&mut bytes, 16), Err(BadPadding));
+        assert_eq!(bytes.len(), 32);
+
+        // the marker is not in the last block
+        let mut bytes = vec![0; 32];
+        bytes[15] = 0x80;
+        assert_eq!(Padding::ISO7816.strip(&mut bytes, 16), Err(BadPadding));
+
+        // nothing was removed from the failed attempts
+        assert_eq!(bytes.len(), 32);
+    }
+
+    #[test]
+    fn openssl_compatible() {
+        let mut aes = aes::AES::new(aes::AESKey::AES128([
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+        ]));
+        aes.change_padding(Padding::PKCS7);
+        let iv: Vec<u8> = (0..16).rev().collect();
+
+        // openssl enc -aes-128-cbc -K 000102030405060708090a0b0c0d0e0f -iv 0f0e0d0c0b0a09080706050403020100
+        let expected = [
+            0xe0, 0x91, 0x75, 0x6c, 0x95, 0xab, 0xf3, 0x72, 0x6c, 0xae, 0xc1, 0x46, 0x68, 0x12,
+            0x28, 0xc6, 0x1a, 0x8d, 0xec, 0xad, 0x57, 0xd7, 0xb3, 0x44, 0x52, 0x61, 0xb5, 0x33,
+            0x55, 0xc0, 0x2e, 0xbf,
+        ];
+
+        let mut message = b"Lorem ipsum dolor sit amet.".to_vec();
+        aes.encrypt(&iv, &mut message).unwrap();
+        assert_eq!(message, expected);
+
+        aes.decrypt(&iv, &mut message).unwrap();
+        assert_eq!(message, b"Lorem ipsum dolor sit amet.");
+    }
+
+    #[test]
+    fn corrupted_ciphertext() {
+        let mut aes = aes::AES::new(aes::AESKey::AES256([7; 32]));
+        let iv = [0u8; 16];
+        for padding in ALL.iter().filter(|p| **p != Padding::Zero) {
+            aes.change_padding(*padding);
+            let mut message = b"hello world, hello padding".to_vec();
+            aes.encrypt(&iv, &mut message).unwrap();
+
+            // a corrupted last block is garbage, which is almost never valid padding
+            let mut failures = 0;
+            for i in 0..16 {
+                let mut corrupted = message.clone();
+                corrupted[16 + i] ^= 0x10;
+                if aes.decrypt(&iv, &mut corrupted).is_err() {
+                    failures += 1;
+                }
+            }
+            assert!(failures >= 15, "{:?}", padding);
+        }
+    }
 }
@@ -0,0 +1,366 @@
+//! The secretbox, box and sealed box constructions of NaCl, compatible with libsodium.
+//!
+//! The `Cipher` implementations use the combined format of libsodium (`crypto_secretbox_easy`
+//! and `crypto_box_easy`), where the 16 byte tag is in front of the ciphertext.
+//! The padded format of the original NaCl API is available with `seal_padded` and `open_padded`.
+//!
+//!     # use codes::crypt::nacl::{CryptoBox, KeyPair};
+//!     # use codes::crypt::Cipher;
+//!     let alice = KeyPair::generate();
+//!     let bob = KeyPair::generate();
+//!     let nonce = [0u8; 24];
+//!
+//!     let mut message = b"hello bob".to_vec();
+//!     CryptoBox::new(&bob.public, alice.secret()).unwrap().encrypt(&nonce, &mut message).unwrap();
+//!     CryptoBox::new(&alice.public, bob.secret()).unwrap().decrypt(&nonce, &mut message).unwrap();
+//!     assert_eq!(message, b"hello bob");
+
+use rand::RngCore;
+
+use super::blake2::blake2b;
+use super::curve25519::{x25519, x25519_base};
+use super::mac::Poly1305;
+use super::salsa20::{hsalsa20, XSalsa20};
+use super::{verify_tag, Cipher, CryptoError};
+
+/// The length of a nonce.
+pub const NONCE_BYTES: usize = 24;
+/// The length of the tag.
+pub const MAC_BYTES: usize = 16;
+/// The number of zeros in front of the plaintext in the padded format.
+pub const ZERO_BYTES: usize = 32;
+/// The number of zeros in front of the ciphertext in the padded format.
+pub const BOX_ZERO_BYTES: usize = 16;
+/// The length of the ephemeral public key in front of a sealed box.
+pub const SEAL_BYTES: usize = 32 + MAC_BYTES;
+
+/// XSalsa20 and Poly1305, `crypto_secretbox`.
+pub struct SecretBox {
+    cipher: XSalsa20,
+}
+
+impl SecretBox {
+    pub fn new(key: &[u8; 32]) -> Self {
+        SecretBox {
+            cipher: XSalsa20::new(key),
+        }
+    }
+
+    /// The Poly1305 key, which is the first 32 bytes of the keystream.
+    fn mac_key(&self, nonce: &[u8]) -> Result<[u8; 32], CryptoError> {
+        let mut key = [0u8; 32];
+        self.cipher.encrypt(nonce, &mut key)?;
+        Ok(key)
+    }
+
+    /// Encrypt in the padded format: the data is `ZERO_BYTES` zeros followed by the message,
+    /// and is replaced by `BOX_ZERO_BYTES` zeros, the tag and the ciphertext.
+    pub fn seal_padded(&self, nonce: &[u8], data: &mut [u8]) -> Result<(), CryptoError> {
+        if data.len() < ZERO_BYTES {
+            return Err(CryptoError::InvalidDataLength(data.len()));
+        }
+        // the zeros become the Poly1305 key
+        for b in data[..ZERO_BYTES].iter_mut() {
+            *b = 0;
+        }
+        self.cipher.encrypt(nonce, data)?;
+
+        let mut key = [0u8; 32];
+        key.clone_from_slice(&data[..32]);
+        let tag = Poly1305::tag(&key, &data[ZERO_BYTES..]);
+
+        data[BOX_ZERO_BYTES..ZERO_BYTES].clone_from_slice(&tag);
+        for b in data[..BOX_ZERO_BYTES].iter_mut() {
+            *b = 0;
+        }
+        Ok(())
+    }
+
+    /// Decrypt the padded format: the data is `BOX_ZERO_BYTES` zeros, the tag and the
+    /// ciphertext, and is replaced by `ZERO_BYTES` zeros followed by the message.
+    /// If the tag is wrong, the data is left as it is.
+    pub fn open_padded(&self, nonce: &[u8], data: &mut [u8]) -> Result<(), CryptoError> {
+        if data.len() < ZERO_BYTES {
+            return Err(CryptoError::InvalidDataLength(data.len()));
+        }
+        let key = self.mac_key(nonce)?;
+        let expected = Poly1305::tag(&key, &data[ZERO_BYTES..]);
+        verify_tag(&expected, &data[BOX_ZERO_BYTES..ZERO_BYTES])?;
+
+        for b in data[..ZERO_BYTES].iter_mut() {
+            *b = 0;
+        }
+        self.cipher.decrypt(nonce, data)?;
+        for b in data[..ZERO_BYTES].iter_mut() {
+            *b = 0;
+        }
+        Ok(())
+    }
+}
+
+impl Cipher<&mut Vec<u8>> for SecretBox {
+    /// Encrypt the message, and put the tag in front of it.
+    fn encrypt(&self, nonce: &[u8], plaintext: &mut Vec<u8>) -> Result<(), CryptoError> {
+        plaintext.splice(0..0, [0u8; ZERO_BYTES].iter().copied());
+        if let Err(e) = self.seal_padded(nonce, plaintext) {
+            plaintext.drain(..ZERO_BYTES);
+            return Err(e);
+        }
+        plaintext.drain(..BOX_ZERO_BYTES);
+        Ok(())
+    }
+
+    /// Verify the tag in front of the ciphertext, and decrypt it.
+    /// If the tag is wrong, the ciphertext is left as it is.
+    fn decrypt(&self, nonce: &[u8], ciphertext: &mut Vec<u8>) -> Result<(), CryptoError> {
+        if ciphertext.len() < MAC_BYTES {
+            return Err(CryptoError::InvalidDataLength(ciphertext.len()));
+        }
+        ciphertext.splice(0..0, [0u8; BOX_ZERO_BYTES].iter().copied());
+        if let Err(e) = self.open_padded(nonce, ciphertext) {
+            ciphertext.drain(..BOX_ZERO_BYTES);
+            return Err(e);
+        }
+        ciphertext.drain(..ZERO_BYTES);
+        Ok(())
+    }
+}
+
+/// A Curve25519 key pair.
+pub struct KeyPair {
+    pub public: [u8; 32],
+    secret: [u8; 32],
+}
+
+impl KeyPair {
+    /// Generate a new key pair from the randomness of the operating system.
+    pub fn generate() -> Self {
+        let mut secret = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut secret);
+        Self::from_secret(&secret)
+    }
+
+    pub fn from_secret(secret: &[u8; 32]) -> Self {
+        KeyPair {
+            public: x25519_base(secret),
+            secret: *secret,
+        }
+    }
+
+    pub fn secret(&self) -> &[u8; 32] {
+        &self.secret
+    }
+}
+
+/// Curve25519, XSalsa20 and Poly1305, `crypto_box`.
+/// This is a secretbox with a key shared between the sender and the receiver.
+pub struct CryptoBox {
+    secretbox: SecretBox,
+}
+
+impl CryptoBox {
+    /// Precompute the shared key, like `crypto_box_beforenm`.
+    /// Public keys of a small order, that give a known shared key, are rejected.
+    pub fn new(their_public: &[u8; 32], our_secret: &[u8; 32]) -> Result<Self, CryptoError> {
+        let shared = x25519(our_secret, their_public);
+        if shared.iter().fold(0, |acc, b| acc | b) == 0 {
+            return Err(CryptoError::InvalidParameter(
+                "the public key has a small order".to_string(),
+            ));
+        }
+        Ok(CryptoBox {
+            secretbox: SecretBox::new(&hsalsa20(&shared, &[0; 16])),
+        })
+    }
+
+    /// The same as `SecretBox::seal_padded`.
+    pub fn seal_padded(&self, nonce: &[u8], data: &mut [u8]) -> Result<(), CryptoError> {
+        self.secretbox.seal_padded(nonce, data)
+    }
+
+    /// The same as `SecretBox::open_padded`.
+    pub fn open_padded(&self, nonce: &[u8], data: &mut [u8]) -> Result<(), CryptoError> {
+        self.secretbox.open_padded(nonce, data)
+    }
+}
+
+impl Cipher<&mut Vec<u8>> for CryptoBox {
+    fn encrypt(&self, nonce: &[u8], plaintext: &mut Vec<u8>) -> Result<(), CryptoError> {
+        self.secretbox.encrypt(nonce, plaintext)
+    }
+
+    fn decrypt(&self, nonce: &[u8], ciphertext: &mut Vec<u8>) -> Result<(), CryptoError> {
+        self.secretbox.decrypt(nonce, ciphertext)
+    }
+}
+
+/// The nonce of a sealed box is the hash of the two public keys.
+fn seal_nonce(ephemeral: &[u8], their_public: &[u8; 32]) -> Vec<u8> {
+    let mut keys = ephemeral.to_vec();
+    keys.extend_from_slice(their_public);
+    blake2b(&[], &keys, NONCE_BYTES).expect("24 bytes is a valid BLAKE2b digest")
+}
+
+/// Encrypt anonymously to a public key, `crypto_box_seal`.
+/// A new ephemeral key pair is used for every message, and its public key is put in front of
+/// the box. The sender can not decrypt the message afterwards.
+pub fn seal(their_public: &[u8; 32], plaintext: &mut Vec<u8>) -> Result<(), CryptoError> {
+    let ephemeral = KeyPair::generate();
+    let nonce = seal_nonce(&ephemeral.public, their_public);
+    CryptoBox::new(their_public, ephemeral.secret())?.encrypt(&nonce, plaintext)?;
+    plaintext.splice(0..0, ephemeral.public.iter().copied());
+    Ok(())
+}
+
+/// Decrypt a sealed box, `crypto_box_seal_open`.
+/// If it can not be opened, the ciphertext is left as it is.
+pub fn seal_open(keys: &KeyPair, ciphertext: &mut Vec<u8>) -> Result<(), CryptoError> {
+    if ciphertext.len() < SEAL_BYTES {
+        return Err(CryptoError::InvalidDataLength(ciphertext.len()));
+    }
+    let mut ephemeral = [0u8; 32];
+    ephemeral.clone_from_slice(&ciphertext[..32]);
+    let nonce = seal_nonce(&ephemeral, &keys.public);
+
+    let mut message = ciphertext[32..].to_vec();
+    CryptoBox::new(&ephemeral, keys.secret())?.decrypt(&nonce, &mut message)?;
+    *ciphertext = message;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::tests::hex;
+
+    fn array(s: &str) -> [u8; 32] {
+        let mut a = [0u8; 32];
+        a.clone_from_slice(&hex(s));
+        a
+    }
+
+    // the keys and message of the tests of NaCl and libsodium
+    fn alice() -> KeyPair {
+        KeyPair::from_secret(&array(
+            "77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a",
+        ))
+    }
+
+    fn bob() -> KeyPair {
+        KeyPair::from_secret(&array(
+            "5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb",
+        ))
+    }
+
+    fn nonce() -> Vec<u8> {
+        hex("69696ee955b62b73cd62bda875fc73d68219e0036b7a0b37")
+    }
+
+    fn message() -> Vec<u8> {
+        hex(
+            "be075fc53c81f2d5cf141316ebeb0c7b5228c52a4c62cbd44b66849b64244ffc
+             e5ecbaaf33bd751a1ac728d45e6c61296cdc3c01233561f41db66cce314adb31
+             0e3be8250c46f06dceea3a7fa1348057e2f6556ad6b1318a024a838f21af1fde
+             048977eb48f59ffd4924ca1c60902e52f0a089bc76897040e082f93776384864
+             5e0705",
+        )
+    }
+
+    fn ciphertext() -> Vec<u8> {
+        hex(
+            "f3ffc7703f9400e52a7dfb4b3d3305d98e993b9f48681273c29650ba32fc76ce
+             48332ea7164d96a4476fb8c531a1186ac0dfc17c98dce87b4da7f011ec48c972
+             71d2c20f9b928fe2270d6fb863d51738b48eeee314a7cc8ab932164548e526ae
+             90224368517acfeabd6bb3732bc0e9da99832b61ca01b6de56244a9e88d5f9b3
+             7973f622a43d14a6599b1f654cb45a74e355a5",
+        )
+    }
+
+    #[test]
+    fn secretbox() -> Result<(), CryptoError> {
+        let firstkey = array("1b27556473e985d462cd51197a9a46c76009549eac6474f206c4ee0844f68389");
+        let secretbox = SecretBox::new(&firstkey);
+
+        // the padded format
+        let mut data = vec![0u8; ZERO_BYTES];
+        data.append(&mut message());
+        secretbox.seal_padded(&nonce(), &mut data)?;
+        assert_eq!(data[..BOX_ZERO_BYTES], [0; BOX_ZERO_BYTES]);
+        assert_eq!(data[BOX_ZERO_BYTES..], ciphertext()[..]);
+        secretbox.open_padded(&nonce(), &mut data)?;
+        assert_eq!(data[..ZERO_BYTES], [0; ZERO_BYTES]);
+        assert_eq!(data[ZERO_BYTES..], message()[..]);
+
+        // the combined format
+        let mut data = message();
+        secretbox.encrypt(&nonce(), &mut data)?;
+        assert_eq!(data, ciphertext());
+        secretbox.decrypt(&nonce(), &mut data)?;
+        assert_eq!(data, message());
+
+        Ok(())
+    }
+
+    #[test]
+    fn crypto_box() -> Result<(), CryptoError> {
+        let (alice, bob) = (alice(), bob());
+
+        let mut data = message();
+        CryptoBox::new(&bob.public, alice.secret())?.encrypt(&nonce(), &mut data)?;
+        assert_eq!(data, ciphertext());
+
+        CryptoBox::new(&alice.public, bob.secret())?.decrypt(&nonce(), &mut data)?;
+        assert_eq!(data, message());
+
+        // a public key of order 1
+        assert!(CryptoBox::new(&[0; 32], alice.secret()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn forged() -> Result<(), CryptoError> {
+        let cryptobox = CryptoBox::new(&alice().public, bob().secret())?;
+        for i in &[0, 15, 16, 100] {
+            let mut data = ciphertext();
+            data[*i] ^= 1;
+            let copy = data.clone();
+            assert_eq!(
+                cryptobox.decrypt(&nonce(), &mut data),
+                Err(CryptoError::AuthenticationFailure)
+            );
+            assert_eq!(data, copy);
+        }
+
+        let mut short = vec![0u8; 15];
+        assert_eq!(
+            cryptobox.decrypt(&nonce(), &mut short),
+            Err(CryptoError::InvalidDataLength(15))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sealed_box() -> Result<(), CryptoError> {
+        let bob = bob();
+
+        // sealed by libsodium
+        let mut data = hex(
+            "3b33028fe759084fb3961386218bf73bfd993ed6e1baedd232f7b619271d7705
+             da78fed06f3fbc0f34381821fbfa26a25a2fc0556eca18469233d0963990",
+        );
+        seal_open(&bob, &mut data)?;
+        assert_eq!(data, b"sealed for bob");
+
+        let mut data = message();
+        seal(&bob.public, &mut data)?;
+        assert_eq!(data.len(), message().len() + SEAL_BYTES);
+
+        let mut wrong = data.clone();
+        assert!(seal_open(&alice(), &mut wrong).is_err());
+        assert_eq!(wrong, data);
+
+        seal_open(&bob, &mut data)?;
+        assert_eq!(data, message());
+        Ok(())
+    }
+}
@@ -0,0 +1,444 @@
+//! The offset codebook mode (OCB3), an AEAD with a single pass of the block cipher over the
+//! data. Every block is encrypted with its own offset, and since the offsets can be computed
+//! directly from the block number, all the blocks are encrypted in parallel.
+//!
+//!     # use codes::crypt::{aes::{AES, AESKey}, ocb::OCB, AEAD};
+//!     let ocb = OCB::new(AES::new(AESKey::AES128([0x42; 16])), 128).unwrap();
+//!     let nonce = [0u8; 12];
+//!
+//!     let mut message = b"attack at dawn".to_vec();
+//!     ocb.encrypt(&nonce, b"header", &mut message).unwrap();
+//!     assert_eq!(message.len(), 14 + 16);
+//!
+//!     ocb.decrypt(&nonce, b"header", &mut message).unwrap();
+//!     assert_eq!(message, b"attack at dawn");
+
+use rayon::prelude::*;
+
+use super::{mac::dbl, verify_tag, BlockCipher, CryptoError, AEAD};
+
+fn xor(a: &mut [u8; 16], b: &[u8]) {
+    for (a, b) in a.iter_mut().zip(b.iter()) {
+        *a ^= b;
+    }
+}
+
+pub struct OCB {
+    cipher: Box<dyn BlockCipher>,
+    /// in bytes
+    tag_len: usize,
+    /// E(0)
+    l_star: [u8; 16],
+    /// double(L_*)
+    l_dollar: [u8; 16],
+    /// L_i = double^(i+2)(L_*), enough for any number of blocks
+    l: Vec<[u8; 16]>,
+}
+
+impl OCB {
+    /// Create an OCB instance of a 128 bit block cipher, the tag length is in bits.
+    pub fn new(cipher: Box<dyn BlockCipher>, tag_len: usize) -> Result<Self, CryptoError> {
+        if cipher.block_size() != 16 {
+            return Err(CryptoError::InvalidParameter(format!(
+                "OCB needs a 128 bit block cipher, not {} bits",
+                cipher.block_size() << 3
+            )));
+        }
+        if tag_len == 0 || tag_len > 128 || tag_len & 7 != 0 {
+            return Err(CryptoError::InvalidTagLength(tag_len));
+        }
+
+        let mut l_star = [0u8; 16];
+        cipher.encrypt_block(&mut l_star);
+        let l_dollar = dbl(&l_star);
+        let mut l = vec![dbl(&l_dollar)];
+        for i in 1..64 {
+            let next = dbl(&l[i - 1]);
+            l.push(next);
+        }
+
+        Ok(OCB {
+            cipher,
+            tag_len: tag_len >> 3,
+            l_star,
+            l_dollar,
+            l,
+        })
+    }
+
+    /// The offset of block i (starting at 1) from the initial offset.
+    /// The L_ntz(i) added for every block adds up to the L_k of the bits of the gray code of i.
+    fn offset(&self, initial: &[u8; 16], i: usize) -> [u8; 16] {
+        let gray = i ^ (i >> 1);
+        let mut offset = *initial;
+        for (k, l) in self.l.iter().enumerate() {
+            if gray >> k == 0 {
+                break;
+            }
+            if gray >> k & 1 == 1 {
+                xor(&mut offset, l);
+            }
+        }
+        offset
+    }
+
+    /// The initial offset from the nonce.
+    fn initial_offset(&self, nonce: &[u8]) -> [u8; 16] {
+        let mut block = [0u8; 16];
+        // the tag length in bits modulo 128, in the first 7 bits
+        block[0] = (((self.tag_len << 3) % 128) << 1) as u8;
+        block[15 - nonce.len()] |= 1;
+        block[(16 - nonce.len())..].clone_from_slice(nonce);
+
+        let bottom = (block[15] & 0x3f) as usize;
+        block[15] &= 0xc0;
+        self.cipher.encrypt_block(&mut block);
+
+        let mut stretch = [0u8; 24];
+        stretch[..16].clone_from_slice(&block);
+        for i in 0..8 {
+            stretch[16 + i] = block[i] ^ block[i + 1];
+        }
+
+        // the 128 bits starting at bit number bottom
+        let (bytes, bits) = (bottom / 8, bottom % 8);
+        let mut offset = [0u8; 16];
+        for (i, o) in offset.iter_mut().enumerate() {
+            *o = if bits == 0 {
+                stretch[bytes + i]
+            } else {
+                (stretch[bytes + i] << bits) | (stretch[bytes + i + 1] >> (8 - bits))
+            };
+        }
+        offset
+    }
+
+    /// Hash the associated data.
+    fn hash(&self, ad: &[u8]) -> [u8; 16] {
+        let zero = [0u8; 16];
+        let blocks = ad.len() / 16;
+        let mut sum = ad[..(blocks * 16)]
+            .par_chunks(16)
+            .enumerate()
+            .map(|(i, a)| {
+                let mut block = self.offset(&zero, i + 1);
+                xor(&mut block, a);
+                self.cipher.encrypt_block(&mut block);
+                block
+            })
+            .reduce(
+                || [0u8; 16],
+                |mut a, b| {
+                    xor(&mut a, &b);
+                    a
+                },
+            );
+
+        let rest = &ad[(blocks * 16)..];
+        if !rest.is_empty() {
+            let mut block = [0u8; 16];
+            block[..rest.len()].clone_from_slice(rest);
+            block[rest.len()] = 0x80;
+            xor(&mut block, &self.offset(&zero, blocks));
+            xor(&mut block, &self.l_star);
+            self.cipher.encrypt_block(&mut block);
+            xor(&mut sum, &block);
+        }
+
+        sum
+    }
+
+    /// Encrypt or decrypt the whole blocks in parallel, and the last partial block.
+    fn crypt(&self, initial: &[u8; 16], data: &mut [u8], encrypt: bool) {
+        let blocks = data.len() / 16;
+        let (whole, rest) = data.split_at_mut(blocks * 16);
+
+        whole.par_chunks_mut(16).enumerate().for_each(|(i, block)| {
+            let offset = self.offset(initial, i + 1);
+            for (b, o) in block.iter_mut().zip(offset.iter()) {
+                *b ^= o;
+            }
+            if encrypt {
+                self.cipher.encrypt_block(block);
+            } else {
+                self.cipher.decrypt_block(block);
+            }
+            for (b, o) in block.iter_mut().zip(offset.iter()) {
+                *b ^= o;
+            }
+        });
+
+        if !rest.is_empty() {
+            let mut pad = self.offset(initial, blocks);
+            xor(&mut pad, &self.l_star);
+            self.cipher.encrypt_block(&mut pad);
+            for (r, p) in rest.iter_mut().zip(pad.iter()) {
+                *r ^= p;
+            }
+        }
+    }
+
+    fn tag(&self, initial: &[u8; 16], ad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let blocks = plaintext.len() / 16;
+        let mut checksum = plaintext.chunks(16).fold([0u8; 16], |mut sum, p| {
+            xor(&mut sum, p);
+            if p.len() < 16 {
+                sum[p.len()] ^= 0x80;
+            }
+            sum
+        });
+
+        let mut offset = self.offset(initial, blocks);
+        if !plaintext.len().is_multiple_of(16) {
+            xor(&mut offset, &self.l_star);
+        }
+        xor(&mut checksum, &offset);
+        xor(&mut checksum, &self.l_dollar);
+        self.cipher.encrypt_block(&mut checksum);
+        xor(&mut checksum, &self.hash(ad));
+
+        checksum[..self.tag_len].to_vec()
+    }
+
+    fn check(nonce: &[u8]) -> Result<(), CryptoError> {
+        if nonce.is_empty() || nonce.len() > 15 {
+            return Err(CryptoError::InvalidParameter(format!(
+                "a nonce of {} bytes, it must be 1 to 15 bytes",
+                nonce.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl AEAD for OCB {
+    /// Encrypt with a nonce of at most 15 bytes, 12 bytes is recommended.
+    fn encrypt(&self, nonce: &[u8], ad: &[u8], plaintext: &mut Vec<u8>) -> Result<(), CryptoError> {
+        Self::check(nonce)?;
+
+        let initial = self.initial_offset(nonce);
+        let mut tag = self.tag(&initial, ad, plaintext);
+        self.crypt(&initial, plaintext, true);
+        plaintext.append(&mut tag);
+        Ok(())
+    }
+
+    fn decrypt(
+        &self,
+        nonce: &[u8],
+        ad: &[u8],
+        ciphertext: &mut Vec<u8>,
+    ) -> Result<(), CryptoError> {
+        Self::check(nonce)?;
+        if ciphertext.len() < self.tag_len {
+            return Err(CryptoError::InvalidDataLength(ciphertext.len()));
+        }
+        let len = ciphertext.len() - self.tag_len;
+
+        let initial = self.initial_offset(nonce);
+        self.crypt(&initial, &mut ciphertext[..len], false);
+
+        let tag = self.tag(&initial, ad, &ciphertext[..len]);
+        if let Err(e) = verify_tag(&tag, &ciphertext[len..]) {
+            // do not release the unauthenticated plaintext
+            self.crypt(&initial, &mut ciphertext[..len], true);
+            return Err(e);
+        }
+
+        ciphertext.truncate(len);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::aes::{AESKey, AES};
+    use crate::crypt::tests::hex;
+
+    fn ocb(key: &str, tag_len: usize) -> OCB {
+        let mut k = [0u8; 16];
+        k.clone_from_slice(&hex(key));
+        OCB::new(AES::new(AESKey::AES128(k)), tag_len).unwrap()
+    }
+
+    #[test]
+    fn rfc7253_vectors() {
+        // appendix A, the nonce is BBAA998877665544332211 followed by the number
+        let ocb = ocb("000102030405060708090A0B0C0D0E0F", 128);
+        let vectors = [
+            (0, 0, "785407BFFFC8AD9EDCC5520AC9111EE6"),
+            (8, 8, "6820B3657B6F615A5725BDA0D3B4EB3A257C9AF1F8F03009"),
+            (8, 0, "81017F8203F081277152FADE694A0A00"),
+            (0, 8, "45DD69F8F5AAE72414054CD1F35D82760B2CD00D2F99BFA9"),
+            (
+                16,
+                16,
+                "571D535B60B277188BE5147170A9A22C3AD7A4FF3835B8C5701C1CCEC8FC3358",
+            ),
+            (16, 0, "8CF761B6902EF764462AD86498CA6B97"),
+            (
+                0,
+                16,
+                "5CE88EC2E0692706A915C00AEB8B2396F40E1C743F52436BDF06D8FA1ECA343D",
+            ),
+            (
+                24,
+                24,
+                "1CA2207308C87C010756104D8840CE1952F09673A448A122
+                 C92C62241051F57356D7F3C90BB0E07F",
+            ),
+            (24, 0, "6DC225A071FC1B9F7C69F93B0F1E10DE"),
+            (
+                0,
+                24,
+                "221BD0DE7FA6FE993ECCD769460A0AF2D6CDED0C395B1C3C
+                 E725F32494B9F914D85C0B1EB38357FF",
+            ),
+            (
+                32,
+                32,
+                "BD6F6C496201C69296C11EFD138A467ABD3C707924B964DEAFFC40319AF5A485
+                 40FBBA186C5553C68AD9F592A79A4240",
+            ),
+            (32, 0, "FE80690BEE8A485D11F32965BC9D2A32"),
+            (
+                0,
+                32,
+                "2942BFC773BDA23CABC6ACFD9BFD5835BD300F0973792EF46040C53F1432BCDF
+                 B5E1DDE3BC18A5F840B52E653444D5DF",
+            ),
+            (
+                40,
+                40,
+                "D5CA91748410C1751FF8A2F618255B68A0A12E093FF454606E59F9C1D0DDC54B
+                 65E8628E568BAD7AED07BA06A4A69483A7035490C5769E60",
+            ),
+            (40, 0, "C5CD9D1850C141E358649994EE701B68"),
+            (
+                0,
+                40,
+                "4412923493C57D5DE0D700F753CCE0D1D2D95060122E9F15A5DDBFC5787E50B5
+                 CC55EE507BCB084E479AD363AC366B95A98CA5F3000B1479",
+            ),
+        ];
+
+        let data: Vec<u8> = (0..40).collect();
+        for (n, (ad_len, len, expected)) in vectors.iter().enumerate() {
+            let nonce = [&hex("BBAA998877665544332211")[..], &[n as u8]].concat();
+            let mut message = data[..*len].to_vec();
+            ocb.encrypt(&nonce, &data[..*ad_len], &mut message).unwrap();
+            assert_eq!(message, hex(expected), "vector {}", n);
+            ocb.decrypt(&nonce, &data[..*ad_len], &mut message).unwrap();
+            assert_eq!(message, data[..*len]);
+        }
+    }
+
+    #[test]
+    fn rfc7253_96_bit_tag() {
+        let ocb = ocb("0F0E0D0C0B0A09080706050403020100", 96);
+        let data: Vec<u8> = (0..40).collect();
+        let nonce = hex("BBAA9988776655443322110D");
+        let mut message = data.clone();
+        ocb.encrypt(&nonce, &data, &mut message).unwrap();
+        assert_eq!(
+            message,
+            hex(
+                "1792A4E31E0755FB03E31B22116E6C2DDF9EFD6E33D536F1A0124B0A55BAE884
+                 ED93481529C76B6AD0C515F4D1CDD4FDAC4F02AA"
+            )
+        );
+        ocb.decrypt(&nonce, &data, &mut message).unwrap();
+        assert_eq!(message, data);
+    }
+
+    #[test]
+    fn rfc7253_iterated() {
+        // the check of all the tag lengths and many message lengths from appendix A
+        for (tag_len, expected) in &[
+            (128, "67E944D23256C5E0B6C61FA22FDF1EA2"),
+            (96, "77A3D8E73589158D25D01209"),
+            (64, "192C9B7BD90BA06A"),
+        ] {
+            let mut key = [0u8; 16];
+            key[15] = *tag_len as u8;
+            let ocb = OCB::new(AES::new(AESKey::AES128(key)), *tag_len).unwrap();
+            let nonce = |n: u32| {
+                let mut nonce = [0u8; 12];
+                nonce[8..].clone_from_slice(&n.to_be_bytes());
+                nonce
+            };
+
+            let mut c = Vec::new();
+            for i in 0..128 {
+                let s = vec![0u8; i as usize];
+                let mut p = s.clone();
+                ocb.encrypt(&nonce(3 * i + 1), &s, &mut p).unwrap();
+                c.append(&mut p);
+                let mut p = s.clone();
+                ocb.encrypt(&nonce(3 * i + 2), &[], &mut p).unwrap();
+                c.append(&mut p);
+                let mut p = Vec::new();
+                ocb.encrypt(&nonce(3 * i + 3), &s, &mut p).unwrap();
+                c.append(&mut p);
+            }
+
+            let mut tag = Vec::new();
+            ocb.encrypt(&nonce(385), &c, &mut tag).unwrap();
+            assert_eq!(tag, hex(expected), "tag length {}", tag_len);
+        }
+    }
+
+    #[test]
+    fn parallel_offsets() {
+        // the direct offsets are the same as adding L_ntz(i) for every block
+        let ocb = ocb("000102030405060708090A0B0C0D0E0F", 128);
+        let initial = ocb.initial_offset(&[1, 2, 3]);
+        let mut offset = initial;
+        for i in 1..5000usize {
+            xor(&mut offset, &ocb.l[i.trailing_zeros() as usize]);
+            assert_eq!(offset, ocb.offset(&initial, i));
+        }
+    }
+
+    #[test]
+    fn tampering() {
+        let ocb = ocb("000102030405060708090A0B0C0D0E0F", 64);
+        let nonce = [5u8; 12];
+        let mut sealed: Vec<u8> = (0..100).collect();
+        ocb.encrypt(&nonce, b"ad", &mut sealed).unwrap();
+        assert_eq!(sealed.len(), 108);
+
+        for i in 0..sealed.len() {
+            let mut tampered = sealed.clone();
+            tampered[i] ^= 0x04;
+            assert_eq!(
+                ocb.decrypt(&nonce, b"ad", &mut tampered),
+                Err(CryptoError::AuthenticationFailure)
+            );
+            tampered[i] ^= 0x04;
+            assert_eq!(tampered, sealed);
+        }
+
+        assert_eq!(
+            ocb.decrypt(&[6; 12], b"ad", &mut sealed.clone()),
+            Err(CryptoError::AuthenticationFailure)
+        );
+        assert_eq!(
+            ocb.decrypt(&nonce, b"ad!", &mut sealed.clone()),
+            Err(CryptoError::AuthenticationFailure)
+        );
+        assert_eq!(
+            ocb.encrypt(&[0; 16], b"", &mut vec![]),
+            Err(CryptoError::InvalidParameter(
+                "a nonce of 16 bytes, it must be 1 to 15 bytes".to_string()
+            ))
+        );
+        assert!(ocb.decrypt(&[], b"", &mut vec![0; 16]).is_err());
+        assert!(ocb.encrypt(&[0; 1], b"", &mut vec![]).is_ok());
+        assert_eq!(
+            OCB::new(AES::new(AESKey::AES128([0; 16])), 0).err(),
+            Some(CryptoError::InvalidTagLength(0))
+        );
+    }
+}
@@ -0,0 +1,150 @@
+//! The RIPEMD-160 hash function, used through `HashAlg::Ripemd160`, see there for where to use it.
+//!
+//!     # use codes::crypt::sha::{Hash, HashAlg};
+//!     let digest = HashAlg::Ripemd160.hash("abc");
+//!     assert_eq!(digest[..4], [0x8e, 0xb2, 0x08, 0xf7]);
+
+use super::sha::pad64;
+
+/// The message word of each step of the left line.
+const R: [usize; 80] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, //
+    7, 4, 13, 1, 10, 6, 15, 3, 12, 0, 9, 5, 2, 14, 11, 8, //
+    3, 10, 14, 4, 9, 15, 8, 1, 2, 7, 0, 6, 13, 11, 5, 12, //
+    1, 9, 11, 10, 0, 8, 12, 4, 13, 3, 7, 15, 14, 5, 6, 2, //
+    4, 0, 5, 9, 7, 12, 2, 10, 14, 1, 3, 8, 11, 6, 15, 13,
+];
+
+/// The message word of each step of the right line.
+const R_PRIME: [usize; 80] = [
+    5, 14, 7, 0, 9, 2, 11, 4, 13, 6, 15, 8, 1, 10, 3, 12, //
+    6, 11, 3, 7, 0, 13, 5, 10, 14, 15, 8, 12, 4, 9, 1, 2, //
+    15, 5, 1, 3, 7, 14, 6, 9, 11, 8, 12, 2, 10, 0, 4, 13, //
+    8, 6, 4, 1, 3, 11, 15, 0, 5, 12, 2, 13, 9, 7, 10, 14, //
+    12, 15, 10, 4, 1, 5, 8, 7, 6, 2, 13, 14, 0, 3, 9, 11,
+];
+
+/// The rotation of each step of the left line.
+const S: [u32; 80] = [
+    11, 14, 15, 12, 5, 8, 7, 9, 11, 13, 14, 15, 6, 7, 9, 8, //
+    7, 6, 8, 13, 11, 9, 7, 15, 7, 12, 15, 9, 11, 7, 13, 12, //
+    11, 13, 6, 7, 14, 9, 13, 15, 14, 8, 13, 6, 5, 12, 7, 5, //
+    11, 12, 14, 15, 14, 15, 9, 8, 9, 14, 5, 6, 8, 6, 5, 12, //
+    9, 15, 5, 11, 6, 8, 13, 12, 5, 12, 13, 14, 11, 8, 5, 6,
+];
+
+/// The rotation of each step of the right line.
+const S_PRIME: [u32; 80] = [
+    8, 9, 9, 11, 13, 15, 15, 5, 7, 7, 8, 11, 14, 14, 12, 6, //
+    9, 13, 15, 7, 12, 8, 9, 11, 7, 7, 12, 7, 6, 15, 13, 11, //
+    9, 7, 15, 11, 8, 6, 6, 14, 12, 13, 5, 14, 13, 13, 7, 5, //
+    15, 5, 8, 11, 14, 14, 6, 14, 6, 9, 12, 9, 12, 5, 15, 8, //
+    8, 5, 12, 9, 12, 5, 14, 6, 8, 13, 6, 5, 15, 13, 11, 11,
+];
+
+/// The constants of each round of the left and the right line.
+const K: [u32; 5] = [0x00000000, 0x5a827999, 0x6ed9eba1, 0x8f1bbcdc, 0xa953fd4e];
+const K_PRIME: [u32; 5] = [0x50a28be6, 0x5c4dd124, 0x6d703ef3, 0x7a6d76e9, 0x00000000];
+
+/// The boolean function of round j, the right line uses them in reverse order.
+fn f(j: usize, x: u32, y: u32, z: u32) -> u32 {
+    match j {
+        0 => x ^ y ^ z,
+        1 => (x & y) | (!x & z),
+        2 => (x | !y) ^ z,
+        3 => (x & z) | (y & !z),
+        _ => x ^ (y | !z),
+    }
+}
+
+/// The RIPEMD-160 digest of the data.
+/// Two parallel lines of five rounds each are combined at the end of every block.
+pub fn ripemd160(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+    for block in pad64(data, false).chunks(64) {
+        let mut x = [0u32; 16];
+        for (x, c) in x.iter_mut().zip(block.chunks(4)) {
+            *x = u32::from_le_bytes([c[0], c[1], c[2], c[3]]);
+        }
+
+        let mut left = h;
+        let mut right = h;
+        for j in 0..80 {
+            let round = j / 16;
+            let [a, b, c, d, e] = left;
+            let t = a
+                .wrapping_add(f(round, b, c, d))
+                .wrapping_add(x[R[j]])
+                .wrapping_add(K[round])
+                .rotate_left(S[j])
+                .wrapping_add(e);
+            left = [e, t, b, c.rotate_left(10), d];
+
+            let [a, b, c, d, e] = right;
+            let t = a
+                .wrapping_add(f(4 - round, b, c, d))
+                .wrapping_add(x[R_PRIME[j]])
+                .wrapping_add(K_PRIME[round])
+                .rotate_left(S_PRIME[j])
+                .wrapping_add(e);
+            right = [e, t, b, c.rotate_left(10), d];
+        }
+
+        // the lines are combined with a rotation of the words
+        let t = h[1].wrapping_add(left[2]).wrapping_add(right[3]);
+        h[1] = h[2].wrapping_add(left[3]).wrapping_add(right[4]);
+        h[2] = h[3].wrapping_add(left[4]).wrapping_add(right[0]);
+        h[3] = h[4].wrapping_add(left[0]).wrapping_add(right[1]);
+        h[4] = h[0].wrapping_add(left[1]).wrapping_add(right[2]);
+        h[0] = t;
+    }
+
+    let mut digest = [0u8; 20];
+    for (c, h) in digest.chunks_mut(4).zip(h.iter()) {
+        c.clone_from_slice(&h.to_le_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::tests::hex;
+
+    #[test]
+    fn vectors() {
+        // the test vectors of the RIPEMD-160 page
+        let vectors = [
+            ("", "9c1185a5c5e9fc54612808977ee8f548b2258d31"),
+            ("a", "0bdc9d2d256b3ee9daae347be6f4dc835a467ffe"),
+            ("abc", "8eb208f7e05d987a9b044a8e98c6b087f15a0bfc"),
+            ("message digest", "5d0689ef49d2fae572b881b123a85ffa21595f36"),
+            (
+                "abcdefghijklmnopqrstuvwxyz",
+                "f71c27109c692c1b56bbdceb5b9d2865b3708dbc",
+            ),
+            (
+                "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "12a053384a9c0c88e405a06c27dcf49ada62eb2b",
+            ),
+            (
+                "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+                "b0e20b6e3116640286ed3a87a5713079b21f5189",
+            ),
+            (
+                "12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                "9b752e45573d4b39f4dbd3323cab82bf63326bfb",
+            ),
+        ];
+        for (input, digest) in vectors.iter() {
+            assert_eq!(ripemd160(input.as_bytes())[..], hex(digest)[..]);
+        }
+
+        let million = vec![b'a'; 1_000_000];
+        assert_eq!(
+            ripemd160(&million)[..],
+            hex("52783243c1697bdbe16d37f97f68f08325dc1528")[..]
+        );
+    }
+}
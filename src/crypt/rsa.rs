@@ -1,5 +1,6 @@
 use num_bigint::{BigUint, ToBigUint};
 
+use super::CryptoError;
 use crate::number_theory::inverse;
 use crate::prime::PrimeGenerator;
 
@@ -11,7 +12,7 @@ struct RSA {
 }
 
 pub trait Crypt {
-    fn encrypt(&mut self, bytes: Vec<u8>) -> Vec<BigUint>;
+    fn encrypt(&mut self, bytes: Vec<u8>) -> Result<Vec<BigUint>, CryptoError>;
     fn decrypt(&mut self, nums: Vec<BigUint>) -> Vec<u8>;
     fn block_size(&self) -> usize;
 }
@@ -54,7 +55,7 @@ impl RSA {
 }
 
 impl Crypt for RSA {
-    fn encrypt(&mut self, bytes: Vec<u8>) -> Vec<BigUint> {
+    fn encrypt(&mut self, bytes: Vec<u8>) -> Result<Vec<BigUint>, CryptoError> {
         // assert that the number of bytes is sufficient:
         // e^message > n
         // (2^16)^(message) > 2 ^ size
@@ -66,8 +67,10 @@ impl Crypt for RSA {
         // log2(bytes) > log2(size >> 7)
         // bytes) > size >> 7
 
-        // TODO: instead of asserting, pad shit
-        assert!(bytes.len() > self.size >> 7);
+        // TODO: instead of failing, pad shit
+        if bytes.len() <= self.size >> 7 {
+            return Err(CryptoError::InvalidDataLength(bytes.len()));
+        }
         let (mut rest, mut data) =
             bytes
                 .iter()
@@ -81,15 +84,18 @@ impl Crypt for RSA {
                     (rest, data)
                 });
 
-        assert!(rest.len() > self.size >> 7);
-
         if !rest.is_empty() {
+            // the last block is too short, report its length
+            if rest.len() <= self.size >> 7 {
+                return Err(CryptoError::InvalidDataLength(rest.len()));
+            }
+
             data.push(self.encrypt_block(&BigUint::from_bytes_be(
                 &rest.drain(0..rest.len()).collect::<Vec<u8>>()[..],
             )));
         }
 
-        data
+        Ok(data)
     }
 
     fn decrypt(&mut self, nums: Vec<BigUint>) -> Vec<u8> {
@@ -119,7 +125,7 @@ mod tests {
 
         let string = b"iuha diuh diuh seouihafhj sfjkhbsvcuyb serufy bwuyebf ysbad ufy busyrbef uyawb uefybakjshdbf askjnbvyu ba yuefb aywebf hkjbcvuybwae fb kwaebyf uyabweuof bwoeyf owyuevfbuoy vacd habs kfjhwuyefbgo uyagfouywe gffhbwefyb aygrf oygwehab fhbwcyb ygrfv aygwerfhjwbe fsjdbc uybsdovgh hbwoauebyf oyuasgdvyb h r yuagrrf87a9 7y 0ra7h bhhas hdbvuyhasbdv ygawbhfnmabsd,nmbasvcbhudcb oghr8 gar jhioj".to_vec();
 
-        let encrypted = keys.encrypt(string.clone());
+        let encrypted = keys.encrypt(string.clone()).unwrap();
 
         assert!(encrypted.len() > 1);
 
@@ -130,6 +136,17 @@ mod tests {
         assert_eq!(decrypted, string);
     }
 
+    #[test]
+    fn short_last_block() {
+        // a block of 511 bits takes 63 bytes, and the last 3 bytes are too few
+        let mut keys = RSA::new(512);
+        assert_eq!(
+            keys.encrypt(vec![1; 66]),
+            Err(CryptoError::InvalidDataLength(3))
+        );
+        assert!(keys.encrypt(vec![1; 68]).is_ok());
+    }
+
     fn big(x: u32) -> BigUint {
         x.to_biguint().unwrap()
     }
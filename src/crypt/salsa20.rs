@@ -0,0 +1,312 @@
+//! The Salsa20 stream cipher, the reduced round variants Salsa20/12 and Salsa20/8,
+//! and XSalsa20 with a 192 bit nonce, as used in NaCl.
+
+use rayon::prelude::*;
+
+use super::{Cipher, CryptoError};
+
+/// "expand 32-byte k" as little endian words.
+const SIGMA: [u32; 4] = [0x61707865, 0x3320646e, 0x79622d32, 0x6b206574];
+
+fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    state[b] ^= state[a].wrapping_add(state[d]).rotate_left(7);
+    state[c] ^= state[b].wrapping_add(state[a]).rotate_left(9);
+    state[d] ^= state[c].wrapping_add(state[b]).rotate_left(13);
+    state[a] ^= state[d].wrapping_add(state[c]).rotate_left(18);
+}
+
+fn rounds(state: &mut [u32; 16], rounds: usize) {
+    for _ in 0..(rounds >> 1) {
+        // the columns
+        quarter_round(state, 0, 4, 8, 12);
+        quarter_round(state, 5, 9, 13, 1);
+        quarter_round(state, 10, 14, 2, 6);
+        quarter_round(state, 15, 3, 7, 11);
+        // the rows
+        quarter_round(state, 0, 1, 2, 3);
+        quarter_round(state, 5, 6, 7, 4);
+        quarter_round(state, 10, 11, 8, 9);
+        quarter_round(state, 15, 12, 13, 14);
+    }
+}
+
+/// Read little endian words from the bytes.
+fn read_words(bytes: &[u8], words: &mut [u32]) {
+    for (w, b) in words.iter_mut().zip(bytes.chunks(4)) {
+        let mut tmp = [0u8; 4];
+        tmp.clone_from_slice(b);
+        *w = u32::from_le_bytes(tmp);
+    }
+}
+
+/// The initial state, with the nonce (and the counter, for HSalsa20) from word 6.
+fn initialize_block(key: &[u8; 32], nonce: &[u8]) -> [u32; 16] {
+    let mut block = [0u32; 16];
+    for (i, c) in SIGMA.iter().enumerate() {
+        block[i * 5] = *c;
+    }
+    read_words(&key[..16], &mut block[1..5]);
+    read_words(&key[16..], &mut block[11..15]);
+    read_words(nonce, &mut block[6..10]);
+    block
+}
+
+/// Xor a block of keystream into the data.
+/// The in-block should be initialized, except for the counter.
+fn salsa20_block(in_block: &[u32; 16], data: &mut [u8], counter: u64, round_count: usize) {
+    let mut state = *in_block;
+    state[8] = counter as u32;
+    state[9] = (counter >> 32) as u32;
+
+    let mut mixed = state;
+    rounds(&mut mixed, round_count);
+
+    for (i, chunk) in data.chunks_mut(4).enumerate() {
+        let word = mixed[i].wrapping_add(state[i]).to_le_bytes();
+        for (d, k) in chunk.iter_mut().zip(word.iter()) {
+            *d ^= k;
+        }
+    }
+}
+
+/// Derive a key from the key and the first 128 bits of a nonce.
+/// This is used by XSalsa20, and to derive the shared key in NaCl.
+pub fn hsalsa20(key: &[u8; 32], nonce: &[u8; 16]) -> [u8; 32] {
+    let mut state = initialize_block(key, nonce);
+    rounds(&mut state, 20);
+
+    let mut res = [0u8; 32];
+    for (r, i) in res.chunks_mut(4).zip([0, 5, 10, 15, 6, 7, 8, 9].iter()) {
+        r.clone_from_slice(&state[*i].to_le_bytes());
+    }
+    res
+}
+
+/// Xor the keystream into the data. The blocks are done in parallel.
+fn apply_keystream(block: &[u32; 16], round_count: usize, data: &mut [u8]) {
+    data.par_chunks_mut(64)
+        .enumerate()
+        .for_each(|(n, chunk)| salsa20_block(block, chunk, n as u64, round_count));
+}
+
+fn check_nonce(nonce: &[u8], expected: usize) -> Result<(), CryptoError> {
+    if nonce.len() != expected {
+        return Err(CryptoError::InvalidIvLength {
+            expected,
+            got: nonce.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Salsa20 with a 64 bit nonce.
+pub struct Salsa20 {
+    key: Box<[u8; 32]>,
+    rounds: usize,
+}
+
+impl Salsa20 {
+    /// Salsa20/20
+    pub fn new(key: &[u8; 32]) -> Self {
+        Salsa20 {
+            key: Box::new(*key),
+            rounds: 20,
+        }
+    }
+
+    /// Salsa20 with another number of rounds, usually 12 or 8.
+    /// Fewer rounds is faster, but has a smaller security margin.
+    pub fn with_rounds(key: &[u8; 32], rounds: usize) -> Result<Self, CryptoError> {
+        if rounds == 0 || rounds & 1 != 0 {
+            return Err(CryptoError::InvalidParameter(format!(
+                "{} rounds, it must be a positive even number",
+                rounds
+            )));
+        }
+        Ok(Salsa20 {
+            key: Box::new(*key),
+            rounds,
+        })
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.rounds
+    }
+}
+
+impl Cipher<&mut [u8]> for Salsa20 {
+    /// Encrypt some text with the Salsa20 stream cipher.
+    /// The nonce has to be unique for every encryption.
+    fn encrypt(&self, nonce: &[u8], plaintext: &mut [u8]) -> Result<(), CryptoError> {
+        check_nonce(nonce, 8)?;
+        apply_keystream(&initialize_block(&self.key, nonce), self.rounds, plaintext);
+        Ok(())
+    }
+
+    /// Decrypt something encrypted with Salsa20.
+    /// This is the same as encrypting it.
+    fn decrypt(&self, nonce: &[u8], ciphertext: &mut [u8]) -> Result<(), CryptoError> {
+        self.encrypt(nonce, ciphertext)
+    }
+}
+
+/// Salsa20 with a 192 bit nonce, which is large enough to be chosen at random.
+pub struct XSalsa20 {
+    key: Box<[u8; 32]>,
+}
+
+impl XSalsa20 {
+    pub fn new(key: &[u8; 32]) -> Self {
+        XSalsa20 {
+            key: Box::new(*key),
+        }
+    }
+}
+
+impl Cipher<&mut [u8]> for XSalsa20 {
+    /// Encrypt some text with the XSalsa20 stream cipher.
+    /// The nonce has to be unique for every encryption.
+    fn encrypt(&self, nonce: &[u8], plaintext: &mut [u8]) -> Result<(), CryptoError> {
+        check_nonce(nonce, 24)?;
+        let mut hn = [0u8; 16];
+        hn.clone_from_slice(&nonce[..16]);
+        let subkey = hsalsa20(&self.key, &hn);
+        apply_keystream(&initialize_block(&subkey, &nonce[16..]), 20, plaintext);
+        Ok(())
+    }
+
+    /// Decrypt something encrypted with XSalsa20.
+    /// This is the same as encrypting it.
+    fn decrypt(&self, nonce: &[u8], ciphertext: &mut [u8]) -> Result<(), CryptoError> {
+        self.encrypt(nonce, ciphertext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::sha::{Hash, HashAlg};
+    use crate::crypt::tests::hex;
+
+    fn key(k: &str) -> [u8; 32] {
+        let mut key = [0u8; 32];
+        key.clone_from_slice(&hex(k));
+        key
+    }
+
+    #[test]
+    fn estream() -> Result<(), CryptoError> {
+        // 256 bit key, set 1, vector 0
+        let k = key("80000000000000000000000000000000 00000000000000000000000000000000");
+        let mut stream = vec![0u8; 512];
+        Salsa20::new(&k).encrypt(&[0; 8], &mut stream)?;
+        assert_eq!(
+            stream[..64],
+            hex(
+                "E3BE8FDD8BECA2E3EA8EF9475B29A6E7003951E1097A5C38D23B7A5FAD9F6844
+                 B22C97559E2723C7CBBD3FE4FC8D9A0744652A83E72A9C461876AF4D7EF1A117"
+            )[..]
+        );
+        assert_eq!(
+            stream[448..],
+            hex(
+                "696AFCFD0CDDCC83C7E77F11A649D79ACDC3354E9635FF137E929933A0BD6F53
+                 77EFA105A3A4266B7C0D089D08F1E855CC32B15B93784A36E56A76CC64BC8477"
+            )[..]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reduced_rounds() -> Result<(), CryptoError> {
+        let k = key("80000000000000000000000000000000 00000000000000000000000000000000");
+
+        let mut stream = [0u8; 64];
+        Salsa20::with_rounds(&k, 12)?.encrypt(&[0; 8], &mut stream)?;
+        assert_eq!(
+            stream[..],
+            hex(
+                "AFE411ED1C4E07E4D0CDE3B33E31EC190FA4CC796A58BAFB848EAD8D07D02CD2
+                 D4B6F9F30CB0B57007E3733895CC8D1060107975ACAEEB689B6CF614AB64A3D6"
+            )[..]
+        );
+
+        let mut stream = [0u8; 64];
+        Salsa20::with_rounds(&k, 8)?.encrypt(&[0; 8], &mut stream)?;
+        assert_eq!(
+            stream[..],
+            hex(
+                "B1F599E9B0D96DF436AE31F5EF589565B92D245DB5A1D4C7A78E5E8D0146F8A4
+                 9D326C1A3BF50C052C9C8F114DC74972C4469591E31C9ED11927AA9871F38583"
+            )[..]
+        );
+
+        assert!(Salsa20::with_rounds(&k, 7).is_err());
+        assert!(Salsa20::with_rounds(&k, 0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn nacl_hsalsa20() {
+        // core1 in the NaCl tests
+        let shared = key("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742");
+        assert_eq!(
+            hsalsa20(&shared, &[0; 16])[..],
+            hex("1b27556473e985d462cd51197a9a46c76009549eac6474f206c4ee0844f68389")[..]
+        );
+    }
+
+    #[test]
+    fn nacl_xsalsa20() -> Result<(), CryptoError> {
+        // the keys and nonce of tests/stream.c, stream2.c and stream3.c of NaCl,
+        // and the first 32 bytes of the stream from stream3.out
+        let firstkey = key("1b27556473e985d462cd51197a9a46c76009549eac6474f206c4ee0844f68389");
+        let secondkey = key("dc908dda0b9344a953629b733820778880f3ceb421bb61b91cbd4c3e66256ce4");
+        let nonce = hex("69696ee955b62b73cd62bda875fc73d68219e0036b7a0b37");
+
+        let mut stream = vec![0u8; 4194304];
+        XSalsa20::new(&firstkey).encrypt(&nonce, &mut stream)?;
+        assert_eq!(
+            stream[..32],
+            hex("eea6a7251c1e72916d11c2cb214d3c252539121d8e234e652d651fa4c8cff880")[..]
+        );
+
+        // stream2.c: Salsa20 with the derived key and the end of the nonce is the same stream
+        let mut stream2 = vec![0u8; 4194304];
+        Salsa20::new(&secondkey).encrypt(&nonce[16..], &mut stream2)?;
+        assert!(stream2 == stream);
+
+        // stream.out and stream2.out are the SHA-256 of the stream,
+        // 662b9d0e3463029156069b12f918691a98f7dfb2ca0393c96bbfc6b1fbd630a2,
+        // and this is its SHA-512, of the stream by crypto_stream_xsalsa20 of libsodium
+        assert_eq!(
+            HashAlg::Sha512.hash(stream)[..],
+            hex(
+                "2bd8e7db6877539e4f2b295ee415cd378ae214aa3beb3e08e911a5bd4a25e6ac
+                 16ca283c79c34c08c99f7bdb560111e8cac1ae65eea08ac384d7a591461ab6e3"
+            )[..]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn roundtrip() -> Result<(), CryptoError> {
+        let k = [3u8; 32];
+        let plaintext: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let mut text = plaintext.clone();
+
+        XSalsa20::new(&k).encrypt(&[1; 24], &mut text)?;
+        assert_ne!(text, plaintext);
+        XSalsa20::new(&k).decrypt(&[1; 24], &mut text)?;
+        assert_eq!(text, plaintext);
+
+        assert_eq!(
+            Salsa20::new(&k).encrypt(&[1; 24], &mut text),
+            Err(CryptoError::InvalidIvLength {
+                expected: 8,
+                got: 24
+            })
+        );
+        Ok(())
+    }
+}
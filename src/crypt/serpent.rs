@@ -0,0 +1,369 @@
+//! The Serpent block cipher, an AES finalist.
+//! This is the bitsliced form, with the byte order of the NESSIE test vectors.
+//! The S-boxes are looked up a bit position at a time, which is slow, but simple.
+
+use super::BlockCipher;
+
+const PHI: u32 = 0x9e3779b9;
+
+const SBOX: [[u8; 16]; 8] = [
+    [3, 8, 15, 1, 10, 6, 5, 11, 14, 13, 4, 2, 7, 0, 9, 12],
+    [15, 12, 2, 7, 9, 0, 5, 10, 1, 11, 14, 8, 6, 13, 3, 4],
+    [8, 6, 7, 9, 3, 12, 10, 15, 13, 1, 14, 4, 0, 11, 5, 2],
+    [0, 15, 11, 8, 12, 9, 6, 3, 13, 1, 2, 4, 10, 7, 5, 14],
+    [1, 15, 8, 3, 12, 0, 11, 6, 2, 5, 4, 10, 9, 14, 7, 13],
+    [15, 5, 2, 11, 4, 10, 9, 12, 0, 3, 14, 8, 13, 6, 7, 1],
+    [7, 2, 12, 5, 8, 4, 6, 11, 14, 9, 1, 15, 13, 3, 10, 0],
+    [1, 13, 15, 0, 14, 8, 2, 11, 7, 4, 12, 10, 9, 3, 5, 6],
+];
+
+/// The inverses of the S-boxes.
+const SBOX_INV: [[u8; 16]; 8] = [
+    [13, 3, 11, 0, 10, 6, 5, 12, 1, 14, 4, 7, 15, 9, 8, 2],
+    [5, 8, 2, 14, 15, 6, 12, 3, 11, 4, 7, 9, 1, 13, 10, 0],
+    [12, 9, 15, 4, 11, 14, 1, 2, 0, 3, 6, 13, 5, 8, 10, 7],
+    [0, 9, 10, 7, 11, 14, 6, 13, 3, 5, 12, 2, 4, 8, 15, 1],
+    [5, 0, 8, 3, 10, 9, 7, 14, 2, 12, 11, 6, 4, 15, 13, 1],
+    [8, 15, 2, 9, 4, 1, 13, 14, 11, 6, 5, 3, 7, 12, 10, 0],
+    [15, 10, 1, 13, 5, 3, 6, 0, 4, 9, 14, 7, 2, 12, 8, 11],
+    [3, 0, 6, 13, 9, 14, 15, 8, 5, 12, 11, 7, 10, 1, 4, 2],
+];
+
+pub enum SerpentKey {
+    SK128([u8; 16]),
+    SK192([u8; 24]),
+    SK256([u8; 32]),
+}
+
+impl SerpentKey {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            SerpentKey::SK128(key) => &key[..],
+            SerpentKey::SK192(key) => &key[..],
+            SerpentKey::SK256(key) => &key[..],
+        }
+    }
+}
+
+impl<'a> std::convert::TryFrom<super::CipherKey<'a>> for SerpentKey {
+    type Error = super::CryptoError;
+
+    fn try_from(key: super::CipherKey) -> Result<SerpentKey, Self::Error> {
+        match key.key.len() {
+            16 => {
+                let mut key_arr = [0u8; 16];
+                key_arr[..].clone_from_slice(key.key);
+                Ok(SerpentKey::SK128(key_arr))
+            }
+            24 => {
+                let mut key_arr = [0u8; 24];
+                key_arr[..].clone_from_slice(key.key);
+                Ok(SerpentKey::SK192(key_arr))
+            }
+            32 => {
+                let mut key_arr = [0u8; 32];
+                key_arr[..].clone_from_slice(key.key);
+                Ok(SerpentKey::SK256(key_arr))
+            }
+            _ => Err(super::CryptoError::InvalidKeyLength(key.key.len())),
+        }
+    }
+}
+
+pub struct Serpent {
+    round_keys: Box<[[u32; 4]; 33]>,
+    mode: super::EncryptionMode,
+    padding: super::Padding,
+    key: SerpentKey,
+}
+
+impl Serpent {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(key: SerpentKey) -> Box<dyn BlockCipher> {
+        Box::new(Serpent {
+            round_keys: expand_key(key.bytes()),
+            mode: super::EncryptionMode::CBC,
+            padding: super::Padding::default(),
+            key,
+        })
+    }
+}
+
+/// Apply the S-box to each bit position of the four words.
+fn substitute(sbox: &[u8; 16], x: &mut [u32; 4]) {
+    let mut out = [0u32; 4];
+    for bit in 0..32 {
+        let nibble = (0..4).fold(0, |n, i| n | (((x[i] >> bit) & 1) << i));
+        let s = sbox[nibble as usize] as u32;
+        for (i, o) in out.iter_mut().enumerate() {
+            *o |= ((s >> i) & 1) << bit;
+        }
+    }
+    *x = out;
+}
+
+fn linear_transform(x: &mut [u32; 4]) {
+    x[0] = x[0].rotate_left(13);
+    x[2] = x[2].rotate_left(3);
+    x[1] ^= x[0] ^ x[2];
+    x[3] ^= x[2] ^ (x[0] << 3);
+    x[1] = x[1].rotate_left(1);
+    x[3] = x[3].rotate_left(7);
+    x[0] ^= x[1] ^ x[3];
+    x[2] ^= x[3] ^ (x[1] << 7);
+    x[0] = x[0].rotate_left(5);
+    x[2] = x[2].rotate_left(22);
+}
+
+fn inverse_linear_transform(x: &mut [u32; 4]) {
+    x[2] = x[2].rotate_right(22);
+    x[0] = x[0].rotate_right(5);
+    x[2] ^= x[3] ^ (x[1] << 7);
+    x[0] ^= x[1] ^ x[3];
+    x[3] = x[3].rotate_right(7);
+    x[1] = x[1].rotate_right(1);
+    x[3] ^= x[2] ^ (x[0] << 3);
+    x[1] ^= x[0] ^ x[2];
+    x[2] = x[2].rotate_right(3);
+    x[0] = x[0].rotate_right(13);
+}
+
+fn xor(x: &mut [u32; 4], k: &[u32; 4]) {
+    for (x, k) in x.iter_mut().zip(k.iter()) {
+        *x ^= k;
+    }
+}
+
+/// Shorter keys are padded to 256 bits with a single one bit and zeros.
+fn expand_key(key: &[u8]) -> Box<[[u32; 4]; 33]> {
+    let mut padded = [0u8; 32];
+    padded[..key.len()].clone_from_slice(key);
+    if key.len() < 32 {
+        padded[key.len()] = 1;
+    }
+
+    // the prekeys, after the eight words of the key
+    let mut w = [0u32; 140];
+    for (w, b) in w.iter_mut().zip(padded.chunks(4)) {
+        let mut tmp = [0u8; 4];
+        tmp.clone_from_slice(b);
+        *w = u32::from_le_bytes(tmp);
+    }
+    for i in 8..140 {
+        w[i] = (w[i - 8] ^ w[i - 5] ^ w[i - 3] ^ w[i - 1] ^ PHI ^ (i as u32 - 8)).rotate_left(11);
+    }
+
+    let mut round_keys = Box::new([[0u32; 4]; 33]);
+    for (i, k) in round_keys.iter_mut().enumerate() {
+        k.clone_from_slice(&w[(8 + 4 * i)..(12 + 4 * i)]);
+        substitute(&SBOX[(35 - i) & 7], k);
+    }
+    round_keys
+}
+
+fn to_words(block: &[u8]) -> [u32; 4] {
+    let mut x = [0u32; 4];
+    for (x, b) in x.iter_mut().zip(block.chunks(4)) {
+        let mut tmp = [0u8; 4];
+        tmp.clone_from_slice(b);
+        *x = u32::from_le_bytes(tmp);
+    }
+    x
+}
+
+fn from_words(x: &[u32; 4], block: &mut [u8]) {
+    for (b, x) in block.chunks_mut(4).zip(x.iter()) {
+        b.clone_from_slice(&x.to_le_bytes());
+    }
+}
+
+impl BlockCipher for Serpent {
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let mut x = to_words(block);
+
+        for round in 0..32 {
+            xor(&mut x, &self.round_keys[round]);
+            substitute(&SBOX[round & 7], &mut x);
+            if round < 31 {
+                linear_transform(&mut x);
+            } else {
+                xor(&mut x, &self.round_keys[32]);
+            }
+        }
+
+        from_words(&x, block);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let mut x = to_words(block);
+
+        for round in (0..32).rev() {
+            if round < 31 {
+                inverse_linear_transform(&mut x);
+            } else {
+                xor(&mut x, &self.round_keys[32]);
+            }
+            substitute(&SBOX_INV[round & 7], &mut x);
+            xor(&mut x, &self.round_keys[round]);
+        }
+
+        from_words(&x, block);
+    }
+
+    fn block_size(&self) -> usize {
+        16
+    }
+
+    fn change_encryption_mode(&mut self, mode: super::EncryptionMode) {
+        self.mode = mode;
+    }
+
+    fn encryption_mode(&self) -> super::EncryptionMode {
+        self.mode
+    }
+
+    fn change_padding(&mut self, padding: super::Padding) {
+        self.padding = padding;
+    }
+
+    fn padding(&self) -> super::Padding {
+        self.padding
+    }
+
+    fn get_key(&self) -> &[u8] {
+        self.key.bytes()
+    }
+
+    fn set_key(&mut self, key: &[u8]) -> Result<(), super::CryptoError> {
+        use std::convert::TryFrom;
+        self.key = SerpentKey::try_from(super::CipherKey { key })?;
+        self.round_keys = expand_key(key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::tests::hex;
+    use std::convert::TryFrom;
+
+    fn serpent(key: &str) -> Box<dyn BlockCipher> {
+        let key = hex(key);
+        Serpent::new(SerpentKey::try_from(crate::crypt::CipherKey { key: &key }).unwrap())
+    }
+
+    #[test]
+    fn nessie_set_1() {
+        // vector 0 of set 1, for each key size
+        let vectors = [
+            (
+                "80000000000000000000000000000000",
+                "264E5481EFF42A4606ABDA06C0BFDA3D",
+            ),
+            (
+                "800000000000000000000000000000000000000000000000",
+                "9E274EAD9B737BB21EFCFCA548602689",
+            ),
+            (
+                "8000000000000000000000000000000000000000000000000000000000000000",
+                "A223AA1288463C0E2BE38EBD825616C0",
+            ),
+        ];
+
+        for (key, ciphertext) in vectors.iter() {
+            let cipher = serpent(key);
+            let mut block = [0u8; 16];
+            cipher.encrypt_block(&mut block);
+            assert_eq!(block[..], hex(ciphertext)[..]);
+            cipher.decrypt_block(&mut block);
+            assert_eq!(block, [0u8; 16]);
+        }
+    }
+
+    #[test]
+    fn nessie_sets_2_and_4() {
+        // vector 0 of set 2 and set 4 for 128 bit keys, the last one is the 256 bit vector 0
+        // of set 4 by libgcrypt, which also gives the vectors of set 1
+        let vectors = [
+            (
+                "00000000000000000000000000000000",
+                "80000000000000000000000000000000",
+                "A3B35DE7C358DDD82644678C64B8BCBB",
+            ),
+            (
+                "000102030405060708090A0B0C0D0E0F",
+                "00112233445566778899AABBCCDDEEFF",
+                "563E2CF8740A27C164804560391E9B27",
+            ),
+            (
+                "000102030405060708090A0B0C0D0E0F101112131415161718191A1B1C1D1E1F",
+                "00112233445566778899AABBCCDDEEFF",
+                "2868B7A2D28ECD5E4FDEFAC3C4330074",
+            ),
+        ];
+
+        for (key, plaintext, ciphertext) in vectors.iter() {
+            let cipher = serpent(key);
+            let mut block = hex(plaintext);
+            cipher.encrypt_block(&mut block);
+            assert_eq!(block, hex(ciphertext));
+            cipher.decrypt_block(&mut block);
+            assert_eq!(block, hex(plaintext));
+        }
+    }
+
+    #[test]
+    fn ecb() {
+        use crate::crypt::{Cipher, EncryptionMode};
+
+        let mut cipher = serpent("000102030405060708090A0B0C0D0E0F");
+        cipher.change_encryption_mode(EncryptionMode::ECB);
+        assert!(matches!(cipher.encryption_mode(), EncryptionMode::ECB));
+
+        let mut data = hex("00112233445566778899AABBCCDDEEFF 00112233445566778899AABBCCDDEEFF");
+        cipher.encrypt(&[], &mut data).unwrap();
+        assert_eq!(data[..16], hex("563E2CF8740A27C164804560391E9B27")[..]);
+        assert_eq!(data[..16], data[16..32]);
+        cipher.decrypt(&[], &mut data).unwrap();
+        assert_eq!(data.len(), 32);
+    }
+
+    #[test]
+    fn keys() {
+        let mut cipher = serpent("80000000000000000000000000000000");
+        assert!(cipher.set_key(&[0u8; 20]).is_err());
+        cipher
+            .set_key(&hex(
+                "8000000000000000000000000000000000000000000000000000000000000000",
+            ))
+            .unwrap();
+        assert_eq!(cipher.get_key().len(), 32);
+
+        let mut block = [0u8; 16];
+        cipher.encrypt_block(&mut block);
+        assert_eq!(block[..], hex("A223AA1288463C0E2BE38EBD825616C0")[..]);
+    }
+
+    #[test]
+    fn cbc() {
+        use crate::crypt::{Cipher, CipherKey, CipherTypes, Padding};
+
+        // checked with nettle
+        let key: Vec<u8> = (0..32).collect();
+        let iv: Vec<u8> = (16..32).collect();
+        let mut cipher = CipherTypes::Serpent.new(CipherKey { key: &key }).unwrap();
+        cipher.change_padding(Padding::PKCS7);
+
+        let plaintext = b"Serpent in CBC mode, with PKCS#7 padding.".to_vec();
+        let mut text = plaintext.clone();
+        cipher.encrypt(&iv, &mut text).unwrap();
+        assert_eq!(
+            text,
+            hex("ac54b0a8034f133d92e130fa505dc5721a6f5c6f83ccd135ea41d1a49fa576e0
+                 1593d0050d967631c3d067d88f1c2ac3")
+        );
+        cipher.decrypt(&iv, &mut text).unwrap();
+        assert_eq!(text, plaintext);
+    }
+}
@@ -1,4 +1,5 @@
 //! Currently SHA512 implemented by [FIPS 180-4](https://csrc.nist.gov/publications/detail/fips/180/4/final) standard.
+//! SHA-1, MD5 and RIPEMD-160 are also in `HashAlg`, see their variants for where to use them.
 //!
 //! here is an example:
 //!
@@ -11,6 +12,16 @@ pub enum HashAlg {
     Sha512,
     Sha512_224,
     Sha512_256,
+    /// SHA-1, not collision resistant, collisions have been found in practice.
+    /// Only use it for HMAC, like in TOTP, and for legacy formats like Git objects.
+    Sha1,
+    /// MD5, by [RFC 1321](https://tools.ietf.org/html/rfc1321), not collision resistant,
+    /// collisions are found in seconds. Only use it for HMAC and legacy checksums.
+    Md5,
+    /// RIPEMD-160, not collision resistant by current standards,
+    /// its 160 bit output only gives 80 bit security against collisions.
+    /// Only use it for HMAC and legacy formats like Bitcoin addresses.
+    Ripemd160,
 }
 
 // {{{ Macros for computation
@@ -203,6 +214,7 @@ impl Hash for HashAlg {
 
         match self {
             Sha512_224 | Sha512_256 | Sha384 | Sha512 => 1024,
+            Sha1 | Md5 | Ripemd160 => 512,
         }
     }
 
@@ -214,6 +226,8 @@ impl Hash for HashAlg {
             Sha512_256 => 256,
             Sha384 => 384,
             Sha512 => 512,
+            Sha1 | Ripemd160 => 160,
+            Md5 => 128,
         }
     }
 
@@ -224,6 +238,10 @@ impl Hash for HashAlg {
         use HashAlg::*;
 
         let mut iv = match self {
+            // the legacy hashes have 32 bit words and their own compression
+            Sha1 => return Box::new(sha1(&Vec::from(data))),
+            Md5 => return Box::new(super::md5::md5(&Vec::from(data))),
+            Ripemd160 => return Box::new(super::ripemd::ripemd160(&Vec::from(data))),
             Sha384 => [
                 0xcbbb9d5dc1059ed8,
                 0x629a292a367cd507,
@@ -268,13 +286,67 @@ impl Hash for HashAlg {
 
         sha512_base(data, &mut iv);
 
-        match self {
-            Sha512_224 => create_box!(iv, 224, u64),
-            Sha512_256 => create_box!(iv, 256, u64),
-            Sha384 => create_box!(iv, 384, u64),
-            Sha512 => create_box!(iv, 512, u64),
+        // the truncated variants are a prefix of the final state
+        let digest = create_box!(iv, 512, u64);
+        digest[..self.size() >> 3].into()
+    }
+}
+
+/// Pad the message to whole 64 byte blocks, with a one bit, zeros and the length in bits
+/// as a 64 bit number. SHA-1 is big endian, MD5 and RIPEMD-160 are little endian.
+pub(crate) fn pad64(data: &[u8], big_endian: bool) -> Vec<u8> {
+    let bits = (data.len() as u64) << 3;
+    let mut padded = data.to_vec();
+    padded.push(1 << 7);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    if big_endian {
+        padded.extend_from_slice(&bits.to_be_bytes());
+    } else {
+        padded.extend_from_slice(&bits.to_le_bytes());
+    }
+    padded
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+    for block in pad64(data, true).chunks(64) {
+        let mut w = [0u32; 80];
+        for (t, c) in block.chunks(4).enumerate() {
+            w[t] = u32::from_be_bytes([c[0], c[1], c[2], c[3]]);
+        }
+        for t in 16..80 {
+            w[t] = (w[t - 3] ^ w[t - 8] ^ w[t - 14] ^ w[t - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (t, wt) in w.iter().enumerate() {
+            let (f, k) = match t / 20 {
+                0 => (ch!(b, c, d), 0x5a827999),
+                1 => (b ^ c ^ d, 0x6ed9eba1),
+                2 => (maj!(b, c, d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let temp = sum!(a.rotate_left(5), f, e, k, *wt);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
         }
+
+        for (h, x) in h.iter_mut().zip([a, b, c, d, e].iter()) {
+            *h = sum!(*h, *x);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (c, h) in digest.chunks_mut(4).zip(h.iter()) {
+        c.clone_from_slice(&h.to_be_bytes());
     }
+    digest
 }
 
 fn sha512_base<V>(input: V, iv: &mut [u64; 8])
@@ -496,4 +568,29 @@ Officiis et placeat alias voluptatem quasi non. Reiciendis qui quo mollitia occa
 
         assert_eq!(result[..], output[..]);
     }
+
+    #[test]
+    fn testsha1() {
+        // the examples of FIPS 180-4 and the empty message
+        let vectors = [
+            ("abc", "a9993e364706816aba3e25717850c26c9cd0d89d"),
+            (
+                "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+            ),
+            ("", "da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+        ];
+        let hash = HashAlg::Sha1;
+        for (input, digest) in vectors.iter() {
+            assert_eq!(hash.hash(*input)[..], crate::crypt::tests::hex(digest)[..]);
+        }
+
+        let million = vec![b'a'; 1_000_000];
+        assert_eq!(
+            hash.hash(million)[..],
+            crate::crypt::tests::hex("34aa973cd4c4daa4f61eeb2bdbad27316534016f")[..]
+        );
+        assert_eq!(hash.size(), 160);
+        assert_eq!(hash.block_size(), 512);
+    }
 }
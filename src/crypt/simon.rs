@@ -0,0 +1,225 @@
+//! The Simon block ciphers, by [Beaulieu et al.](https://eprint.iacr.org/2013/404)
+//! A round is only AND, XOR and rotations, made for hardware, but it is small in software too.
+//!
+//! The sizes and the byte order are the same as for Speck,
+//! the key schedule mixes in one of five constant bit sequences.
+
+use super::speck::{rounds, Word};
+use super::{BlockCipher, CryptoError};
+
+/// The block size and key length in bits, and the number of rounds.
+const PARAMETERS: [(usize, usize, usize); 10] = [
+    (32, 64, 32),
+    (48, 72, 36),
+    (48, 96, 36),
+    (64, 96, 42),
+    (64, 128, 44),
+    (96, 96, 52),
+    (96, 144, 54),
+    (128, 128, 68),
+    (128, 192, 69),
+    (128, 256, 72),
+];
+
+/// The sequences z0 to z4, with the first bit as bit 61.
+const Z: [u64; 5] = [
+    0x3e8958737d12b0e6,
+    0x23be4c2d477c985a,
+    0x2bdc0d262847e5b3,
+    0x36eb19781229cd0f,
+    0x3479ad88170ca4ef,
+];
+
+/// The sequence used by the block size and key length in bits.
+fn sequence(block: usize, key: usize) -> u64 {
+    match (block, key) {
+        (32, 64) | (48, 72) => Z[0],
+        (48, 96) => Z[1],
+        (64, 96) | (96, 96) | (128, 128) => Z[2],
+        (64, 128) | (96, 144) | (128, 192) => Z[3],
+        _ => Z[4],
+    }
+}
+
+pub struct Simon {
+    word: Word,
+    round_keys: Vec<u64>,
+    padding: super::Padding,
+    key: Vec<u8>,
+}
+
+impl Simon {
+    /// The block size is in bytes, and the key has to be one of the lengths
+    /// the paper gives for the block size.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(block_size: usize, key: &[u8]) -> Result<Box<dyn BlockCipher>, CryptoError> {
+        let rounds = rounds(&PARAMETERS, block_size, key.len())?;
+        let word = Word::new(block_size >> 1);
+        Ok(Box::new(Simon {
+            word,
+            round_keys: expand_key(word, key, rounds),
+            padding: super::Padding::default(),
+            key: key.to_vec(),
+        }))
+    }
+
+    fn f(&self, x: u64) -> u64 {
+        let w = self.word;
+        (w.rotl(x, 1) & w.rotl(x, 8)) ^ w.rotl(x, 2)
+    }
+}
+
+fn expand_key(word: Word, key: &[u8], rounds: usize) -> Vec<u64> {
+    let m = key.len() / word.bytes();
+    let z = sequence(word.bytes() << 4, key.len() << 3);
+    // the constant c, all ones except the two lowest bits
+    let c = word.mask() ^ 3;
+
+    let mut k = word.load(key);
+    for i in m..rounds {
+        let mut tmp = word.rotr(k[i - 1], 3);
+        if m == 4 {
+            tmp ^= k[i - 3];
+        }
+        tmp ^= word.rotr(tmp, 1);
+        let bit = (z >> (61 - (i - m) % 62)) & 1;
+        k.push(c ^ bit ^ k[i - m] ^ tmp);
+    }
+    k
+}
+
+impl BlockCipher for Simon {
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let yx = self.word.load(block);
+        let (mut y, mut x) = (yx[0], yx[1]);
+        for k in self.round_keys.iter() {
+            let next = y ^ self.f(x) ^ k;
+            y = x;
+            x = next;
+        }
+        self.word.store(&[y, x], block);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let yx = self.word.load(block);
+        let (mut y, mut x) = (yx[0], yx[1]);
+        for k in self.round_keys.iter().rev() {
+            let prev = x ^ self.f(y) ^ k;
+            x = y;
+            y = prev;
+        }
+        self.word.store(&[y, x], block);
+    }
+
+    fn block_size(&self) -> usize {
+        self.word.bytes() << 1
+    }
+
+    fn change_padding(&mut self, padding: super::Padding) {
+        self.padding = padding;
+    }
+
+    fn padding(&self) -> super::Padding {
+        self.padding
+    }
+
+    fn get_key(&self) -> &[u8] {
+        &self.key
+    }
+
+    /// The block size is kept, so the key has to be one of the lengths for it.
+    fn set_key(&mut self, key: &[u8]) -> Result<(), CryptoError> {
+        let rounds = rounds(&PARAMETERS, self.block_size(), key.len())?;
+        self.round_keys = expand_key(self.word, key, rounds);
+        self.key = key.to_vec();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::tests::hex;
+    use crate::crypt::{Cipher, Padding};
+
+    /// The numbers of the paper are the reversed bytes.
+    fn reversed(s: &str) -> Vec<u8> {
+        let mut bytes = hex(s);
+        bytes.reverse();
+        bytes
+    }
+
+    #[test]
+    fn vectors() {
+        // appendix B of the paper
+        let vectors = [
+            ("1918 1110 0908 0100", "6565 6877", "c69b e9bb"),
+            ("121110 0a0908 020100", "612067 6e696c", "dae5ac 292cac"),
+            (
+                "1a1918 121110 0a0908 020100",
+                "726963 20646e",
+                "6e06a5 acf156",
+            ),
+            (
+                "13121110 0b0a0908 03020100",
+                "6f722067 6e696c63",
+                "5ca2e27f 111a8fc8",
+            ),
+            (
+                "1b1a1918 13121110 0b0a0908 03020100",
+                "656b696c 20646e75",
+                "44c8fc20 b9dfa07a",
+            ),
+            (
+                "0d0c0b0a0908 050403020100",
+                "2072616c6c69 702065687420",
+                "602807a462b4 69063d8ff082",
+            ),
+            (
+                "151413121110 0d0c0b0a0908 050403020100",
+                "746168742074 73756420666f",
+                "ecad1c6c451e 3f59c5db1ae9",
+            ),
+            (
+                "0f0e0d0c0b0a0908 0706050403020100",
+                "6373656420737265 6c6c657661727420",
+                "49681b1e1e54fe3f 65aa832af84e0bbc",
+            ),
+            (
+                "1716151413121110 0f0e0d0c0b0a0908 0706050403020100",
+                "206572656874206e 6568772065626972",
+                "c4ac61effcdc0d4f 6c9c8d6e2597b85b",
+            ),
+            (
+                "1f1e1d1c1b1a1918 1716151413121110 0f0e0d0c0b0a0908 0706050403020100",
+                "74206e69206d6f6f 6d69732061207369",
+                "8d2b5579afc8a3a0 3bf72a87efe7b868",
+            ),
+        ];
+
+        for (key, plaintext, ciphertext) in vectors.iter() {
+            let plaintext = reversed(plaintext);
+            let simon = Simon::new(plaintext.len(), &reversed(key)).unwrap();
+            let mut block = plaintext.clone();
+            simon.encrypt_block(&mut block);
+            assert_eq!(block, reversed(ciphertext));
+            simon.decrypt_block(&mut block);
+            assert_eq!(block, plaintext);
+        }
+    }
+
+    #[test]
+    fn cbc() {
+        // the odd block sizes work with the modes that only need the block size
+        for (size, key_len) in &[(4, 8), (6, 9), (12, 18)] {
+            let mut simon = Simon::new(*size, &vec![0x42; *key_len]).unwrap();
+            simon.change_padding(Padding::PKCS7);
+            let iv = vec![0u8; *size];
+            let mut message = b"a message of a few blocks".to_vec();
+            simon.encrypt(&iv, &mut message).unwrap();
+            assert_eq!(message.len() % size, 0);
+            simon.decrypt(&iv, &mut message).unwrap();
+            assert_eq!(message, b"a message of a few blocks");
+        }
+    }
+}
@@ -0,0 +1,285 @@
+//! The synthetic initialization vector mode, a nonce misuse resistant AEAD.
+//! The IV is a CMAC of the associated data and the plaintext (S2V), and it is used as both the
+//! tag and the counter for the encryption. Reusing a nonce only reveals if the same message
+//! was encrypted twice.
+//!
+//! The ciphertext is the IV followed by the encrypted plaintext.
+//!
+//!     # use codes::crypt::{siv::SIV, CipherTypes, AEAD};
+//!     // AES-SIV-256, two 128 bit keys
+//!     let siv = SIV::new(CipherTypes::AES, &[0x42; 32]).unwrap();
+//!
+//!     // the deterministic mode, with any number of associated data components
+//!     let mut wrapped = b"a key to wrap".to_vec();
+//!     siv.encrypt_components(&[b"header", b"more header"], &mut wrapped).unwrap();
+//!     assert_eq!(wrapped.len(), 16 + 13);
+//!
+//!     siv.decrypt_components(&[b"header", b"more header"], &mut wrapped).unwrap();
+//!     assert_eq!(wrapped, b"a key to wrap");
+//!
+//!     // or with a nonce, like any other AEAD
+//!     let mut message = b"attack at dawn".to_vec();
+//!     siv.encrypt(b"nonce", b"header", &mut message).unwrap();
+//!     siv.decrypt(b"nonce", b"header", &mut message).unwrap();
+//!     assert_eq!(message, b"attack at dawn");
+
+use super::{
+    mac::dbl, mac::CMAC, verify_tag, BlockCipher, CipherKey, CipherTypes, CryptoError, AEAD,
+};
+
+/// The maximum number of associated data components, including the nonce.
+const MAX_COMPONENTS: usize = 126;
+
+pub struct SIV {
+    mac: Box<dyn BlockCipher>,
+    ctr: Box<dyn BlockCipher>,
+}
+
+impl SIV {
+    /// Create a SIV instance of a 128 bit block cipher.
+    /// The key is the MAC key followed by the encryption key, of the same length.
+    /// For AES, 32, 48 or 64 bytes.
+    pub fn new(cipher: CipherTypes, key: &[u8]) -> Result<Self, CryptoError> {
+        if !key.len().is_multiple_of(2) {
+            return Err(CryptoError::InvalidKeyLength(key.len()));
+        }
+        let (mac_key, ctr_key) = key.split_at(key.len() / 2);
+        let invalid = |_| CryptoError::InvalidKeyLength(key.len());
+        let mac = cipher.new(CipherKey { key: mac_key }).map_err(invalid)?;
+        let ctr = cipher.new(CipherKey { key: ctr_key }).map_err(invalid)?;
+
+        if mac.block_size() != 16 {
+            return Err(CryptoError::InvalidParameter(format!(
+                "SIV needs a 128 bit block cipher, not {} bits",
+                mac.block_size() << 3
+            )));
+        }
+
+        Ok(SIV { mac, ctr })
+    }
+
+    /// The pseudo random function of a vector of strings.
+    fn s2v(&self, components: &[&[u8]], plaintext: &[u8]) -> [u8; 16] {
+        let cmac = |text: &[u8]| {
+            let mut block = [0u8; 16];
+            block.clone_from_slice(&CMAC::tag(&*self.mac, text));
+            block
+        };
+
+        let mut d = cmac(&[0u8; 16]);
+        for component in components {
+            d = dbl(&d);
+            for (d, c) in d.iter_mut().zip(cmac(component).iter()) {
+                *d ^= c;
+            }
+        }
+
+        let mut t = plaintext.to_vec();
+        if t.len() >= 16 {
+            // xor the end of the plaintext
+            let offset = t.len() - 16;
+            for (t, d) in t[offset..].iter_mut().zip(d.iter()) {
+                *t ^= d;
+            }
+        } else {
+            t.push(0x80);
+            t.resize(16, 0);
+            for (t, d) in t.iter_mut().zip(dbl(&d).iter()) {
+                *t ^= d;
+            }
+        }
+
+        cmac(&t)
+    }
+
+    /// The IV with two bits cleared, so the 32 bit counters can be incremented as 64 bit
+    /// numbers.
+    fn counter(iv: &[u8]) -> Vec<u8> {
+        let mut q = iv.to_vec();
+        q[8] &= 0x7f;
+        q[12] &= 0x7f;
+        q
+    }
+
+    /// Encrypt deterministically with a list of associated data, prepending the IV.
+    /// A nonce should be the last component.
+    pub fn encrypt_components(
+        &self,
+        components: &[&[u8]],
+        plaintext: &mut Vec<u8>,
+    ) -> Result<(), CryptoError> {
+        if components.len() > MAX_COMPONENTS {
+            return Err(CryptoError::InvalidParameter(format!(
+                "at most {} associated data components",
+                MAX_COMPONENTS
+            )));
+        }
+
+        let iv = self.s2v(components, plaintext);
+        self.ctr.ctr(&Self::counter(&iv), plaintext);
+        plaintext.splice(0..0, iv.iter().copied());
+        Ok(())
+    }
+
+    /// Decrypt something encrypted with `encrypt_components`, with the same associated data.
+    pub fn decrypt_components(
+        &self,
+        components: &[&[u8]],
+        ciphertext: &mut Vec<u8>,
+    ) -> Result<(), CryptoError> {
+        if components.len() > MAX_COMPONENTS {
+            return Err(CryptoError::InvalidParameter(format!(
+                "at most {} associated data components",
+                MAX_COMPONENTS
+            )));
+        }
+        if ciphertext.len() < 16 {
+            return Err(CryptoError::InvalidDataLength(ciphertext.len()));
+        }
+
+        let counter = Self::counter(&ciphertext[..16]);
+        self.ctr.ctr(&counter, &mut ciphertext[16..]);
+
+        let iv = self.s2v(components, &ciphertext[16..]);
+        if let Err(e) = verify_tag(&iv, &ciphertext[..16]) {
+            // do not release the unauthenticated plaintext
+            self.ctr.ctr(&counter, &mut ciphertext[16..]);
+            return Err(e);
+        }
+
+        ciphertext.drain(..16);
+        Ok(())
+    }
+}
+
+impl AEAD for SIV {
+    /// Encrypt with the associated data and nonce as the two components.
+    fn encrypt(&self, nonce: &[u8], ad: &[u8], plaintext: &mut Vec<u8>) -> Result<(), CryptoError> {
+        self.encrypt_components(&[ad, nonce], plaintext)
+    }
+
+    fn decrypt(
+        &self,
+        nonce: &[u8],
+        ad: &[u8],
+        ciphertext: &mut Vec<u8>,
+    ) -> Result<(), CryptoError> {
+        self.decrypt_components(&[ad, nonce], ciphertext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::tests::hex;
+
+    #[test]
+    fn rfc5297_deterministic() {
+        // A.1
+        let siv = SIV::new(
+            CipherTypes::AES,
+            &hex("fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0 f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff"),
+        )
+        .unwrap();
+        let ad = hex("101112131415161718191a1b1c1d1e1f2021222324252627");
+        let plaintext = hex("112233445566778899aabbccddee");
+
+        let mut data = plaintext.clone();
+        siv.encrypt_components(&[&ad], &mut data).unwrap();
+        assert_eq!(
+            data,
+            hex("85632d07c6e8f37f950acd320a2ecc93 40c02b9690c4dc04daef7f6afe5c")
+        );
+
+        siv.decrypt_components(&[&ad], &mut data).unwrap();
+        assert_eq!(data, plaintext);
+    }
+
+    #[test]
+    fn rfc5297_nonce() {
+        // A.2
+        let siv = SIV::new(
+            CipherTypes::AES,
+            &hex("7f7e7d7c7b7a79787776757473727170 404142434445464748494a4b4c4d4e4f"),
+        )
+        .unwrap();
+        let ad1 = hex(
+            "00112233445566778899aabbccddeeff deaddadadeaddadaffeeddccbbaa9988
+             7766554433221100",
+        );
+        let ad2 = hex("102030405060708090a0");
+        let nonce = hex("09f911029d74e35bd84156c5635688c0");
+        let plaintext = b"this is some plaintext to encrypt using SIV-AES".to_vec();
+
+        let mut data = plaintext.clone();
+        siv.encrypt_components(&[&ad1, &ad2, &nonce], &mut data)
+            .unwrap();
+        assert_eq!(
+            data,
+            hex(
+                "7bdb6e3b432667eb06f4d14bff2fbd0f cb900f2fddbe404326601965c889bf17
+                 dba77ceb094fa663b7a3f748ba8af829 ea64ad544a272e9c485b62a3fd5c0d"
+            )
+        );
+
+        siv.decrypt_components(&[&ad1, &ad2, &nonce], &mut data)
+            .unwrap();
+        assert_eq!(data, plaintext);
+    }
+
+    #[test]
+    fn nonce_reuse() {
+        // the same message gives the same ciphertext, but nothing else is revealed
+        let siv = SIV::new(CipherTypes::AES, &[7; 64]).unwrap();
+        let mut a = b"message one".to_vec();
+        let mut b = b"message two".to_vec();
+        let mut c = b"message one".to_vec();
+        siv.encrypt(b"nonce", b"", &mut a).unwrap();
+        siv.encrypt(b"nonce", b"", &mut b).unwrap();
+        siv.encrypt(b"nonce", b"", &mut c).unwrap();
+        assert_eq!(a, c);
+        assert_ne!(a[..16], b[..16]);
+        assert_ne!(a[16..], b[16..]);
+    }
+
+    #[test]
+    fn tampering() {
+        let siv = SIV::new(CipherTypes::Twofish, &[1; 48]).unwrap();
+        let mut sealed = b"Lorem ipsum dolor sit amet".to_vec();
+        siv.encrypt_components(&[b"one", b"two"], &mut sealed)
+            .unwrap();
+
+        for i in 0..sealed.len() {
+            let mut tampered = sealed.clone();
+            tampered[i] ^= 0x02;
+            assert_eq!(
+                siv.decrypt_components(&[b"one", b"two"], &mut tampered),
+                Err(CryptoError::AuthenticationFailure)
+            );
+            tampered[i] ^= 0x02;
+            assert_eq!(tampered, sealed);
+        }
+
+        // the order and number of components matter
+        for components in &[
+            vec![&b"two"[..], &b"one"[..]],
+            vec![&b"one"[..]],
+            vec![&b"one"[..], &b"two"[..], &b""[..]],
+            vec![&b"onetwo"[..]],
+        ] {
+            assert_eq!(
+                siv.decrypt_components(components, &mut sealed.clone()),
+                Err(CryptoError::AuthenticationFailure)
+            );
+        }
+
+        assert_eq!(
+            siv.decrypt_components(&[], &mut vec![0; 15]),
+            Err(CryptoError::InvalidDataLength(15))
+        );
+        assert_eq!(
+            SIV::new(CipherTypes::AES, &[0; 40]).err(),
+            Some(CryptoError::InvalidKeyLength(40))
+        );
+    }
+}
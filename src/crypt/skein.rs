@@ -0,0 +1,329 @@
+//! The Skein hash function, by the [Skein 1.3](https://www.schneier.com/academic/skein/) paper.
+//! Threefish is chained by UBI, where the tweak holds the position and the type of the block.
+//! The key, the configuration, the personalization string, the message and the output
+//! are each processed as a UBI of their own type.
+//!
+//!     # use codes::crypt::skein::*;
+//!     # use codes::crypt::sha::Hash;
+//!     let skein = Skein::new(StateSize::Bits512, 256).unwrap();
+//!     let digest = skein.hash("Lorem ipsum dolor sit amet.");
+//!     assert_eq!(digest.len(), 32);
+
+use super::mac::MAC;
+use super::sha::Hash;
+use super::threefish::{encrypt, extend_key, extend_tweak, words};
+use super::CryptoError;
+
+const TYPE_KEY: u64 = 0;
+const TYPE_CFG: u64 = 4;
+const TYPE_PRS: u64 = 8;
+const TYPE_MSG: u64 = 48;
+const TYPE_OUT: u64 = 63;
+
+/// The state size is the block size of the underlying Threefish.
+#[derive(Clone, Copy)]
+pub enum StateSize {
+    Bits256,
+    Bits512,
+    Bits1024,
+}
+
+impl StateSize {
+    fn bytes(self) -> usize {
+        match self {
+            StateSize::Bits256 => 32,
+            StateSize::Bits512 => 64,
+            StateSize::Bits1024 => 128,
+        }
+    }
+}
+
+/// Compress the message with the chaining value in UBI mode.
+/// The message is padded with zeros to whole blocks, and there is always one.
+fn ubi(g: &[u64], message: &[u8], ty: u64) -> Vec<u64> {
+    let bs = g.len() << 3;
+    let blocks = message.len().div_ceil(bs).max(1);
+    let mut g = g.to_vec();
+    let mut block = vec![0u8; bs];
+
+    for i in 0..blocks {
+        let m = &message[i * bs..message.len().min((i + 1) * bs)];
+        block.iter_mut().for_each(|b| *b = 0);
+        block[..m.len()].clone_from_slice(m);
+
+        // the position is the number of message bytes processed, including this block
+        let position = (i * bs + m.len()) as u128;
+        let first = if i == 0 { 1 << 62 } else { 0 };
+        let last = if i + 1 == blocks { 1 << 63 } else { 0 };
+        let tweak = [
+            position as u64,
+            (position >> 64) as u64 | (ty << 56) | first | last,
+        ];
+
+        let plaintext = words(&block);
+        let mut h = plaintext.clone();
+        encrypt(&extend_key(&g), &extend_tweak(tweak), &mut h);
+        for ((g, h), m) in g.iter_mut().zip(h.iter()).zip(plaintext.iter()) {
+            *g = h ^ m;
+        }
+    }
+
+    g
+}
+
+/// Skein with a state size and an output size in bits,
+/// and optionally a personalization string, like an application name and a date.
+pub struct Skein {
+    state: StateSize,
+    out_len: usize,
+    personalization: Vec<u8>,
+}
+
+impl Skein {
+    /// The output length has to be a whole number of bytes.
+    pub fn new(state: StateSize, out_len: usize) -> Result<Self, CryptoError> {
+        if out_len == 0 || out_len & 7 != 0 {
+            return Err(CryptoError::InvalidParameter(format!(
+                "an output of {} bits, it must be a positive number of bytes",
+                out_len
+            )));
+        }
+        Ok(Skein {
+            state,
+            out_len,
+            personalization: Vec::new(),
+        })
+    }
+
+    pub fn personalized(mut self, personalization: &[u8]) -> Self {
+        self.personalization = personalization.to_vec();
+        self
+    }
+
+    /// The digest of the message, keyed if the key is not empty.
+    pub fn digest(&self, key: &[u8], message: &[u8]) -> Vec<u8> {
+        let bs = self.state.bytes();
+        let mut g = vec![0u64; bs >> 3];
+        if !key.is_empty() {
+            g = ubi(&g, key, TYPE_KEY);
+        }
+
+        // the schema identifier "SHA3", version 1 and no tree hashing
+        let mut config = [0u8; 32];
+        config[..4].clone_from_slice(b"SHA3");
+        config[4] = 1;
+        config[8..16].clone_from_slice(&(self.out_len as u64).to_le_bytes());
+        g = ubi(&g, &config, TYPE_CFG);
+
+        if !self.personalization.is_empty() {
+            g = ubi(&g, &self.personalization, TYPE_PRS);
+        }
+        g = ubi(&g, message, TYPE_MSG);
+
+        // the output is the UBI of a counter, for as many blocks as needed
+        let out_bytes = self.out_len >> 3;
+        let mut output = Vec::with_capacity(out_bytes);
+        for counter in 0..out_bytes.div_ceil(bs) as u64 {
+            let block = ubi(&g, &counter.to_le_bytes(), TYPE_OUT);
+            output.extend(block.iter().flat_map(|w| w.to_le_bytes().to_vec()));
+        }
+        output.truncate(out_bytes);
+        output
+    }
+}
+
+impl Hash for Skein {
+    fn hash<T>(&self, data: T) -> Box<[u8]>
+    where
+        std::vec::Vec<u8>: From<T>,
+    {
+        self.digest(&[], &Vec::from(data)).into_boxed_slice()
+    }
+
+    fn size(&self) -> usize {
+        self.out_len
+    }
+
+    fn block_size(&self) -> usize {
+        self.state.bytes() << 3
+    }
+}
+
+/// Skein-MAC, where the key is processed before the configuration.
+/// A tag shorter than the output size is truncated, like with HMAC.
+impl MAC for Skein {
+    fn mac(&self, key: &[u8], text: &[u8], tag_len: usize) -> Result<Vec<u8>, CryptoError> {
+        if tag_len == 0 || tag_len > self.out_len || tag_len & 7 != 0 {
+            return Err(CryptoError::InvalidTagLength(tag_len));
+        }
+        let mut tag = self.digest(key, text);
+        tag.truncate(tag_len >> 3);
+        Ok(tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::tests::hex;
+
+    /// The bytes 0xff, 0xfe and so on, as in the known answers.
+    fn countdown(len: usize) -> Vec<u8> {
+        (0..len).map(|i| 0xff - i as u8).collect()
+    }
+
+    #[test]
+    fn skein256() {
+        // the known answers of the Skein 1.3 paper, appendix C
+        let skein = Skein::new(StateSize::Bits256, 256).unwrap();
+        let vectors = [
+            (
+                1,
+                "0b98dcd198ea0e50a7a244c444e25c23da30c10fc9a1f270a6637f1f34e67ed2",
+            ),
+            (
+                32,
+                "8d0fa4ef777fd759dfd4044e6f6a5ac3c774aec943dcfc07927b723b5dbf408b",
+            ),
+            (
+                64,
+                "df28e916630d0b44c4a849dc9a02f07a07cb30f732318256b15d865ac4ae162f",
+            ),
+        ];
+        for (len, digest) in vectors.iter() {
+            assert_eq!(skein.hash(countdown(*len))[..], hex(digest)[..]);
+        }
+    }
+
+    #[test]
+    fn skein512() {
+        let skein = Skein::new(StateSize::Bits512, 512).unwrap();
+        let vectors = [
+            (
+                1,
+                "71b7bce6fe6452227b9ced6014249e5bf9a9754c3ad618ccc4e0aae16b316cc8
+                 ca698d864307ed3e80b6ef1570812ac5272dc409b5a012df2a579102f340617a",
+            ),
+            (
+                64,
+                "45863ba3be0c4dfc27e75d358496f4ac9a736a505d9313b42b2f5eada79fc17f
+                 63861e947afb1d056aa199575ad3f8c9a3cc1780b5e5fa4cae050e989876625b",
+            ),
+            (
+                128,
+                "91cca510c263c4ddd010530a33073309628631f308747e1bcbaa90e451cab92e
+                 5188087af4188773a332303e6667a7a210856f742139000071f48e8ba2a5adb7",
+            ),
+        ];
+        for (len, digest) in vectors.iter() {
+            assert_eq!(skein.hash(countdown(*len))[..], hex(digest)[..]);
+        }
+    }
+
+    #[test]
+    fn skein1024() {
+        let skein = Skein::new(StateSize::Bits1024, 1024).unwrap();
+        let vectors = [
+            (
+                1,
+                "e62c05802ea0152407cdd8787fda9e35703de862a4fbc119cff8590afe79250b
+                 ccc8b3faf1bd2422ab5c0d263fb2f8afb3f796f048000381531b6f00d85161bc
+                 0fff4bef2486b1ebcd3773fabf50ad4ad5639af9040e3f29c6c931301bf79832
+                 e9da09857e831e82ef8b4691c235656515d437d2bda33bcec001c67ffde15ba8",
+            ),
+            (
+                128,
+                "1f3e02c46fb80a3fcd2dfbbc7c173800b40c60c2354af551189ebf433c3d85f9
+                 ff1803e6d920493179ed7ae7fce69c3581a5a2f82d3e0c7a295574d0cd7d217c
+                 484d2f6313d59a7718ead07d0729c24851d7e7d2491b902d489194e6b7d369db
+                 0ab7aa106f0ee0a39a42efc54f18d93776080985f907574f995ec6a37153a578",
+            ),
+            (
+                256,
+                "842a53c99c12b0cf80cf69491be5e2f7515de8733b6ea9422dfd676665b5fa42
+                 ffb3a9c48c217777950848cecdb48f640f81fb92bef6f88f7a85c1f7cd1446c9
+                 161c0afe8f25ae444f40d3680081c35aa43f640fd5fa3c3c030bcc06abac01d0
+                 98bcc984ebd8322712921e00b1ba07d6d01f26907050255ef2c8e24f716c52a5",
+            ),
+        ];
+        for (len, digest) in vectors.iter() {
+            assert_eq!(skein.hash(countdown(*len))[..], hex(digest)[..]);
+        }
+    }
+
+    #[test]
+    fn empty() {
+        // the digests of the empty message by the reference implementation
+        let vectors = [
+            (
+                StateSize::Bits256,
+                256,
+                "c8877087da56e072870daa843f176e9453115929094c3a40c463a196c29bf7ba",
+            ),
+            (
+                StateSize::Bits512,
+                256,
+                "39ccc4554a8b31853b9de7a1fe638a24cce6b35a55f2431009e18780335d2621",
+            ),
+            (
+                StateSize::Bits1024,
+                1024,
+                "0fff9563bb3279289227ac77d319b6fff8d7e9f09da1247b72a0a265cd6d2a62
+                 645ad547ed8193db48cff847c06494a03f55666d3b47eb4c20456c9373c86297
+                 d630d5578ebd34cb40991578f9f52b18003efa35d3da6553ff35db91b81ab890
+                 bec1b189b7f52cb2a783ebb7d823d725b0b4a71f6824e88f68f982eefc6d19c6",
+            ),
+        ];
+        for (state, size, digest) in vectors.iter() {
+            let skein = Skein::new(*state, *size).unwrap();
+            assert_eq!(skein.size(), *size);
+            assert_eq!(skein.hash(Vec::new())[..], hex(digest)[..]);
+        }
+    }
+
+    #[test]
+    fn mac() {
+        let skein = Skein::new(StateSize::Bits512, 256).unwrap();
+        let key = b"a key of any length";
+
+        // the key changes the chaining value before the configuration,
+        // and the empty key is the same as no key
+        let tag = skein.mac(key, b"message", 256).unwrap();
+        assert_ne!(tag[..], skein.hash("message")[..]);
+        assert_eq!(
+            skein.mac(&[], b"message", 256).unwrap()[..],
+            skein.hash("message")[..]
+        );
+        assert_eq!(skein.mac(key, b"message", 128).unwrap(), tag[..16]);
+        assert!(skein.verify(key, b"message", &tag).is_ok());
+        assert!(skein.verify(key, b"massage", &tag).is_err());
+        assert!(skein.mac(key, b"message", 512).is_err());
+        assert!(Skein::new(StateSize::Bits512, 100).is_err());
+    }
+
+    #[test]
+    fn personalization() {
+        let skein = Skein::new(StateSize::Bits256, 256).unwrap();
+        let a = skein.personalized(b"20261018 app@example.com a");
+        let b = Skein::new(StateSize::Bits256, 256)
+            .unwrap()
+            .personalized(b"20261018 app@example.com b");
+        assert_ne!(a.hash("message"), b.hash("message"));
+        assert_ne!(
+            a.hash("message"),
+            Skein::new(StateSize::Bits256, 256).unwrap().hash("message")
+        );
+    }
+
+    #[test]
+    fn long_output() {
+        // the output length is a part of the configuration,
+        // so a longer output does not start with a shorter one
+        let short = Skein::new(StateSize::Bits256, 256).unwrap().hash("message");
+        let long = Skein::new(StateSize::Bits256, 1024)
+            .unwrap()
+            .hash("message");
+        assert_eq!(long.len(), 128);
+        assert_ne!(long[..32], short[..]);
+    }
+}
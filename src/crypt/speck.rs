@@ -0,0 +1,287 @@
+//! The Speck block ciphers, by [Beaulieu et al.](https://eprint.iacr.org/2013/404)
+//! A round is an addition, two rotations and two XOR of the two words in the block,
+//! so there are no tables to fit in the memory of a microcontroller.
+//!
+//! There are ten block and key sizes, the words are little endian with the key word k0 first,
+//! and the block is y followed by x.
+//! The test vectors of the paper are written as numbers, which are the reversed bytes.
+
+use super::{BlockCipher, CryptoError};
+
+/// The block size and key length in bits, and the number of rounds.
+const PARAMETERS: [(usize, usize, usize); 10] = [
+    (32, 64, 22),
+    (48, 72, 22),
+    (48, 96, 23),
+    (64, 96, 26),
+    (64, 128, 27),
+    (96, 96, 28),
+    (96, 144, 29),
+    (128, 128, 32),
+    (128, 192, 33),
+    (128, 256, 34),
+];
+
+/// The number of rounds of the block size and key length in bytes.
+pub(crate) fn rounds(
+    parameters: &[(usize, usize, usize)],
+    block_size: usize,
+    key_len: usize,
+) -> Result<usize, CryptoError> {
+    let sizes: Vec<_> = parameters
+        .iter()
+        .filter(|(b, _, _)| *b == block_size << 3)
+        .collect();
+    if sizes.is_empty() {
+        return Err(CryptoError::InvalidParameter(format!(
+            "a block of {} bits, it must be 32, 48, 64, 96 or 128",
+            block_size << 3
+        )));
+    }
+    sizes
+        .iter()
+        .find(|(_, k, _)| *k == key_len << 3)
+        .map(|(_, _, t)| *t)
+        .ok_or(CryptoError::InvalidKeyLength(key_len))
+}
+
+/// Arithmetic on words of 16 to 64 bits, held in the low bits of a `u64`.
+#[derive(Clone, Copy)]
+pub(crate) struct Word {
+    bits: u32,
+}
+
+impl Word {
+    pub(crate) fn new(bytes: usize) -> Self {
+        Word {
+            bits: (bytes << 3) as u32,
+        }
+    }
+
+    pub(crate) fn bytes(self) -> usize {
+        (self.bits >> 3) as usize
+    }
+
+    pub(crate) fn mask(self) -> u64 {
+        u64::MAX >> (64 - self.bits)
+    }
+
+    pub(crate) fn rotl(self, x: u64, r: u32) -> u64 {
+        ((x << r) | (x >> (self.bits - r))) & self.mask()
+    }
+
+    pub(crate) fn rotr(self, x: u64, r: u32) -> u64 {
+        self.rotl(x, self.bits - r)
+    }
+
+    fn add(self, x: u64, y: u64) -> u64 {
+        x.wrapping_add(y) & self.mask()
+    }
+
+    fn sub(self, x: u64, y: u64) -> u64 {
+        x.wrapping_sub(y) & self.mask()
+    }
+
+    /// Split the bytes into little endian words.
+    pub(crate) fn load(self, bytes: &[u8]) -> Vec<u64> {
+        bytes
+            .chunks(self.bytes())
+            .map(|c| c.iter().rev().fold(0, |w, b| (w << 8) | *b as u64))
+            .collect()
+    }
+
+    pub(crate) fn store(self, words: &[u64], bytes: &mut [u8]) {
+        for (c, w) in bytes.chunks_mut(self.bytes()).zip(words.iter()) {
+            for (i, b) in c.iter_mut().enumerate() {
+                *b = (w >> (i << 3)) as u8;
+            }
+        }
+    }
+}
+
+pub struct Speck {
+    word: Word,
+    round_keys: Vec<u64>,
+    padding: super::Padding,
+    key: Vec<u8>,
+}
+
+impl Speck {
+    /// The block size is in bytes, and the key has to be one of the lengths
+    /// the paper gives for the block size.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(block_size: usize, key: &[u8]) -> Result<Box<dyn BlockCipher>, CryptoError> {
+        let rounds = rounds(&PARAMETERS, block_size, key.len())?;
+        let word = Word::new(block_size >> 1);
+        Ok(Box::new(Speck {
+            word,
+            round_keys: expand_key(word, key, rounds),
+            padding: super::Padding::default(),
+            key: key.to_vec(),
+        }))
+    }
+}
+
+/// The rotations are smaller for the 32 bit block.
+fn alpha_beta(word: Word) -> (u32, u32) {
+    if word.bytes() == 2 {
+        (7, 2)
+    } else {
+        (8, 3)
+    }
+}
+
+/// The key schedule uses the round function, with the round number as the key.
+fn expand_key(word: Word, key: &[u8], rounds: usize) -> Vec<u64> {
+    let (alpha, beta) = alpha_beta(word);
+    let words = word.load(key);
+    let mut k = words[0];
+    let mut l = words[1..].to_vec();
+
+    let mut round_keys = Vec::with_capacity(rounds);
+    round_keys.push(k);
+    for i in 0..rounds - 1 {
+        let next = word.add(word.rotr(l[i], alpha), k) ^ i as u64;
+        k = word.rotl(k, beta) ^ next;
+        l.push(next);
+        round_keys.push(k);
+    }
+    round_keys
+}
+
+impl BlockCipher for Speck {
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let w = self.word;
+        let (alpha, beta) = alpha_beta(w);
+        let yx = w.load(block);
+        let (mut y, mut x) = (yx[0], yx[1]);
+        for k in self.round_keys.iter() {
+            x = w.add(w.rotr(x, alpha), y) ^ k;
+            y = w.rotl(y, beta) ^ x;
+        }
+        w.store(&[y, x], block);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let w = self.word;
+        let (alpha, beta) = alpha_beta(w);
+        let yx = w.load(block);
+        let (mut y, mut x) = (yx[0], yx[1]);
+        for k in self.round_keys.iter().rev() {
+            y = w.rotr(y ^ x, beta);
+            x = w.rotl(w.sub(x ^ k, y), alpha);
+        }
+        w.store(&[y, x], block);
+    }
+
+    fn block_size(&self) -> usize {
+        self.word.bytes() << 1
+    }
+
+    fn change_padding(&mut self, padding: super::Padding) {
+        self.padding = padding;
+    }
+
+    fn padding(&self) -> super::Padding {
+        self.padding
+    }
+
+    fn get_key(&self) -> &[u8] {
+        &self.key
+    }
+
+    /// The block size is kept, so the key has to be one of the lengths for it.
+    fn set_key(&mut self, key: &[u8]) -> Result<(), CryptoError> {
+        let rounds = rounds(&PARAMETERS, self.block_size(), key.len())?;
+        self.round_keys = expand_key(self.word, key, rounds);
+        self.key = key.to_vec();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::tests::hex;
+
+    /// The numbers of the paper are the reversed bytes.
+    fn reversed(s: &str) -> Vec<u8> {
+        let mut bytes = hex(s);
+        bytes.reverse();
+        bytes
+    }
+
+    #[test]
+    fn vectors() {
+        // appendix C of the paper
+        let vectors = [
+            ("1918 1110 0908 0100", "6574 694c", "a868 42f2"),
+            ("121110 0a0908 020100", "20796c 6c6172", "c049a5 385adc"),
+            (
+                "1a1918 121110 0a0908 020100",
+                "6d2073 696874",
+                "735e10 b6445d",
+            ),
+            (
+                "13121110 0b0a0908 03020100",
+                "74614620 736e6165",
+                "9f7952ec 4175946c",
+            ),
+            (
+                "1b1a1918 13121110 0b0a0908 03020100",
+                "3b726574 7475432d",
+                "8c6fa548 454e028b",
+            ),
+            (
+                "0d0c0b0a0908 050403020100",
+                "65776f68202c 656761737520",
+                "9e4d09ab7178 62bdde8f79aa",
+            ),
+            (
+                "151413121110 0d0c0b0a0908 050403020100",
+                "656d6974206e 69202c726576",
+                "2bf31072228a 7ae440252ee6",
+            ),
+            (
+                "0f0e0d0c0b0a0908 0706050403020100",
+                "6c61766975716520 7469206564616d20",
+                "a65d985179783265 7860fedf5c570d18",
+            ),
+            (
+                "1716151413121110 0f0e0d0c0b0a0908 0706050403020100",
+                "7261482066656968 43206f7420746e65",
+                "1be4cf3a13135566 f9bc185de03c1886",
+            ),
+            (
+                "1f1e1d1c1b1a1918 1716151413121110 0f0e0d0c0b0a0908 0706050403020100",
+                "65736f6874206e49 202e72656e6f6f70",
+                "4109010405c0f53e 4eeeb48d9c188f43",
+            ),
+        ];
+
+        for (key, plaintext, ciphertext) in vectors.iter() {
+            let plaintext = reversed(plaintext);
+            let speck = Speck::new(plaintext.len(), &reversed(key)).unwrap();
+            let mut block = plaintext.clone();
+            speck.encrypt_block(&mut block);
+            assert_eq!(block, reversed(ciphertext));
+            speck.decrypt_block(&mut block);
+            assert_eq!(block, plaintext);
+        }
+    }
+
+    #[test]
+    fn sizes() {
+        assert!(Speck::new(16, &[0; 16]).is_ok());
+        assert_eq!(
+            Speck::new(16, &[0; 12]).err(),
+            Some(CryptoError::InvalidKeyLength(12))
+        );
+        assert!(Speck::new(10, &[0; 16]).is_err());
+
+        let mut speck = Speck::new(8, &[0; 12]).unwrap();
+        assert!(speck.set_key(&[0; 16]).is_ok());
+        assert!(speck.set_key(&[0; 8]).is_err());
+        assert_eq!(speck.block_size(), 8);
+    }
+}
@@ -0,0 +1,399 @@
+//! Encrypt while writing and decrypt while reading, without holding the whole message in
+//! memory. CBC (with the padding of the cipher) and the ChaCha20 keystreams are supported.
+//!
+//!     # use codes::crypt::{aes::{AES, AESKey}, stream::{DecryptReader, EncryptWriter}};
+//!     # use std::io::{Read, Write};
+//!     let aes = AES::new(AESKey::AES128([0x42; 16]));
+//!     let iv = [0u8; 16];
+//!
+//!     let mut writer = EncryptWriter::cbc(&*aes, &iv, Vec::new()).unwrap();
+//!     writer.write_all(b"Lorem ipsum ").unwrap();
+//!     writer.write_all(b"dolor sit amet").unwrap();
+//!     // the padding is added when finishing
+//!     let ciphertext = writer.finish().unwrap();
+//!
+//!     let mut reader = DecryptReader::cbc(&*aes, &iv, &ciphertext[..]).unwrap();
+//!     let mut plaintext = String::new();
+//!     reader.read_to_string(&mut plaintext).unwrap();
+//!     assert_eq!(plaintext, "Lorem ipsum dolor sit amet");
+
+use std::io::{self, Read, Write};
+
+use super::{chacha20::KeyStream, BlockCipher, CryptoError};
+
+/// The number of bytes read from the inner reader at a time.
+const CHUNK: usize = 1 << 16;
+
+fn io_error(e: CryptoError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+enum Mode<'a> {
+    Block {
+        cipher: &'a (dyn BlockCipher + 'static),
+        /// the last ciphertext block, or the iv.
+        chain: Vec<u8>,
+    },
+    Stream(KeyStream),
+}
+
+impl<'a> Mode<'a> {
+    fn cbc(cipher: &'a (dyn BlockCipher + 'static), iv: &[u8]) -> Result<Self, CryptoError> {
+        if iv.len() != cipher.block_size() {
+            return Err(CryptoError::InvalidIvLength {
+                expected: cipher.block_size(),
+                got: iv.len(),
+            });
+        }
+        Ok(Mode::Block {
+            cipher,
+            chain: iv.to_vec(),
+        })
+    }
+}
+
+/// Encrypts everything written to it, and writes the ciphertext to the inner writer.
+/// With CBC, the last partial block is only written by `finish`.
+pub struct EncryptWriter<'a, W: Write> {
+    inner: W,
+    mode: Mode<'a>,
+    /// plaintext that is not a whole block yet.
+    buffer: Vec<u8>,
+}
+
+impl<'a, W: Write> EncryptWriter<'a, W> {
+    /// Encrypt in CBC mode, padded with the padding of the cipher.
+    pub fn cbc(
+        cipher: &'a (dyn BlockCipher + 'static),
+        iv: &[u8],
+        inner: W,
+    ) -> Result<Self, CryptoError> {
+        Ok(EncryptWriter {
+            inner,
+            mode: Mode::cbc(cipher, iv)?,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Encrypt with a ChaCha20 or XChaCha20 keystream.
+    pub fn keystream(keystream: KeyStream, inner: W) -> Self {
+        EncryptWriter {
+            inner,
+            mode: Mode::Stream(keystream),
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Pad and write the last block, and return the inner writer.
+    /// Without it the end of a CBC encrypted message is lost.
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Mode::Block { cipher, chain } = &mut self.mode {
+            let mut last = std::mem::take(&mut self.buffer);
+            cipher.padding().pad(&mut last, cipher.block_size());
+            cipher.cbc_encrypt(chain, &mut last).map_err(io_error)?;
+            self.inner.write_all(&last)?;
+        }
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<'a, W: Write> Write for EncryptWriter<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.mode {
+            Mode::Block { cipher, chain } => {
+                self.buffer.extend_from_slice(buf);
+                let bs = cipher.block_size();
+                let whole = self.buffer.len() - self.buffer.len() % bs;
+                if whole > 0 {
+                    let rest = self.buffer.split_off(whole);
+                    cipher
+                        .cbc_encrypt(chain, &mut self.buffer)
+                        .map_err(io_error)?;
+                    chain.clone_from_slice(&self.buffer[(whole - bs)..]);
+                    self.inner.write_all(&self.buffer)?;
+                    self.buffer = rest;
+                }
+            }
+            Mode::Stream(keystream) => {
+                let mut data = buf.to_vec();
+                keystream.apply(&mut data).map_err(io::Error::other)?;
+                self.inner.write_all(&data)?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decrypts everything read from the inner reader.
+/// With CBC, the last two blocks are held back until the end, since they might be padding.
+/// Malformed ciphertext gives an error of kind `InvalidData`.
+pub struct DecryptReader<'a, R: Read> {
+    inner: R,
+    mode: Mode<'a>,
+    /// ciphertext that is not a whole block yet.
+    pending: Vec<u8>,
+    /// decrypted plaintext that is not returned yet.
+    plaintext: Vec<u8>,
+    /// the start of the plaintext not returned yet.
+    position: usize,
+    finished: bool,
+}
+
+impl<'a, R: Read> DecryptReader<'a, R> {
+    /// Decrypt in CBC mode, with the padding of the cipher.
+    pub fn cbc(
+        cipher: &'a (dyn BlockCipher + 'static),
+        iv: &[u8],
+        inner: R,
+    ) -> Result<Self, CryptoError> {
+        Ok(DecryptReader {
+            inner,
+            mode: Mode::cbc(cipher, iv)?,
+            pending: Vec::new(),
+            plaintext: Vec::new(),
+            position: 0,
+            finished: false,
+        })
+    }
+
+    /// Decrypt with a ChaCha20 or XChaCha20 keystream.
+    pub fn keystream(keystream: KeyStream, inner: R) -> Self {
+        DecryptReader {
+            inner,
+            mode: Mode::Stream(keystream),
+            pending: Vec::new(),
+            plaintext: Vec::new(),
+            position: 0,
+            finished: false,
+        }
+    }
+
+    /// Return the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// The plaintext that is ready to be returned.
+    fn available(&self, cipher: &(dyn BlockCipher + 'static)) -> usize {
+        if self.finished {
+            self.plaintext.len()
+        } else {
+            self.plaintext
+                .len()
+                .saturating_sub(cipher.block_size() << 1)
+        }
+    }
+
+    /// Read and decrypt more of the inner reader.
+    fn fill(&mut self) -> io::Result<()> {
+        let (cipher, chain) = match &mut self.mode {
+            Mode::Block { cipher, chain } => (*cipher, chain),
+            Mode::Stream(_) => unreachable!(),
+        };
+        let bs = cipher.block_size();
+
+        let start = self.pending.len();
+        self.pending.resize(start + CHUNK, 0);
+        let n = self.inner.read(&mut self.pending[start..])?;
+        self.pending.truncate(start + n);
+
+        if n == 0 {
+            self.finished = true;
+            if !self.pending.is_empty() {
+                return Err(io_error(CryptoError::InvalidDataLength(self.pending.len())));
+            }
+            // what is left is at most the two last blocks
+            let mut last = self.plaintext.split_off(self.position);
+            cipher.padding().strip(&mut last, bs).map_err(io_error)?;
+            self.plaintext = last;
+            self.position = 0;
+            return Ok(());
+        }
+
+        let whole = self.pending.len() - self.pending.len() % bs;
+        if whole > 0 {
+            let rest = self.pending.split_off(whole);
+            let next_chain = self.pending[(whole - bs)..].to_vec();
+            cipher
+                .cbc_decrypt(chain, &mut self.pending)
+                .map_err(io_error)?;
+            *chain = next_chain;
+
+            // drop what is already read, and add the new plaintext
+            self.plaintext.drain(..self.position);
+            self.position = 0;
+            self.plaintext.append(&mut self.pending);
+            self.pending = rest;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, R: Read> Read for DecryptReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let cipher = match &mut self.mode {
+            Mode::Stream(keystream) => {
+                let n = self.inner.read(buf)?;
+                keystream.apply(&mut buf[..n]).map_err(io::Error::other)?;
+                return Ok(n);
+            }
+            Mode::Block { cipher, .. } => *cipher,
+        };
+
+        while self.available(cipher) <= self.position && !self.finished {
+            self.fill()?;
+        }
+
+        let end = self.available(cipher).min(self.position + buf.len());
+        let n = end.saturating_sub(self.position);
+        buf[..n].clone_from_slice(&self.plaintext[self.position..end]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::aes::{AESKey, AES};
+    use crate::crypt::chacha20::{ChaCha20, XChaCha20};
+    use crate::crypt::twofish::{Twofish, TwofishKey};
+    use crate::crypt::{Cipher, Padding};
+
+    /// A reader that returns at most a few bytes at a time.
+    struct Trickle<'a>(&'a [u8], usize);
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.1.min(buf.len()).min(self.0.len());
+            buf[..n].clone_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn cbc_same_as_buffer() {
+        let plaintext: Vec<u8> = (0..1000).map(|i| (i * 13) as u8).collect();
+        let iv = [5u8; 16];
+
+        for padding in &[Padding::Length, Padding::PKCS7, Padding::ISO7816] {
+            let mut aes = AES::new(AESKey::AES192([1; 24]));
+            aes.change_padding(*padding);
+
+            for len in &[0, 1, 15, 16, 17, 31, 32, 100, 1000] {
+                let mut expected = plaintext[..*len].to_vec();
+                aes.encrypt(&iv, &mut expected).unwrap();
+
+                // written in pieces of different sizes
+                let mut writer = EncryptWriter::cbc(&*aes, &iv, Vec::new()).unwrap();
+                for piece in plaintext[..*len].chunks(7) {
+                    writer.write_all(piece).unwrap();
+                }
+                let ciphertext = writer.finish().unwrap();
+                assert_eq!(ciphertext, expected, "{:?} {}", padding, len);
+
+                for size in &[1, 5, 16, 1000] {
+                    let mut reader =
+                        DecryptReader::cbc(&*aes, &iv, Trickle(&ciphertext, *size)).unwrap();
+                    let mut decrypted = Vec::new();
+                    reader.read_to_end(&mut decrypted).unwrap();
+                    assert_eq!(decrypted, plaintext[..*len]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cbc_large() {
+        // more than a chunk, read in small pieces
+        let twofish = Twofish::new(TwofishKey::TK128([9; 16]));
+        let plaintext: Vec<u8> = (0..300_000).map(|i| (i % 253) as u8).collect();
+        let iv = [0u8; 16];
+
+        let mut writer = EncryptWriter::cbc(&*twofish, &iv, Vec::new()).unwrap();
+        writer.write_all(&plaintext).unwrap();
+        let ciphertext = writer.finish().unwrap();
+
+        let mut reader = DecryptReader::cbc(&*twofish, &iv, &ciphertext[..]).unwrap();
+        let mut decrypted = Vec::new();
+        let mut buf = [0u8; 1000];
+        loop {
+            let n = reader.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            decrypted.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn cbc_malformed() {
+        let aes = AES::new(AESKey::AES128([1; 16]));
+        let iv = [0u8; 16];
+        let mut ciphertext = b"some message".to_vec();
+        aes.encrypt(&iv, &mut ciphertext).unwrap();
+
+        // not whole blocks
+        let mut reader = DecryptReader::cbc(&*aes, &iv, &ciphertext[..20]).unwrap();
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // bad padding
+        ciphertext[15] ^= 0x80;
+        let mut reader = DecryptReader::cbc(&*aes, &iv, &ciphertext[..]).unwrap();
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        assert!(EncryptWriter::cbc(&*aes, &[0; 8], Vec::new()).is_err());
+    }
+
+    #[test]
+    fn chacha_same_as_buffer() {
+        let key = [3u8; 32];
+        let plaintext: Vec<u8> = (0..1000).map(|i| (i * 7) as u8).collect();
+
+        let chacha = ChaCha20::new(&key);
+        let mut expected = plaintext.clone();
+        chacha.encrypt(&[1; 8], &mut expected).unwrap();
+
+        let mut writer = EncryptWriter::keystream(chacha.keystream(&[1; 8]).unwrap(), Vec::new());
+        for piece in plaintext.chunks(33) {
+            writer.write_all(piece).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), expected);
+
+        let xchacha = XChaCha20::new(&key);
+        let mut expected = plaintext.clone();
+        xchacha.encrypt(&[2; 24], &mut expected).unwrap();
+
+        let mut reader =
+            DecryptReader::keystream(xchacha.keystream(&[2; 24]).unwrap(), Trickle(&expected, 13));
+        let mut decrypted = Vec::new();
+        reader.read_to_end(&mut decrypted).unwrap();
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn chacha_exhausted() {
+        // the last block of the 32 bit IETF counter, where 65 bytes are one too many
+        let chacha = ChaCha20::ietf(&[3u8; 32]);
+        let keystream = || chacha.keystream_from(&[0; 12], u32::MAX as u64).unwrap();
+
+        let mut writer = EncryptWriter::keystream(keystream(), Vec::new());
+        let err = writer.write(&[0; 65]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+        assert!(writer.write(&[0; 64]).is_ok());
+
+        let mut reader = DecryptReader::keystream(keystream(), &[0u8; 65][..]);
+        let err = reader.read(&mut [0; 65]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+    }
+}
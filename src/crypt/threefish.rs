@@ -0,0 +1,347 @@
+//! The Threefish tweakable block cipher, by the [Skein 1.3](https://www.schneier.com/academic/skein/) paper.
+//! The block is as large as the key, 256, 512 or 1024 bits, and there is a 128 bit tweak.
+//! It is built from additions, rotations and XOR of 64 bit words, so there are no tables.
+
+use super::BlockCipher;
+
+/// The parity constant of the key schedule.
+const C240: u64 = 0x1BD11BDAA9FC1A22;
+
+const R256: [[u32; 2]; 8] = [
+    [14, 16],
+    [52, 57],
+    [23, 40],
+    [5, 37],
+    [25, 33],
+    [46, 12],
+    [58, 22],
+    [32, 32],
+];
+
+const R512: [[u32; 4]; 8] = [
+    [46, 36, 19, 37],
+    [33, 27, 14, 42],
+    [17, 49, 36, 39],
+    [44, 9, 54, 56],
+    [39, 30, 34, 24],
+    [13, 50, 10, 17],
+    [25, 29, 39, 43],
+    [8, 35, 56, 22],
+];
+
+const R1024: [[u32; 8]; 8] = [
+    [24, 13, 8, 47, 8, 17, 22, 37],
+    [38, 19, 10, 55, 49, 18, 23, 52],
+    [33, 4, 51, 13, 34, 41, 59, 17],
+    [5, 20, 48, 41, 47, 28, 16, 25],
+    [41, 9, 37, 31, 12, 47, 44, 30],
+    [16, 34, 56, 51, 4, 53, 42, 41],
+    [31, 44, 47, 46, 19, 42, 44, 25],
+    [9, 48, 35, 52, 23, 31, 37, 20],
+];
+
+/// The word permutations after each round, word i is taken from word `PI[i]`.
+const PI256: [usize; 4] = [0, 3, 2, 1];
+const PI512: [usize; 8] = [2, 1, 4, 7, 6, 5, 0, 3];
+const PI1024: [usize; 16] = [0, 9, 2, 13, 6, 11, 4, 15, 10, 7, 12, 3, 14, 5, 8, 1];
+
+/// The rotations of round d are the ones at d mod 8.
+fn rotation(words: usize, d: usize, j: usize) -> u32 {
+    match words {
+        4 => R256[d % 8][j],
+        8 => R512[d % 8][j],
+        _ => R1024[d % 8][j],
+    }
+}
+
+fn permutation(words: usize) -> &'static [usize] {
+    match words {
+        4 => &PI256,
+        8 => &PI512,
+        _ => &PI1024,
+    }
+}
+
+fn rounds(words: usize) -> usize {
+    if words == 16 {
+        80
+    } else {
+        72
+    }
+}
+
+/// The key words followed by the parity word.
+pub(crate) fn extend_key(key: &[u64]) -> Vec<u64> {
+    let parity = key.iter().fold(C240, |p, k| p ^ k);
+    let mut extended = key.to_vec();
+    extended.push(parity);
+    extended
+}
+
+/// The two tweak words followed by their XOR.
+pub(crate) fn extend_tweak(tweak: [u64; 2]) -> [u64; 3] {
+    [tweak[0], tweak[1], tweak[0] ^ tweak[1]]
+}
+
+/// Word i of subkey s.
+fn subkey(key: &[u64], tweak: &[u64; 3], s: usize, i: usize) -> u64 {
+    let words = key.len() - 1;
+    let k = key[(s + i) % (words + 1)];
+    if i == words - 3 {
+        k.wrapping_add(tweak[s % 3])
+    } else if i == words - 2 {
+        k.wrapping_add(tweak[(s + 1) % 3])
+    } else if i == words - 1 {
+        k.wrapping_add(s as u64)
+    } else {
+        k
+    }
+}
+
+/// Encrypt the words with the extended key and tweak.
+pub(crate) fn encrypt(key: &[u64], tweak: &[u64; 3], block: &mut [u64]) {
+    let words = block.len();
+    let pi = permutation(words);
+    let mut tmp = vec![0u64; words];
+
+    for d in 0..rounds(words) {
+        if d % 4 == 0 {
+            for (i, b) in block.iter_mut().enumerate() {
+                *b = b.wrapping_add(subkey(key, tweak, d / 4, i));
+            }
+        }
+        for j in 0..words / 2 {
+            block[2 * j] = block[2 * j].wrapping_add(block[2 * j + 1]);
+            block[2 * j + 1] = block[2 * j + 1].rotate_left(rotation(words, d, j)) ^ block[2 * j];
+        }
+        for (t, p) in tmp.iter_mut().zip(pi.iter()) {
+            *t = block[*p];
+        }
+        block.clone_from_slice(&tmp);
+    }
+
+    let s = rounds(words) / 4;
+    for (i, b) in block.iter_mut().enumerate() {
+        *b = b.wrapping_add(subkey(key, tweak, s, i));
+    }
+}
+
+/// Every step of the encryption undone, in reverse order.
+fn decrypt(key: &[u64], tweak: &[u64; 3], block: &mut [u64]) {
+    let words = block.len();
+    let pi = permutation(words);
+    let mut tmp = vec![0u64; words];
+
+    let s = rounds(words) / 4;
+    for (i, b) in block.iter_mut().enumerate() {
+        *b = b.wrapping_sub(subkey(key, tweak, s, i));
+    }
+
+    for d in (0..rounds(words)).rev() {
+        for (b, p) in block.iter().zip(pi.iter()) {
+            tmp[*p] = *b;
+        }
+        block.clone_from_slice(&tmp);
+        for j in 0..words / 2 {
+            block[2 * j + 1] =
+                (block[2 * j + 1] ^ block[2 * j]).rotate_right(rotation(words, d, j));
+            block[2 * j] = block[2 * j].wrapping_sub(block[2 * j + 1]);
+        }
+        if d % 4 == 0 {
+            for (i, b) in block.iter_mut().enumerate() {
+                *b = b.wrapping_sub(subkey(key, tweak, d / 4, i));
+            }
+        }
+    }
+}
+
+/// Read little endian words.
+pub(crate) fn words(bytes: &[u8]) -> Vec<u64> {
+    bytes
+        .chunks(8)
+        .map(|c| {
+            let mut w = [0u8; 8];
+            w.clone_from_slice(c);
+            u64::from_le_bytes(w)
+        })
+        .collect()
+}
+
+fn store(words: &[u64], bytes: &mut [u8]) {
+    for (c, w) in bytes.chunks_mut(8).zip(words.iter()) {
+        c.clone_from_slice(&w.to_le_bytes());
+    }
+}
+
+pub enum ThreefishKey {
+    TF256([u8; 32]),
+    TF512([u8; 64]),
+    TF1024([u8; 128]),
+}
+
+impl ThreefishKey {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            ThreefishKey::TF256(key) => &key[..],
+            ThreefishKey::TF512(key) => &key[..],
+            ThreefishKey::TF1024(key) => &key[..],
+        }
+    }
+}
+
+impl<'a> std::convert::TryFrom<super::CipherKey<'a>> for ThreefishKey {
+    type Error = super::CryptoError;
+
+    fn try_from(key: super::CipherKey) -> Result<ThreefishKey, Self::Error> {
+        match key.key.len() {
+            32 => {
+                let mut key_arr = [0u8; 32];
+                key_arr[..].clone_from_slice(key.key);
+                Ok(ThreefishKey::TF256(key_arr))
+            }
+            64 => {
+                let mut key_arr = [0u8; 64];
+                key_arr[..].clone_from_slice(key.key);
+                Ok(ThreefishKey::TF512(key_arr))
+            }
+            128 => {
+                let mut key_arr = [0u8; 128];
+                key_arr[..].clone_from_slice(key.key);
+                Ok(ThreefishKey::TF1024(key_arr))
+            }
+            _ => Err(super::CryptoError::InvalidKeyLength(key.key.len())),
+        }
+    }
+}
+
+pub struct Threefish {
+    extended_key: Vec<u64>,
+    tweak: [u64; 3],
+    padding: super::Padding,
+    key: ThreefishKey,
+}
+
+impl Threefish {
+    /// Threefish with a zero tweak.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(key: ThreefishKey) -> Box<dyn BlockCipher> {
+        Self::with_tweak(key, [0; 16])
+    }
+
+    /// Every block is encrypted with the same tweak,
+    /// use a new cipher for every tweak, like the sector number of a disk.
+    pub fn with_tweak(key: ThreefishKey, tweak: [u8; 16]) -> Box<dyn BlockCipher> {
+        let t = words(&tweak);
+        Box::new(Threefish {
+            extended_key: extend_key(&words(key.bytes())),
+            tweak: extend_tweak([t[0], t[1]]),
+            padding: super::Padding::default(),
+            key,
+        })
+    }
+}
+
+impl BlockCipher for Threefish {
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let mut w = words(block);
+        encrypt(&self.extended_key, &self.tweak, &mut w);
+        store(&w, block);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let mut w = words(block);
+        decrypt(&self.extended_key, &self.tweak, &mut w);
+        store(&w, block);
+    }
+
+    fn block_size(&self) -> usize {
+        self.key.bytes().len()
+    }
+
+    fn change_padding(&mut self, padding: super::Padding) {
+        self.padding = padding;
+    }
+
+    fn padding(&self) -> super::Padding {
+        self.padding
+    }
+
+    fn get_key(&self) -> &[u8] {
+        self.key.bytes()
+    }
+
+    /// The tweak is kept.
+    fn set_key(&mut self, key: &[u8]) -> Result<(), super::CryptoError> {
+        use std::convert::TryFrom;
+        self.key = ThreefishKey::try_from(super::CipherKey { key })?;
+        self.extended_key = extend_key(&words(key));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::tests::hex;
+    use std::convert::TryFrom;
+
+    fn threefish(key: &[u8], tweak: &[u8]) -> Box<dyn BlockCipher> {
+        let mut t = [0u8; 16];
+        t.clone_from_slice(tweak);
+        Threefish::with_tweak(
+            ThreefishKey::try_from(crate::crypt::CipherKey { key }).unwrap(),
+            t,
+        )
+    }
+
+    #[test]
+    fn zero() {
+        // the known answers of the Skein 1.3 submission
+        let vectors = [
+            "84da2a1f8beaee947066ae3e3103f1ad536db1f4a1192495116b9f3ce6133fd8",
+            "b1a2bbc6ef6025bc40eb3822161f36e375d1bb0aee3186fbd19e47c5d479947b
+             7bc2f8586e35f0cff7e7f03084b0b7b1f1ab3961a580a3e97eb41ea14a6d7bbe",
+        ];
+        for ciphertext in vectors.iter() {
+            let ciphertext = hex(ciphertext);
+            let cipher = threefish(&vec![0; ciphertext.len()], &[0; 16]);
+            let mut block = vec![0; ciphertext.len()];
+            cipher.encrypt_block(&mut block);
+            assert_eq!(block, ciphertext);
+            cipher.decrypt_block(&mut block);
+            assert_eq!(block, vec![0; ciphertext.len()]);
+        }
+    }
+
+    #[test]
+    fn tweaked() {
+        // the second known answer of Threefish-256, with counting key, tweak and plaintext
+        let key: Vec<u8> = (0x10..0x30).collect();
+        let tweak: Vec<u8> = (0..16).collect();
+        let plaintext: Vec<u8> = (0..32).map(|i| 0xff - i).collect();
+        let cipher = threefish(&key, &tweak);
+        let mut block = plaintext.clone();
+        cipher.encrypt_block(&mut block);
+        assert_eq!(
+            block,
+            hex("e0d091ff0eea8fdfc98192e62ed80ad59d865d08588df476657056b5955e97df")
+        );
+        cipher.decrypt_block(&mut block);
+        assert_eq!(block, plaintext);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let key: Vec<u8> = (0..128).collect();
+        for size in &[32, 64, 128] {
+            let cipher = threefish(&key[..*size], &[7; 16]);
+            assert_eq!(cipher.block_size(), *size);
+            let plaintext: Vec<u8> = (0..*size as u8).rev().collect();
+            let mut block = plaintext.clone();
+            cipher.encrypt_block(&mut block);
+            assert_ne!(block, plaintext);
+            cipher.decrypt_block(&mut block);
+            assert_eq!(block, plaintext);
+        }
+    }
+}
@@ -30,7 +30,7 @@ pub enum TwofishKey {
 }
 
 impl<'a> std::convert::TryFrom<super::CipherKey<'a>> for TwofishKey {
-    type Error=String;
+    type Error = super::CryptoError;
 
     fn try_from(key: super::CipherKey) -> Result<TwofishKey, Self::Error> {
         match key.key.len() {
@@ -49,7 +49,7 @@ impl<'a> std::convert::TryFrom<super::CipherKey<'a>> for TwofishKey {
                 key_arr[..].clone_from_slice(key.key);
                 Ok(TwofishKey::TK256(key_arr))
             },
-            _ => Err("Not implemented yet".to_string()),
+            _ => Err(super::CryptoError::InvalidKeyLength(key.key.len())),
         }
     }
 }
@@ -57,6 +57,7 @@ impl<'a> std::convert::TryFrom<super::CipherKey<'a>> for TwofishKey {
 pub struct Twofish {
     sub_box: Vec<u32>,
     exp_key: Box<[u32; 40]>,
+    padding: super::Padding,
     key: TwofishKey,
 }
 
@@ -67,6 +68,7 @@ impl Twofish {
         Box::new(Twofish {
             sub_box,
             exp_key,
+            padding: super::Padding::default(),
             key,
         })
     }
@@ -161,6 +163,14 @@ impl BlockCipher for Twofish {
         16
     }
 
+    fn change_padding(&mut self, padding: super::Padding) {
+        self.padding = padding;
+    }
+
+    fn padding(&self) -> super::Padding {
+        self.padding
+    }
+
     fn get_key(&self) -> &[u8] {
         use TwofishKey::*;
         match &self.key {
@@ -170,7 +180,7 @@ impl BlockCipher for Twofish {
         }
     }
 
-    fn set_key(&mut self, key: &[u8]) -> Result<(), String> {
+    fn set_key(&mut self, key: &[u8]) -> Result<(), super::CryptoError> {
         use TwofishKey::*;
         let key_sized;
         match key.len() {
@@ -189,7 +199,7 @@ impl BlockCipher for Twofish {
                 k[..].clone_from_slice(key);
                 key_sized = TK128(k);
             }
-            _ => return Err(format!("{} is not a key length for AES", key.len())),
+            _ => return Err(super::CryptoError::InvalidKeyLength(key.len())),
         }
 
         let (exp_key, sub_box) = expand_key(&key_sized);
@@ -0,0 +1,387 @@
+//! The XEX-based tweaked-codebook mode with ciphertext stealing.
+//! The data is split into data units (e.g. disk sectors), and every data unit is encrypted with
+//! a tweak derived from its number. The ciphertext is as long as the plaintext,
+//! so it is suited for disk encryption, but there is no authentication.
+//!
+//!     # use codes::crypt::{xts::XTS, CipherTypes};
+//!     // two AES-128 keys, one for the data and one for the tweak
+//!     let key: Vec<u8> = (0..32).collect();
+//!     let xts = XTS::new(CipherTypes::AES, &key, 512).unwrap();
+//!
+//!     let disk: Vec<u8> = (0..2000).map(|i| i as u8).collect();
+//!     let mut image = disk.clone();
+//!
+//!     // the first data unit is sector 7
+//!     xts.encrypt_sectors(7, &mut image).unwrap();
+//!     assert_ne!(disk, image);
+//!
+//!     // a single sector can be decrypted by itself
+//!     let mut sector = image[512..1024].to_vec();
+//!     xts.decrypt_sector(8, &mut sector).unwrap();
+//!     assert_eq!(disk[512..1024], sector[..]);
+
+use rayon::prelude::*;
+
+use super::{BlockCipher, Cipher, CipherKey, CipherTypes, CryptoError};
+
+/// Multiply the tweak by the primitive element of GF(2^128).
+/// The bytes are little endian.
+fn mul_alpha(tweak: &mut [u8; 16]) {
+    let carry = tweak[15] >> 7;
+    for i in (1..16).rev() {
+        tweak[i] = (tweak[i] << 1) | (tweak[i - 1] >> 7);
+    }
+    tweak[0] = (tweak[0] << 1) ^ (0x87 * carry);
+}
+
+pub struct XTS {
+    cipher: Box<dyn BlockCipher>,
+    tweak: Box<dyn BlockCipher>,
+    data_unit: usize,
+}
+
+impl XTS {
+    /// Create an XTS instance of a 128 bit block cipher.
+    /// The key is the data key followed by the tweak key, of the same length.
+    /// The data unit is the size of a sector in bytes, at least one block and at most
+    /// 2^20 blocks.
+    pub fn new(cipher: CipherTypes, key: &[u8], data_unit: usize) -> Result<Self, CryptoError> {
+        if !key.len().is_multiple_of(2) {
+            return Err(CryptoError::InvalidKeyLength(key.len()));
+        }
+        let (data_key, tweak_key) = key.split_at(key.len() / 2);
+        let invalid = |_| CryptoError::InvalidKeyLength(key.len());
+        let data_cipher = cipher.new(CipherKey { key: data_key }).map_err(invalid)?;
+        let tweak_cipher = cipher.new(CipherKey { key: tweak_key }).map_err(invalid)?;
+
+        if data_cipher.block_size() != 16 {
+            return Err(CryptoError::InvalidKeyLength(key.len()));
+        }
+        if !(16..=16 << 20).contains(&data_unit) {
+            return Err(CryptoError::InvalidDataLength(data_unit));
+        }
+
+        Ok(XTS {
+            cipher: data_cipher,
+            tweak: tweak_cipher,
+            data_unit,
+        })
+    }
+
+    /// The size of a data unit in bytes.
+    pub fn data_unit(&self) -> usize {
+        self.data_unit
+    }
+
+    /// Encrypt or decrypt a single block with the tweak.
+    fn xex(&self, block: &mut [u8], tweak: &[u8; 16], encrypt: bool) {
+        for (b, t) in block.iter_mut().zip(tweak.iter()) {
+            *b ^= t;
+        }
+        if encrypt {
+            self.cipher.encrypt_block(block);
+        } else {
+            self.cipher.decrypt_block(block);
+        }
+        for (b, t) in block.iter_mut().zip(tweak.iter()) {
+            *b ^= t;
+        }
+    }
+
+    /// Encrypt or decrypt a data unit of any length of at least one block.
+    fn unit(&self, tweak: &[u8], data: &mut [u8], encrypt: bool) -> Result<(), CryptoError> {
+        if tweak.len() != 16 {
+            return Err(CryptoError::InvalidIvLength {
+                expected: 16,
+                got: tweak.len(),
+            });
+        }
+        if !(16..=16 << 20).contains(&data.len()) {
+            return Err(CryptoError::InvalidDataLength(data.len()));
+        }
+
+        let mut t = [0u8; 16];
+        t.clone_from_slice(tweak);
+        self.tweak.encrypt_block(&mut t);
+
+        let d = data.len() % 16;
+        // with a partial last block, the last whole block is involved in the stealing
+        let whole = if d == 0 {
+            data.len()
+        } else {
+            data.len() - d - 16
+        };
+
+        for block in data[..whole].chunks_mut(16) {
+            self.xex(block, &t, encrypt);
+            mul_alpha(&mut t);
+        }
+
+        if d != 0 {
+            let mut next = t;
+            mul_alpha(&mut next);
+            // the decryption uses the tweaks in the opposite order
+            let (first, second) = if encrypt { (t, next) } else { (next, t) };
+
+            let (block, partial) = data[whole..].split_at_mut(16);
+            self.xex(block, &first, encrypt);
+            // the partial block is padded with the end of the processed block,
+            // and the start of the processed block becomes the partial block.
+            block[..d].swap_with_slice(partial);
+            self.xex(block, &second, encrypt);
+        }
+
+        Ok(())
+    }
+
+    /// Encrypt one data unit with the tweak of the sector number.
+    /// The data does not have to be the size of a data unit.
+    pub fn encrypt_sector(&self, sector: u128, data: &mut [u8]) -> Result<(), CryptoError> {
+        self.unit(&sector.to_le_bytes(), data, true)
+    }
+
+    /// Decrypt one data unit encrypted with `encrypt_sector`.
+    pub fn decrypt_sector(&self, sector: u128, data: &mut [u8]) -> Result<(), CryptoError> {
+        self.unit(&sector.to_le_bytes(), data, false)
+    }
+
+    /// Check the sectors before any of them is changed: the last sector may not be shorter
+    /// than a block, and the number of the last sector has to fit.
+    fn check_sectors(&self, first: u128, data: &[u8]) -> Result<(), CryptoError> {
+        let partial = data.len() % self.data_unit;
+        if partial != 0 && partial < 16 {
+            return Err(CryptoError::InvalidDataLength(partial));
+        }
+        let sectors = data.len().div_ceil(self.data_unit);
+        if sectors > 0 && first.checked_add(sectors as u128 - 1).is_none() {
+            return Err(CryptoError::InvalidParameter(format!(
+                "{} sectors after sector {} overflow",
+                sectors, first
+            )));
+        }
+        Ok(())
+    }
+
+    /// Encrypt consecutive sectors in parallel, starting with sector number `first`.
+    /// The last sector may be shorter than a data unit, but not shorter than a block.
+    /// On an error the data is left as it is.
+    pub fn encrypt_sectors(&self, first: u128, data: &mut [u8]) -> Result<(), CryptoError> {
+        self.check_sectors(first, data)?;
+        data.par_chunks_mut(self.data_unit)
+            .enumerate()
+            .try_for_each(|(i, sector)| self.encrypt_sector(first + i as u128, sector))
+    }
+
+    /// Decrypt consecutive sectors encrypted with `encrypt_sectors`.
+    pub fn decrypt_sectors(&self, first: u128, data: &mut [u8]) -> Result<(), CryptoError> {
+        self.check_sectors(first, data)?;
+        data.par_chunks_mut(self.data_unit)
+            .enumerate()
+            .try_for_each(|(i, sector)| self.decrypt_sector(first + i as u128, sector))
+    }
+}
+
+impl Cipher<&mut [u8]> for XTS {
+    /// Encrypt a single data unit, the iv is the 16 byte tweak.
+    fn encrypt(&self, tweak: &[u8], plaintext: &mut [u8]) -> Result<(), CryptoError> {
+        self.unit(tweak, plaintext, true)
+    }
+
+    /// Decrypt a single data unit, the iv is the 16 byte tweak.
+    fn decrypt(&self, tweak: &[u8], ciphertext: &mut [u8]) -> Result<(), CryptoError> {
+        self.unit(tweak, ciphertext, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::tests::hex;
+
+    /// Check an IEEE 1619 test vector, both ways.
+    fn check(key: &str, sector: u128, plaintext: &[u8], ciphertext: &str) {
+        let xts = XTS::new(CipherTypes::AES, &hex(key), 512).unwrap();
+        let mut data = plaintext.to_vec();
+        xts.encrypt_sector(sector, &mut data).unwrap();
+        assert_eq!(data, hex(ciphertext));
+        xts.decrypt_sector(sector, &mut data).unwrap();
+        assert_eq!(data, plaintext);
+    }
+
+    #[test]
+    fn ieee1619_vectors_1_to_3() {
+        check(
+            &"00".repeat(32),
+            0,
+            &[0; 32],
+            "917cf69ebd68b2ec9b9fe9a3eadda692cd43d2f59598ed858c02c2652fbf922e",
+        );
+        check(
+            &("11".repeat(16) + &"22".repeat(16)),
+            0x3333333333,
+            &[0x44; 32],
+            "c454185e6a16936e39334038acef838bfb186fff7480adc4289382ecd6d394f0",
+        );
+        check(
+            &("fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0".to_string() + &"22".repeat(16)),
+            0x3333333333,
+            &[0x44; 32],
+            "af85336b597afc1a900b2eb21ec949d292df4c047e0b21532186a5971a227a89",
+        );
+    }
+
+    #[test]
+    fn ieee1619_vector_4() {
+        let plaintext: Vec<u8> = (0..512).map(|i| i as u8).collect();
+        check(
+            "27182818284590452353602874713526 31415926535897932384626433832795",
+            0,
+            &plaintext,
+            "27a7479befa1d476489f308cd4cfa6e2a96e4bbe3208ff25287dd3819616e89c
+             c78cf7f5e543445f8333d8fa7f56000005279fa5d8b5e4ad40e736ddb4d35412
+             328063fd2aab53e5ea1e0a9f332500a5df9487d07a5c92cc512c8866c7e860ce
+             93fdf166a24912b422976146ae20ce846bb7dc9ba94a767aaef20c0d61ad0265
+             5ea92dc4c4e41a8952c651d33174be51a10c421110e6d81588ede82103a252d8
+             a750e8768defffed9122810aaeb99f9172af82b604dc4b8e51bcb08235a6f434
+             1332e4ca60482a4ba1a03b3e65008fc5da76b70bf1690db4eae29c5f1badd03c
+             5ccf2a55d705ddcd86d449511ceb7ec30bf12b1fa35b913f9f747a8afd1b130e
+             94bff94effd01a91735ca1726acd0b197c4e5b03393697e126826fb6bbde8ecc
+             1e08298516e2c9ed03ff3c1b7860f6de76d4cecd94c8119855ef5297ca67e9f3
+             e7ff72b1e99785ca0a7e7720c5b36dc6d72cac9574c8cbbc2f801e23e56fd344
+             b07f22154beba0f08ce8891e643ed995c94d9a69c9f1b5f499027a78572aeebd
+             74d20cc39881c213ee770b1010e4bea718846977ae119f7a023ab58cca0ad752
+             afe656bb3c17256a9f6e9bf19fdd5a38fc82bbe872c5539edb609ef4f79c203e
+             bb140f2e583cb2ad15b4aa5b655016a8449277dbd477ef2c8d6c017db738b18d
+             eb4a427d1923ce3ff262735779a418f20a282df920147beabe421ee5319d0568",
+        );
+    }
+
+    #[test]
+    fn ieee1619_vector_10() {
+        // XTS-AES-256
+        let plaintext: Vec<u8> = (0..512).map(|i| i as u8).collect();
+        check(
+            "27182818284590452353602874713526 62497757247093699959574966967627
+             31415926535897932384626433832795 02884197169399375105820974944592",
+            0xff,
+            &plaintext,
+            "1c3b3a102f770386e4836c99e370cf9bea00803f5e482357a4ae12d414a3e63b
+             5d31e276f8fe4a8d66b317f9ac683f44680a86ac35adfc3345befecb4bb188fd
+             5776926c49a3095eb108fd1098baec70aaa66999a72a82f27d848b21d4a741b0
+             c5cd4d5fff9dac89aeba122961d03a757123e9870f8acf1000020887891429ca
+             2a3e7a7d7df7b10355165c8b9a6d0a7de8b062c4500dc4cd120c0f7418dae3d0
+             b5781c34803fa75421c790dfe1de1834f280d7667b327f6c8cd7557e12ac3a0f
+             93ec05c52e0493ef31a12d3d9260f79a289d6a379bc70c50841473d1a8cc81ec
+             583e9645e07b8d9670655ba5bbcfecc6dc3966380ad8fecb17b6ba02469a020a
+             84e18e8f84252070c13e9f1f289be54fbc481457778f616015e1327a02b140f1
+             505eb309326d68378f8374595c849d84f4c333ec4423885143cb47bd71c5edae
+             9be69a2ffeceb1bec9de244fbe15992b11b77c040f12bd8f6a975a44a0f90c29
+             a9abc3d4d893927284c58754cce294529f8614dcd2aba991925fedc4ae74ffac
+             6e333b93eb4aff0479da9a410e4450e0dd7ae4c6e2910900575da401fc07059f
+             645e8b7e9bfdef33943054ff84011493c27b3429eaedb4ed5376441a77ed4385
+             1ad77f16f541dfd269d50d6a5f14fb0aab1cbb4c1550be97f7ab4066193c4caa
+             773dad38014bd2092fa755c824bb5e54c4f36ffda9fcea70b9c6e693e148c151",
+        );
+    }
+
+    #[test]
+    fn ieee1619_vectors_15_to_18() {
+        // partial blocks, with ciphertext stealing
+        let key = "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0 bfbebdbcbbbab9b8b7b6b5b4b3b2b1b0";
+        let plaintext: Vec<u8> = (0..20).collect();
+        for (len, ciphertext) in &[
+            (17, "6c1625db4671522d3d7599601de7ca09ed"),
+            (18, "d069444b7a7e0cab09e24447d24deb1fedbf"),
+            (19, "e5df1351c0544ba1350b3363cd8ef4beedbf9d"),
+            (20, "9d84c813f719aa2c7be3f66171c7c5c2edbf9dac"),
+        ] {
+            check(key, 0x123456789a, &plaintext[..*len], ciphertext);
+        }
+    }
+
+    #[test]
+    fn sectors() {
+        // the sectors are the same as encrypting the data units by themselves
+        let key: Vec<u8> = (100..164).collect();
+        let plaintext: Vec<u8> = (0..5000).map(|i| (i * 7) as u8).collect();
+        let xts = XTS::new(CipherTypes::AES, &key, 512).unwrap();
+
+        let mut disk = plaintext.clone();
+        xts.encrypt_sectors(1000, &mut disk).unwrap();
+        assert_eq!(disk.len(), plaintext.len());
+
+        for (i, sector) in plaintext.chunks(512).enumerate() {
+            let mut expected = sector.to_vec();
+            let tweak = (1000 + i as u128).to_le_bytes();
+            xts.encrypt(&tweak, &mut expected).unwrap();
+            assert_eq!(disk[(i * 512)..(i * 512 + sector.len())], expected[..]);
+        }
+
+        xts.decrypt_sectors(1000, &mut disk).unwrap();
+        assert_eq!(disk, plaintext);
+    }
+
+    #[test]
+    fn twofish() {
+        let key = [0x5a; 64];
+        let xts = XTS::new(CipherTypes::Twofish, &key, 4096).unwrap();
+        let plaintext: Vec<u8> = (0..10000).map(|i| (i % 251) as u8).collect();
+        let mut disk = plaintext.clone();
+        xts.encrypt_sectors(0, &mut disk).unwrap();
+        assert_ne!(disk, plaintext);
+        xts.decrypt_sectors(0, &mut disk).unwrap();
+        assert_eq!(disk, plaintext);
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            XTS::new(CipherTypes::AES, &[0; 33], 512).err(),
+            Some(CryptoError::InvalidKeyLength(33))
+        );
+        assert_eq!(
+            XTS::new(CipherTypes::AES, &[0; 40], 512).err(),
+            Some(CryptoError::InvalidKeyLength(40))
+        );
+        assert_eq!(
+            XTS::new(CipherTypes::AES, &[0; 32], 15).err(),
+            Some(CryptoError::InvalidDataLength(15))
+        );
+
+        let xts = XTS::new(CipherTypes::AES, &[0; 32], 512).unwrap();
+        assert_eq!(
+            xts.encrypt_sector(0, &mut [0; 15]),
+            Err(CryptoError::InvalidDataLength(15))
+        );
+        // a last sector shorter than a block
+        assert_eq!(
+            xts.encrypt_sectors(0, &mut [0; 520]),
+            Err(CryptoError::InvalidDataLength(8))
+        );
+        // the sector numbers would wrap around after the last one
+        assert!(matches!(
+            xts.encrypt_sectors(u128::MAX, &mut [0; 1024]),
+            Err(CryptoError::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            xts.decrypt_sectors(u128::MAX - 1, &mut [0; 1300]),
+            Err(CryptoError::InvalidParameter(_))
+        ));
+        assert!(xts.encrypt_sectors(u128::MAX, &mut [0; 512]).is_ok());
+
+        // a failing call does not change any sector
+        let data: Vec<u8> = (0..5000).map(|i| i as u8).collect();
+        let mut disk = data.clone();
+        assert!(xts.encrypt_sectors(0, &mut disk[..4104]).is_err());
+        assert!(xts.encrypt_sectors(u128::MAX - 3, &mut disk).is_err());
+        assert!(xts.decrypt_sectors(u128::MAX - 8, &mut disk).is_err());
+        assert_eq!(disk, data);
+        assert_eq!(
+            xts.encrypt(&[0; 8], &mut [0; 16]),
+            Err(CryptoError::InvalidIvLength {
+                expected: 16,
+                got: 8
+            })
+        );
+    }
+}
@@ -0,0 +1,376 @@
+//! Binary BCH codes, correcting up to t bit errors in every codeword of n = 2^m - 1 bits.
+//!
+//! The generator polynomial is the least common multiple of the minimal polynomials of
+//! alpha, alpha^2, ..., alpha^2t. The encoding is systematic, and the decoding finds the error
+//! locator polynomial with Berlekamp-Massey and its roots with a Chien search.
+//!
+//!     # use codes::error::bch::BCH;
+//!     // BCH(15, 7) corrects two errors
+//!     let bch = BCH::new(4, 2).unwrap();
+//!     let message = b"flash memory".to_vec();
+//!
+//!     let mut encoded = bch.encode(&message);
+//!     encoded[0] ^= 0b10000010;
+//!
+//!     let decoded = bch.decode(&encoded).unwrap();
+//!     assert_eq!(decoded[..message.len()], message[..]);
+
+use super::galois::GaloisField;
+
+/// A binary BCH code, with codewords of n = 2^m - 1 bits carrying k message bits.
+pub struct BCH {
+    field: GaloisField,
+    t: usize,
+    /// The generator polynomial, generator[i] is the coefficient of x^i.
+    generator: Vec<u8>,
+}
+
+impl BCH {
+    /// Create a BCH code over GF(2^m) correcting t errors.
+    pub fn new(m: usize, t: usize) -> Result<Self, String> {
+        Self::with_field(GaloisField::new(m)?, t)
+    }
+
+    /// Create a BCH code correcting t errors over the given field.
+    pub fn with_field(field: GaloisField, t: usize) -> Result<Self, String> {
+        let n = field.order();
+        if t == 0 || 2 * t >= n {
+            return Err(format!(
+                "can not correct {} errors with codewords of {} bits",
+                t, n
+            ));
+        }
+
+        // the cyclotomic cosets of the roots alpha^1..alpha^2t,
+        // all the conjugates has to be roots of the generator.
+        let mut roots = vec![false; n];
+        for i in 1..=2 * t {
+            let mut j = i;
+            while !roots[j] {
+                roots[j] = true;
+                j = (j << 1) % n;
+            }
+        }
+
+        let generator = roots
+            .iter()
+            .enumerate()
+            .filter(|(_, root)| **root)
+            .fold(vec![1u16], |g, (i, _)| {
+                field.poly_mul(&g, &[field.alpha_pow(i), 1])
+            });
+
+        // the product of full cyclotomic cosets are binary
+        debug_assert!(generator.iter().all(|c| *c <= 1));
+
+        if generator.len() > n {
+            return Err(format!(
+                "can not correct {} errors with codewords of {} bits",
+                t, n
+            ));
+        }
+
+        Ok(BCH {
+            t,
+            generator: generator.iter().map(|c| *c as u8).collect(),
+            field,
+        })
+    }
+
+    /// The number of bits in a codeword.
+    pub fn n(&self) -> usize {
+        self.field.order()
+    }
+
+    /// The number of message bits in a codeword.
+    pub fn k(&self) -> usize {
+        self.n() + 1 - self.generator.len()
+    }
+
+    /// The number of bit errors that can be corrected in a codeword.
+    pub fn t(&self) -> usize {
+        self.t
+    }
+
+    /// The generator polynomial, the coefficients of x^0 first.
+    pub fn generator(&self) -> &[u8] {
+        &self.generator
+    }
+
+    /// Encode k bits (one bit in each byte) into a codeword of n bits.
+    ///
+    /// Bit i of the codeword is the coefficient of x^i in c(x) = x^(n-k) m(x) + r(x), where r(x)
+    /// is the remainder of x^(n-k) m(x) divided by the generator.
+    /// So the parity is in the first n - k bits, and the message in the last k bits.
+    pub fn encode_block(&self, data: &[u8]) -> Vec<u8> {
+        assert_eq!(data.len(), self.k(), "the message should be k bits");
+        let parity = self.n() - self.k();
+
+        let mut codeword = vec![0u8; parity];
+        codeword.extend_from_slice(data);
+
+        // long division, the remainder ends up in the parity bits.
+        let mut rem = codeword.clone();
+        for i in (parity..self.n()).rev() {
+            if rem[i] == 1 {
+                for (r, g) in rem[(i - parity)..=i].iter_mut().zip(self.generator.iter()) {
+                    *r ^= g;
+                }
+            }
+        }
+
+        codeword[..parity].clone_from_slice(&rem[..parity]);
+        codeword
+    }
+
+    /// The syndromes S_1, ..., S_2t of the received word.
+    fn syndromes(&self, codeword: &[u8]) -> Vec<u16> {
+        (1..=2 * self.t)
+            .map(|j| {
+                codeword
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, bit)| **bit == 1)
+                    .fold(0, |s, (i, _)| s ^ self.field.alpha_pow(i * j))
+            })
+            .collect()
+    }
+
+    /// Find the error locator polynomial from the syndromes with the Berlekamp-Massey algorithm.
+    fn berlekamp_massey(&self, syndromes: &[u16]) -> Vec<u16> {
+        let field = &self.field;
+        let mut c = vec![1u16];
+        let mut b = vec![1u16];
+        let mut l = 0;
+        let mut shift = 1;
+        let mut prev_discrepancy = 1;
+
+        for n in 0..syndromes.len() {
+            let discrepancy = (1..=l).fold(syndromes[n], |d, i| {
+                d ^ field.mul(*c.get(i).unwrap_or(&0), syndromes[n - i])
+            });
+
+            if discrepancy == 0 {
+                shift += 1;
+                continue;
+            }
+
+            let coef = field.div(discrepancy, prev_discrepancy);
+            let previous = c.clone();
+
+            if c.len() < b.len() + shift {
+                c.resize(b.len() + shift, 0);
+            }
+            for (i, x) in b.iter().enumerate() {
+                c[i + shift] ^= field.mul(coef, *x);
+            }
+
+            if 2 * l <= n {
+                l = n + 1 - l;
+                b = previous;
+                prev_discrepancy = discrepancy;
+                shift = 1;
+            } else {
+                shift += 1;
+            }
+        }
+
+        c.truncate(l + 1);
+        c
+    }
+
+    /// Correct the errors in a codeword of n bits in place.
+    /// Returns the number of corrected bits, or an error if there is more errors than the code
+    /// is able to correct.
+    pub fn decode_block(&self, codeword: &mut [u8]) -> Result<usize, String> {
+        assert_eq!(codeword.len(), self.n(), "the codeword should be n bits");
+        let syndromes = self.syndromes(codeword);
+        if syndromes.iter().all(|s| *s == 0) {
+            return Ok(0);
+        }
+
+        let locator = self.berlekamp_massey(&syndromes);
+        let errors = locator.len() - 1;
+        if errors > self.t {
+            return Err(format!("more than {} errors in the codeword", self.t));
+        }
+
+        // Chien search, the error positions i are the inverse roots: locator(alpha^-i) = 0
+        let n = self.n();
+        let positions: Vec<usize> = (0..n)
+            .filter(|i| self.field.eval(&locator, self.field.alpha_pow(n - i)) == 0)
+            .collect();
+
+        if positions.len() != errors {
+            return Err(format!("more than {} errors in the codeword", self.t));
+        }
+
+        for i in positions {
+            codeword[i] ^= 1;
+        }
+
+        Ok(errors)
+    }
+
+    /// Encode a byte stream.
+    /// The bits are split into blocks of k bits (the last one padded with zeros),
+    /// and the codewords of n bits are packed together.
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        let bits = to_bits(data);
+        let codewords: Vec<u8> = bits
+            .chunks(self.k())
+            .flat_map(|chunk| {
+                let mut block = chunk.to_vec();
+                block.resize(self.k(), 0);
+                self.encode_block(&block)
+            })
+            .collect();
+
+        from_bits(&codewords)
+    }
+
+    /// Decode a byte stream created by `encode`.
+    /// Might return trailing zeros.
+    pub fn decode(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let bits = to_bits(data);
+        let mut message = Vec::new();
+
+        // the zeros padding the last byte is less than a block, and is dropped by chunks_exact
+        for chunk in bits.chunks_exact(self.n()) {
+            let mut block = chunk.to_vec();
+            self.decode_block(&mut block)?;
+            message.extend_from_slice(&block[(self.n() - self.k())..]);
+        }
+
+        message.truncate(message.len() & !7);
+        Ok(from_bits(&message))
+    }
+}
+
+/// Split bytes into bits, most significant bit first.
+fn to_bits(data: &[u8]) -> Vec<u8> {
+    data.iter()
+        .flat_map(|byte| (0..8).map(move |i| (byte >> (7 - i)) & 1))
+        .collect()
+}
+
+/// Pack bits into bytes, the last byte padded with zeros.
+fn from_bits(bits: &[u8]) -> Vec<u8> {
+    bits.chunks(8)
+        .map(|byte| byte.iter().fold(0, |b, bit| (b << 1) | bit) << (8 - byte.len()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poly(bits: u32, len: usize) -> Vec<u8> {
+        (0..len).map(|i| ((bits >> i) & 1) as u8).collect()
+    }
+
+    #[test]
+    fn generator_polynomials() {
+        // tabulated in Lin & Costello
+        let bch = BCH::new(4, 1).unwrap();
+        assert_eq!(bch.generator(), &poly(0x13, 5)[..]);
+        assert_eq!(bch.k(), 11);
+
+        let bch = BCH::new(4, 2).unwrap();
+        assert_eq!(bch.generator(), &poly(0x1d1, 9)[..]);
+        assert_eq!(bch.k(), 7);
+
+        let bch = BCH::new(4, 3).unwrap();
+        assert_eq!(bch.generator(), &poly(0x537, 11)[..]);
+        assert_eq!(bch.k(), 5);
+
+        let bch = BCH::new(5, 2).unwrap();
+        assert_eq!(bch.generator(), &poly(0x769, 11)[..]);
+        assert_eq!(bch.k(), 21);
+
+        assert_eq!(BCH::new(8, 2).unwrap().k(), 239);
+        assert_eq!(BCH::new(8, 8).unwrap().k(), 191);
+        assert_eq!(BCH::new(10, 5).unwrap().k(), 973);
+    }
+
+    #[test]
+    fn invalid_parameters() {
+        assert!(BCH::new(4, 0).is_err());
+        assert!(BCH::new(4, 8).is_err());
+        assert!(BCH::new(1, 1).is_err());
+    }
+
+    #[test]
+    fn systematic() {
+        let bch = BCH::new(5, 3).unwrap();
+        let message: Vec<u8> = (0..bch.k()).map(|i| ((i * 7) % 3 == 0) as u8).collect();
+        let codeword = bch.encode_block(&message);
+        assert_eq!(codeword[(bch.n() - bch.k())..], message[..]);
+        assert!(bch.syndromes(&codeword).iter().all(|s| *s == 0));
+    }
+
+    #[test]
+    fn correct_t_errors() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+
+        for &(m, t) in &[(4, 1), (4, 2), (4, 3), (5, 5), (6, 4), (8, 8), (10, 12)] {
+            let bch = BCH::new(m, t).unwrap();
+            for _ in 0..20 {
+                let message: Vec<u8> = (0..bch.k()).map(|_| rng.gen_range(0, 2)).collect();
+                let codeword = bch.encode_block(&message);
+
+                for errors in 0..=t {
+                    let mut received = codeword.clone();
+                    let mut flipped = Vec::new();
+                    while flipped.len() < errors {
+                        let i = rng.gen_range(0, bch.n());
+                        if !flipped.contains(&i) {
+                            flipped.push(i);
+                            received[i] ^= 1;
+                        }
+                    }
+
+                    assert_eq!(bch.decode_block(&mut received), Ok(errors));
+                    assert_eq!(received, codeword);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn detect_too_many_errors() {
+        // BCH(15, 7) with 3 errors is either detected, or miscorrected into another codeword.
+        let bch = BCH::new(4, 2).unwrap();
+        let codeword = bch.encode_block(&[1, 0, 1, 1, 0, 0, 1]);
+        let mut received = codeword.clone();
+        received[0] ^= 1;
+        received[5] ^= 1;
+        received[10] ^= 1;
+
+        if bch.decode_block(&mut received).is_ok() {
+            assert_ne!(received, codeword);
+            assert!(bch.syndromes(&received).iter().all(|s| *s == 0));
+        }
+    }
+
+    #[test]
+    fn byte_stream() {
+        let bch = BCH::new(6, 3).unwrap();
+        let message = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit.".to_vec();
+        let mut encoded = bch.encode(&message);
+
+        // three errors in every codeword
+        let n = bch.n();
+        for block in 0..((encoded.len() << 3) / n) {
+            for i in &[3, 17, 40] {
+                let bit = block * n + i;
+                encoded[bit >> 3] ^= 1 << (7 - (bit & 7));
+            }
+        }
+
+        let decoded = bch.decode(&encoded).unwrap();
+        assert_eq!(decoded[..message.len()], message[..]);
+        assert!(decoded[message.len()..].iter().all(|b| *b == 0));
+    }
+}
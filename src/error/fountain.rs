@@ -0,0 +1,421 @@
+//! LT codes, a rateless erasure code (a fountain code).
+//!
+//! The data is split into k source symbols, and the encoder produces an unbounded stream of
+//! encoded symbols, each the xor of a random set of source symbols. The receiver can reconstruct
+//! the data from any set of slightly more than k received symbols, no matter which ones are lost.
+//!
+//! The number of source symbols in an encoded symbol (the degree) is drawn from the robust
+//! soliton distribution, and the source symbols are picked by a pseudorandom generator seeded
+//! with the seed and the symbol id. So the sender and receiver only has to agree on the data
+//! length, the symbol size and the seed, and the id is sent with every symbol.
+//!
+//!     # use codes::error::fountain::LT;
+//!     let data: Vec<u8> = (0..1000).map(|i| (i * 7) as u8).collect();
+//!     let lt = LT::new(data.len(), 16, 1234);
+//!
+//!     let mut decoder = lt.decoder();
+//!     // every other symbol is lost
+//!     for symbol in lt.encoder(&data).step_by(2) {
+//!         if decoder.push(symbol) {
+//!             break;
+//!         }
+//!     }
+//!
+//!     assert_eq!(decoder.data().unwrap(), data);
+
+/// The splitmix64 generator, used since the sender and receiver has to draw the exact same
+/// numbers.
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// A uniform number in [0, 1)
+    fn uniform(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A number in 0..n
+    fn below(&mut self, n: usize) -> usize {
+        (self.uniform() * n as f64) as usize
+    }
+}
+
+/// A received (or sent) encoded symbol.
+#[derive(Clone)]
+pub struct Symbol {
+    pub id: u32,
+    pub data: Vec<u8>,
+}
+
+/// The parameters shared between the encoder and decoder.
+pub struct LT {
+    len: usize,
+    symbol_size: usize,
+    k: usize,
+    seed: u64,
+    /// the cumulative robust soliton distribution, cdf[d - 1] = P(degree <= d)
+    cdf: Vec<f64>,
+}
+
+impl LT {
+    /// Create an LT code for data of `len` bytes split in symbols of `symbol_size` bytes.
+    /// Uses the robust soliton distribution with c = 0.1 and delta = 0.5.
+    pub fn new(len: usize, symbol_size: usize, seed: u64) -> Self {
+        Self::with_distribution(len, symbol_size, seed, 0.1, 0.5)
+    }
+
+    /// Create an LT code with the given parameters c and delta for the robust soliton
+    /// distribution. Delta is a bound on the probability of failing to decode after receiving
+    /// k + O(sqrt(k) ln^2(k / delta)) symbols.
+    pub fn with_distribution(
+        len: usize,
+        symbol_size: usize,
+        seed: u64,
+        c: f64,
+        delta: f64,
+    ) -> Self {
+        assert!(symbol_size > 0, "the symbol size can not be zero");
+        let k = std::cmp::max(1, len.div_ceil(symbol_size));
+        let kf = k as f64;
+
+        // ideal soliton
+        let rho = |d: usize| {
+            if d == 1 {
+                1.0 / kf
+            } else {
+                1.0 / (d as f64 * (d - 1) as f64)
+            }
+        };
+
+        // the extra probability mass of the robust soliton
+        let r = c * (kf / delta).ln() * kf.sqrt();
+        let spike = std::cmp::min(k, std::cmp::max(1, (kf / r).floor() as usize));
+        let tau = |d: usize| {
+            if d < spike {
+                r / (d as f64 * kf)
+            } else if d == spike {
+                r * (r / delta).ln() / kf
+            } else {
+                0.0
+            }
+        };
+
+        let weights: Vec<f64> = (1..=k).map(|d| rho(d) + tau(d).max(0.0)).collect();
+        let beta: f64 = weights.iter().sum();
+        let mut sum = 0.0;
+        let cdf = weights
+            .iter()
+            .map(|w| {
+                sum += w / beta;
+                sum
+            })
+            .collect();
+
+        LT {
+            len,
+            symbol_size,
+            k,
+            seed,
+            cdf,
+        }
+    }
+
+    /// The number of source symbols.
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// The source symbols the encoded symbol with the given id is the xor of.
+    fn neighbours(&self, id: u32) -> Vec<usize> {
+        let mut rng = SplitMix64 {
+            state: self.seed ^ (id as u64).wrapping_mul(0xd1342543de82ef95),
+        };
+
+        let u = rng.uniform();
+        let degree = self.cdf.iter().position(|p| u < *p).unwrap_or(self.k - 1) + 1;
+
+        let mut neighbours = Vec::with_capacity(degree);
+        while neighbours.len() < degree {
+            let i = rng.below(self.k);
+            if !neighbours.contains(&i) {
+                neighbours.push(i);
+            }
+        }
+        neighbours
+    }
+
+    /// Create the encoder for the data, which has to be the length given to `new`.
+    pub fn encoder<'a>(&'a self, data: &[u8]) -> LTEncoder<'a> {
+        assert_eq!(data.len(), self.len, "the data has the wrong length");
+        let mut source = data.to_vec();
+        source.resize(self.k * self.symbol_size, 0);
+
+        LTEncoder {
+            lt: self,
+            source,
+            id: 0,
+        }
+    }
+
+    pub fn decoder(&self) -> LTDecoder<'_> {
+        LTDecoder {
+            lt: self,
+            source: vec![None; self.k],
+            recovered: 0,
+            pending: Vec::new(),
+            references: vec![Vec::new(); self.k],
+        }
+    }
+}
+
+/// An endless stream of encoded symbols.
+pub struct LTEncoder<'a> {
+    lt: &'a LT,
+    source: Vec<u8>,
+    id: u32,
+}
+
+impl<'a> LTEncoder<'a> {
+    /// Encode the symbol with the given id.
+    pub fn symbol(&self, id: u32) -> Symbol {
+        let size = self.lt.symbol_size;
+        let mut data = vec![0u8; size];
+        for i in self.lt.neighbours(id) {
+            for (d, s) in data.iter_mut().zip(self.source[(i * size)..].iter()) {
+                *d ^= s;
+            }
+        }
+
+        Symbol { id, data }
+    }
+}
+
+impl<'a> Iterator for LTEncoder<'a> {
+    type Item = Symbol;
+    fn next(&mut self) -> Option<Self::Item> {
+        let symbol = self.symbol(self.id);
+        self.id = self.id.wrapping_add(1);
+        Some(symbol)
+    }
+}
+
+/// Peeling decoder: whenever a received symbol has only a single unknown source symbol left,
+/// that source symbol is recovered, and removed from all the other received symbols.
+pub struct LTDecoder<'a> {
+    lt: &'a LT,
+    source: Vec<Option<Vec<u8>>>,
+    recovered: usize,
+    /// received symbols with more than one unknown source symbol
+    pending: Vec<(Vec<usize>, Vec<u8>)>,
+    /// the pending symbols containing each source symbol
+    references: Vec<Vec<usize>>,
+}
+
+impl<'a> LTDecoder<'a> {
+    /// Add a received symbol. Returns true when all the source symbols are recovered.
+    pub fn push(&mut self, symbol: Symbol) -> bool {
+        if self.is_complete() || symbol.data.len() != self.lt.symbol_size {
+            return self.is_complete();
+        }
+
+        let mut data = symbol.data;
+        let mut neighbours = self.lt.neighbours(symbol.id);
+
+        // remove the source symbols that is already known
+        neighbours.retain(|i| match &self.source[*i] {
+            Some(known) => {
+                for (d, s) in data.iter_mut().zip(known.iter()) {
+                    *d ^= s;
+                }
+                false
+            }
+            None => true,
+        });
+
+        match neighbours.len() {
+            0 => (),
+            1 => self.recover(neighbours[0], data),
+            _ => {
+                let index = self.pending.len();
+                for i in neighbours.iter() {
+                    self.references[*i].push(index);
+                }
+                self.pending.push((neighbours, data));
+            }
+        }
+
+        self.is_complete()
+    }
+
+    /// Add a recovered source symbol, and peel it off the pending symbols.
+    fn recover(&mut self, index: usize, data: Vec<u8>) {
+        let mut queue = vec![(index, data)];
+
+        while let Some((i, data)) = queue.pop() {
+            if self.source[i].is_some() {
+                continue;
+            }
+
+            for p in std::mem::take(&mut self.references[i]) {
+                let (neighbours, pending) = &mut self.pending[p];
+                if neighbours.is_empty() {
+                    continue;
+                }
+                for (d, s) in pending.iter_mut().zip(data.iter()) {
+                    *d ^= s;
+                }
+                neighbours.retain(|n| *n != i);
+
+                if neighbours.len() == 1 {
+                    let next = neighbours.pop().unwrap();
+                    queue.push((next, std::mem::take(pending)));
+                }
+            }
+
+            self.source[i] = Some(data);
+            self.recovered += 1;
+        }
+    }
+
+    /// The number of recovered source symbols.
+    pub fn recovered(&self) -> usize {
+        self.recovered
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.recovered == self.lt.k
+    }
+
+    /// The decoded data, if all the source symbols are recovered.
+    pub fn data(&self) -> Option<Vec<u8>> {
+        if !self.is_complete() {
+            return None;
+        }
+        let mut data: Vec<u8> = self
+            .source
+            .iter()
+            .flat_map(|s| s.as_ref().unwrap().iter().copied())
+            .collect();
+        data.truncate(self.lt.len);
+        Some(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 31 + i / 7) as u8).collect()
+    }
+
+    /// The number of symbols needed before the data is decoded,
+    /// when the symbols are lost with the given probability.
+    fn symbols_needed(len: usize, size: usize, seed: u64, loss: f64) -> usize {
+        let data = data(len);
+        let lt = LT::new(len, size, seed);
+        let mut decoder = lt.decoder();
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let mut received = 0;
+        for symbol in lt.encoder(&data).filter(|_| rng.gen::<f64>() >= loss) {
+            received += 1;
+            if decoder.push(symbol) {
+                break;
+            }
+            assert!(received < 10 * lt.k(), "did not converge");
+        }
+        assert_eq!(decoder.data().unwrap(), data);
+        received
+    }
+
+    #[test]
+    fn distribution() {
+        let lt = LT::new(10000, 10, 0);
+        assert_eq!(lt.k(), 1000);
+        assert!((lt.cdf.last().unwrap() - 1.0).abs() < 1e-9);
+        assert!(lt.cdf.windows(2).all(|w| w[0] <= w[1]));
+
+        // the average degree of the robust soliton grows like ln(k / delta)
+        let average: f64 = (0..10000)
+            .map(|id| lt.neighbours(id).len() as f64)
+            .sum::<f64>()
+            / 10000.0;
+        assert!((4.0..20.0).contains(&average), "{}", average);
+    }
+
+    #[test]
+    fn deterministic() {
+        let data = data(500);
+        let a = LT::new(500, 8, 42);
+        let b = LT::new(500, 8, 42);
+        let c = LT::new(500, 8, 43);
+
+        let sa: Vec<Vec<u8>> = a.encoder(&data).take(100).map(|s| s.data).collect();
+        let sb: Vec<Vec<u8>> = b.encoder(&data).take(100).map(|s| s.data).collect();
+        let sc: Vec<Vec<u8>> = c.encoder(&data).take(100).map(|s| s.data).collect();
+
+        assert_eq!(sa, sb);
+        assert_ne!(sa, sc);
+        assert_eq!(a.encoder(&data).symbol(77).data, sa[77]);
+    }
+
+    #[test]
+    fn decode_without_loss() {
+        for &len in &[1, 15, 16, 17, 100, 1000] {
+            symbols_needed(len, 16, len as u64, 0.0);
+        }
+    }
+
+    #[test]
+    fn decode_with_loss() {
+        let mut total = 0;
+        let runs = 20;
+        for seed in 0..runs {
+            let needed = symbols_needed(32000, 32, seed, 0.3);
+            total += needed;
+        }
+        // on average only a bit more than k = 1000 symbols is needed
+        let average = total as f64 / runs as f64;
+        assert!(average < 1300.0, "{}", average);
+    }
+
+    #[test]
+    fn any_order() {
+        let data = data(2000);
+        let lt = LT::new(data.len(), 20, 7);
+        let mut symbols: Vec<Symbol> = lt.encoder(&data).take(400).collect();
+        symbols.reverse();
+
+        let mut decoder = lt.decoder();
+        for symbol in symbols {
+            if decoder.push(symbol) {
+                break;
+            }
+        }
+        assert_eq!(decoder.data().unwrap(), data);
+    }
+
+    #[test]
+    fn incomplete() {
+        let data = data(1000);
+        let lt = LT::new(data.len(), 10, 7);
+        let mut decoder = lt.decoder();
+        for symbol in lt.encoder(&data).take(50) {
+            assert!(!decoder.push(symbol));
+        }
+        assert!(decoder.recovered() < lt.k());
+        assert_eq!(decoder.data(), None);
+    }
+}
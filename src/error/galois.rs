@@ -0,0 +1,193 @@
+//! Arithmetic over the finite field GF(2^m).
+//!
+//! The elements are represented as polynomials over GF(2) in the bits of an `u16`,
+//! and multiplication is done with log/antilog tables generated from a primitive polynomial.
+//!
+//!     # use codes::error::galois::GaloisField;
+//!     let field = GaloisField::new(4).unwrap();
+//!     let a = field.alpha_pow(3);
+//!     assert_eq!(field.mul(a, field.inv(a)), 1);
+
+/// Primitive polynomials for GF(2^m), indexed by m.
+/// Including the x^m term.
+const PRIMITIVE: [u32; 17] = [
+    0, 0, 0x7, 0xb, 0x13, 0x25, 0x43, 0x89, 0x11d, 0x211, 0x409, 0x805, 0x1053, 0x201b, 0x4443,
+    0x8003, 0x1100b,
+];
+
+pub struct GaloisField {
+    m: usize,
+    poly: u32,
+    exp: Vec<u16>,
+    log: Vec<u16>,
+}
+
+impl GaloisField {
+    /// Create the field GF(2^m) with a default primitive polynomial.
+    /// m has to be between 2 and 16.
+    pub fn new(m: usize) -> Result<Self, String> {
+        if !(2..PRIMITIVE.len()).contains(&m) {
+            return Err(format!("GF(2^{}) is not supported, m must be in 2..=16", m));
+        }
+        Self::with_polynomial(m, PRIMITIVE[m])
+    }
+
+    /// Create the field GF(2^m) from the given primitive polynomial (including the x^m term).
+    pub fn with_polynomial(m: usize, poly: u32) -> Result<Self, String> {
+        if !(2..PRIMITIVE.len()).contains(&m) {
+            return Err(format!("GF(2^{}) is not supported, m must be in 2..=16", m));
+        }
+        if poly >> m != 1 {
+            return Err(format!("{:#x} is not a polynomial of degree {}", poly, m));
+        }
+
+        let n = (1usize << m) - 1;
+        let mut exp = vec![0u16; n];
+        let mut log = vec![0u16; n + 1];
+
+        let mut x = 1u32;
+        for (i, e) in exp.iter_mut().enumerate() {
+            // if alpha^i = 1 for i < n, the polynomial is not primitive.
+            if i > 0 && x == 1 {
+                return Err(format!("{:#x} is not a primitive polynomial", poly));
+            }
+            *e = x as u16;
+            log[x as usize] = i as u16;
+
+            x <<= 1;
+            if x >> m == 1 {
+                x ^= poly;
+            }
+        }
+
+        Ok(GaloisField { m, poly, exp, log })
+    }
+
+    /// The degree m of the field GF(2^m).
+    pub fn degree(&self) -> usize {
+        self.m
+    }
+
+    /// The primitive polynomial the field was generated from.
+    pub fn polynomial(&self) -> u32 {
+        self.poly
+    }
+
+    /// The number of nonzero elements, 2^m - 1.
+    pub fn order(&self) -> usize {
+        self.exp.len()
+    }
+
+    /// Addition (and subtraction) is xor.
+    #[inline]
+    pub fn add(a: u16, b: u16) -> u16 {
+        a ^ b
+    }
+
+    #[inline]
+    pub fn mul(&self, a: u16, b: u16) -> u16 {
+        if a == 0 || b == 0 {
+            return 0;
+        }
+        let n = self.order();
+        self.exp[(self.log[a as usize] as usize + self.log[b as usize] as usize) % n]
+    }
+
+    /// Divide a by b. Panics if b is zero.
+    #[inline]
+    pub fn div(&self, a: u16, b: u16) -> u16 {
+        assert_ne!(b, 0, "division by zero in GF(2^{})", self.m);
+        if a == 0 {
+            return 0;
+        }
+        let n = self.order();
+        self.exp[(self.log[a as usize] as usize + n - self.log[b as usize] as usize) % n]
+    }
+
+    /// The multiplicative inverse. Panics if a is zero.
+    #[inline]
+    pub fn inv(&self, a: u16) -> u16 {
+        self.div(1, a)
+    }
+
+    /// alpha^i, where alpha is the primitive element.
+    #[inline]
+    pub fn alpha_pow(&self, i: usize) -> u16 {
+        self.exp[i % self.order()]
+    }
+
+    /// The discrete logarithm of a, i.e. i such that alpha^i = a.
+    #[inline]
+    pub fn log(&self, a: u16) -> Option<usize> {
+        if a == 0 {
+            None
+        } else {
+            Some(self.log[a as usize] as usize)
+        }
+    }
+
+    /// Evaluate the polynomial in x, where poly[i] is the coefficient of x^i.
+    pub fn eval(&self, poly: &[u16], x: u16) -> u16 {
+        poly.iter().rev().fold(0, |acc, c| self.mul(acc, x) ^ c)
+    }
+
+    /// Multiply two polynomials with coefficients in the field.
+    pub fn poly_mul(&self, a: &[u16], b: &[u16]) -> Vec<u16> {
+        if a.is_empty() || b.is_empty() {
+            return Vec::new();
+        }
+        let mut res = vec![0u16; a.len() + b.len() - 1];
+        for (i, x) in a.iter().enumerate() {
+            for (j, y) in b.iter().enumerate() {
+                res[i + j] ^= self.mul(*x, *y);
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn primitive_polynomials() {
+        for m in 2..=16 {
+            assert!(GaloisField::new(m).is_ok(), "m = {}", m);
+        }
+        assert!(GaloisField::new(1).is_err());
+        assert!(GaloisField::new(17).is_err());
+        // x^4 + x^3 + x^2 + x + 1 is irreducible, but not primitive
+        assert!(GaloisField::with_polynomial(4, 0x1f).is_err());
+    }
+
+    #[test]
+    fn gf16_table() {
+        let field = GaloisField::new(4).unwrap();
+        assert_eq!(field.alpha_pow(4), 0b0011);
+        assert_eq!(field.alpha_pow(14), 0b1001);
+        assert_eq!(field.alpha_pow(15), 1);
+        assert_eq!(field.mul(0b0110, 0b1011), field.alpha_pow(5 + 7));
+    }
+
+    #[test]
+    fn inverse() {
+        for m in 2..=10 {
+            let field = GaloisField::new(m).unwrap();
+            for a in 1..=field.order() as u16 {
+                assert_eq!(field.mul(a, field.inv(a)), 1);
+                assert_eq!(field.div(field.mul(a, 7 % a + 1), a), 7 % a + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn polynomials() {
+        let field = GaloisField::new(8).unwrap();
+        // (x + 2)(x + 3) = x^2 + x + 6
+        assert_eq!(field.poly_mul(&[2, 1], &[3, 1]), [6, 1, 1]);
+        assert_eq!(field.eval(&[6, 1, 1], 2), 0);
+        assert_eq!(field.eval(&[6, 1, 1], 3), 0);
+        assert_eq!(field.eval(&[6, 1, 1], 0), 6);
+    }
+}
@@ -0,0 +1,362 @@
+//! The binary Golay codes.
+//!
+//! The perfect Golay code (23, 12) corrects up to three errors in every 23 bit codeword.
+//! The extended Golay code (24, 12) adds a parity bit, so it also detects four errors,
+//! and the codewords are a whole number of bytes.
+//!
+//!     # use codes::error::golay::Golay;
+//!     let golay = Golay::extended();
+//!     let message = b"three errors".to_vec();
+//!
+//!     let mut encoded = golay.encode(&message);
+//!     encoded[0] ^= 0b10100001;
+//!
+//!     let decoded = golay.decode(&encoded).unwrap();
+//!     assert_eq!(decoded[..message.len()], message[..]);
+
+/// g(x) = x^11 + x^10 + x^6 + x^5 + x^4 + x^2 + 1
+const GENERATOR: u32 = 0xc75;
+
+/// The remainder of the codeword divided by the generator.
+fn remainder(mut word: u32) -> u32 {
+    for i in (11..23).rev() {
+        if word >> i & 1 == 1 {
+            word ^= GENERATOR << (i - 11);
+        }
+    }
+    word
+}
+
+pub struct Golay {
+    extended: bool,
+    /// The error pattern of weight at most 3 for all the 2^11 syndromes
+    errors: Vec<u32>,
+}
+
+impl Default for Golay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Golay {
+    /// The perfect Golay code (23, 12)
+    pub fn new() -> Self {
+        // since the code is perfect, every syndrome belongs to exactly one
+        // error pattern with weight at most 3.
+        let mut errors = vec![0u32; 1 << 11];
+        for i in 0..23 {
+            for j in i..23 {
+                for k in j..23 {
+                    let e = (1 << i) | (1 << j) | (1 << k);
+                    errors[remainder(e) as usize] = e;
+                }
+            }
+        }
+        errors[0] = 0;
+
+        Golay {
+            extended: false,
+            errors,
+        }
+    }
+
+    /// The extended Golay code (24, 12)
+    pub fn extended() -> Self {
+        Golay {
+            extended: true,
+            ..Self::new()
+        }
+    }
+
+    /// The number of bits in a codeword.
+    pub fn n(&self) -> usize {
+        if self.extended {
+            24
+        } else {
+            23
+        }
+    }
+
+    /// Encode the lower 12 bits of data.
+    /// The data is in the upper 12 bits of the codeword, and the parity in the lower 11.
+    /// The extended code has the overall parity bit as bit 23.
+    pub fn encode_block(&self, data: u16) -> u32 {
+        let shifted = ((data as u32) & 0xfff) << 11;
+        let codeword = shifted | remainder(shifted);
+
+        if self.extended {
+            codeword | ((codeword.count_ones() & 1) << 23)
+        } else {
+            codeword
+        }
+    }
+
+    /// Decode a codeword, correcting up to three errors.
+    /// Returns the data and the number of corrected errors, or an error if the extended code
+    /// detects four errors.
+    pub fn decode_block(&self, codeword: u32) -> Result<(u16, usize), String> {
+        let word = codeword & 0x7fffff;
+        let error = self.errors[remainder(word) as usize];
+        let mut corrected = error.count_ones() as usize;
+
+        if self.extended {
+            // the codewords have even weight, so odd parity means an odd number of errors.
+            let odd = (codeword & 0xffffff).count_ones() & 1 == 1;
+            match (corrected, odd) {
+                (3, false) => return Err("four errors in the codeword".to_string()),
+                (0, true) | (1, false) | (2, true) => corrected += 1,
+                _ => (),
+            }
+        }
+
+        Ok((((word ^ error) >> 11) as u16, corrected))
+    }
+
+    /// Encode a byte stream.
+    /// The bits are split into blocks of 12 (the last one padded with zeros),
+    /// and the codewords are packed together.
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        data.iter().copied().golay_encode(self).collect()
+    }
+
+    /// Decode a byte stream created by `encode`. Might return trailing zeros.
+    pub fn decode(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let mut input = data.iter().copied();
+        let mut decoder = input.golay_decode(self);
+        let decoded = decoder.by_ref().collect();
+        match decoder.failed {
+            0 => Ok(decoded),
+            n => Err(format!("{} codewords could not be corrected", n)),
+        }
+    }
+}
+
+/// Collects bits, and takes them out again in chunks.
+struct BitBuffer {
+    bits: u64,
+    len: usize,
+}
+
+impl BitBuffer {
+    fn push(&mut self, value: u32, len: usize) {
+        self.bits = (self.bits << len) | value as u64;
+        self.len += len;
+    }
+
+    fn take(&mut self, len: usize) -> Option<u32> {
+        if self.len < len {
+            return None;
+        }
+        self.len -= len;
+        let value = (self.bits >> self.len) & ((1 << len) - 1);
+        self.bits &= (1 << self.len) - 1;
+        Some(value as u32)
+    }
+}
+
+pub struct GolayEncoder<'a, I: Iterator<Item = u8>> {
+    iterator: &'a mut I,
+    golay: &'a Golay,
+    input: BitBuffer,
+    output: BitBuffer,
+}
+
+impl<'a, I: Iterator<Item = u8>> Iterator for GolayEncoder<'a, I> {
+    type Item = u8;
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.output.len < 8 {
+            while self.input.len < 12 {
+                if let Some(byte) = self.iterator.next() {
+                    self.input.push(byte as u32, 8);
+                } else if self.input.len > 0 {
+                    // pad the last block with zeros
+                    self.input.push(0, 12 - self.input.len);
+                } else if self.output.len > 0 {
+                    // pad the last byte with zeros
+                    self.output.push(0, 8 - self.output.len);
+                    break;
+                } else {
+                    return None;
+                }
+            }
+
+            if let Some(data) = self.input.take(12) {
+                self.output
+                    .push(self.golay.encode_block(data as u16), self.golay.n());
+            }
+        }
+
+        self.output.take(8).map(|byte| byte as u8)
+    }
+}
+
+pub struct GolayDecoder<'a, I: Iterator<Item = u8>> {
+    iterator: &'a mut I,
+    golay: &'a Golay,
+    input: BitBuffer,
+    output: BitBuffer,
+    /// The number of codewords with uncorrectable errors.
+    pub failed: usize,
+}
+
+impl<'a, I: Iterator<Item = u8>> Iterator for GolayDecoder<'a, I> {
+    type Item = u8;
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.output.len < 8 {
+            while self.input.len < self.golay.n() {
+                // what is left is only the padding of the last byte
+                self.input.push(self.iterator.next()? as u32, 8);
+            }
+
+            let codeword = self.input.take(self.golay.n()).unwrap();
+            let data = match self.golay.decode_block(codeword) {
+                Ok((data, _)) => data,
+                Err(_) => {
+                    // not much to do, use the data bits as they are
+                    self.failed += 1;
+                    ((codeword & 0x7fffff) >> 11) as u16
+                }
+            };
+            self.output.push(data as u32, 12);
+        }
+
+        self.output.take(8).map(|byte| byte as u8)
+    }
+}
+
+pub trait GolayCoding<'a, I: Iterator<Item = u8>> {
+    fn golay_encode<'b>(&'b mut self, golay: &'b Golay) -> GolayEncoder<'b, I>;
+    fn golay_decode<'b>(&'b mut self, golay: &'b Golay) -> GolayDecoder<'b, I>;
+}
+
+impl<'a, I: Iterator<Item = u8>> GolayCoding<'a, I> for I {
+    fn golay_encode<'b>(&'b mut self, golay: &'b Golay) -> GolayEncoder<'b, I> {
+        GolayEncoder {
+            iterator: self,
+            golay,
+            input: BitBuffer { bits: 0, len: 0 },
+            output: BitBuffer { bits: 0, len: 0 },
+        }
+    }
+
+    fn golay_decode<'b>(&'b mut self, golay: &'b Golay) -> GolayDecoder<'b, I> {
+        GolayDecoder {
+            iterator: self,
+            golay,
+            input: BitBuffer { bits: 0, len: 0 },
+            output: BitBuffer { bits: 0, len: 0 },
+            failed: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weight_patterns(n: usize, max: usize) -> Vec<u32> {
+        (0u32..(1 << n))
+            .filter(|e| e.count_ones() as usize <= max)
+            .collect()
+    }
+
+    #[test]
+    fn perfect() {
+        // every syndrome is used exactly once
+        let golay = Golay::new();
+        let mut syndromes: Vec<u32> = weight_patterns(23, 3).into_iter().map(remainder).collect();
+        syndromes.sort_unstable();
+        syndromes.dedup();
+        assert_eq!(syndromes.len(), 2048);
+        assert_eq!(golay.errors.iter().filter(|e| **e != 0).count(), 2047);
+    }
+
+    #[test]
+    fn minimum_distance() {
+        let golay = Golay::extended();
+        let min = (1..4096)
+            .map(|data| golay.encode_block(data).count_ones())
+            .min()
+            .unwrap();
+        assert_eq!(min, 8);
+
+        let golay = Golay::new();
+        let min = (1..4096)
+            .map(|data| golay.encode_block(data).count_ones())
+            .min()
+            .unwrap();
+        assert_eq!(min, 7);
+    }
+
+    #[test]
+    fn correct_three_errors() {
+        for golay in &[Golay::new(), Golay::extended()] {
+            let patterns = weight_patterns(golay.n(), 3);
+            for data in (0..4096).step_by(331) {
+                let codeword = golay.encode_block(data);
+                for e in patterns.iter() {
+                    assert_eq!(
+                        golay.decode_block(codeword ^ e),
+                        Ok((data, e.count_ones() as usize))
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn detect_four_errors() {
+        let golay = Golay::extended();
+        let codeword = golay.encode_block(0xabc);
+        for e in (0u32..(1 << 24))
+            .step_by(97)
+            .filter(|e| e.count_ones() == 4)
+        {
+            assert!(golay.decode_block(codeword ^ e).is_err());
+        }
+    }
+
+    #[test]
+    fn byte_stream() {
+        let message = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit.".to_vec();
+        for golay in &[Golay::new(), Golay::extended()] {
+            let mut encoded = golay.encode(&message);
+            assert_eq!(
+                encoded.len(),
+                ((message.len() * 8).div_ceil(12) * golay.n()).div_ceil(8)
+            );
+
+            // three errors in every codeword
+            let n = golay.n();
+            for block in 0..((encoded.len() << 3) / n) {
+                for i in &[0, 9, 22] {
+                    let bit = block * n + i;
+                    encoded[bit >> 3] ^= 1 << (7 - (bit & 7));
+                }
+            }
+
+            let decoded = golay.decode(&encoded).unwrap();
+            assert_eq!(decoded[..message.len()], message[..]);
+            assert!(decoded[message.len()..].iter().all(|b| *b == 0));
+        }
+    }
+
+    #[test]
+    fn extended_is_byte_aligned() {
+        // 3 bytes is two codewords, which is 6 bytes
+        let golay = Golay::extended();
+        let encoded = golay.encode(&[0x12, 0x34, 0x56]);
+        assert_eq!(encoded.len(), 6);
+        assert_eq!(encoded[..3], golay.encode_block(0x123).to_be_bytes()[1..]);
+        assert_eq!(golay.decode(&encoded).unwrap(), [0x12, 0x34, 0x56]);
+    }
+
+    #[test]
+    fn detected_in_stream() {
+        let golay = Golay::extended();
+        let mut encoded = golay.encode(b"abc");
+        encoded[0] ^= 0xf0;
+        assert!(golay.decode(&encoded).is_err());
+    }
+}
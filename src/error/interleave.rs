@@ -0,0 +1,360 @@
+//! Bit interleavers to spread burst errors over several codewords.
+//!
+//! The hamming code can only correct a single flipped bit in every 16 bit block, so a burst of two
+//! neighbouring bits on the channel is not correctable. By interleaving the encoded stream,
+//! neighbouring bits on the channel end up in different codewords, and each codeword only sees a
+//! single error.
+//!
+//!     # use codes::error::hamming::ErrorDetection;
+//!     # use codes::error::interleave::Interleaving;
+//!     let message = b"burst errors are no fun".to_vec();
+//!
+//!     // 8 codewords of 16 bits is interleaved at the time
+//!     let mut sent: Vec<u8> = message
+//!         .clone()
+//!         .into_iter()
+//!         .encode()
+//!         .block_interleave(8, 16)
+//!         .collect();
+//!
+//!     // a burst of 8 bits
+//!     sent[3] ^= 0xff;
+//!
+//!     let received: Vec<u8> = sent
+//!         .into_iter()
+//!         .block_deinterleave(8, 16)
+//!         .decode()
+//!         .collect();
+//!
+//!     assert_eq!(received[..message.len()], message[..]);
+
+use std::collections::VecDeque;
+
+/// Split a byte into bits, most significant bit first.
+fn push_bits(bits: &mut VecDeque<u8>, byte: u8) {
+    for i in (0..8).rev() {
+        bits.push_back((byte >> i) & 1);
+    }
+}
+
+/// The order the bits of a block is read out in.
+/// The bits are written row by row into a matrix with `width` columns,
+/// and read out column by column.
+fn block_order(len: usize, width: usize) -> impl Iterator<Item = usize> {
+    let rows = len / width;
+    (0..width).flat_map(move |c| (0..rows).map(move |r| r * width + c))
+}
+
+/// Interleaves (or deinterleaves) blocks of `depth` codewords of `width` bits.
+///
+/// A burst of at most `depth` bits will hit every codeword at most once.
+/// The last block is padded with zeros to a whole block, so just like the hamming decoder the
+/// deinterleaved stream might have trailing zeros.
+pub struct BlockInterleaver<'a, I: Iterator<Item = u8>> {
+    iterator: &'a mut I,
+    width: usize,
+    block_len: usize,
+    inverse: bool,
+    output: VecDeque<u8>,
+}
+
+impl<'a, I: Iterator<Item = u8>> BlockInterleaver<'a, I> {
+    fn new(iterator: &'a mut I, depth: usize, width: usize, inverse: bool) -> Self {
+        assert!(depth > 0 && width > 0, "the interleaver can not be empty");
+        assert_eq!(
+            (depth * width) & 7,
+            0,
+            "the interleaved block has to be a whole number of bytes"
+        );
+        BlockInterleaver {
+            iterator,
+            width,
+            block_len: (depth * width) >> 3,
+            inverse,
+            output: VecDeque::new(),
+        }
+    }
+
+    /// Read the next block from the input and permute it.
+    fn next_block(&mut self) {
+        let mut bits = VecDeque::new();
+        for byte in self.iterator.by_ref().take(self.block_len) {
+            push_bits(&mut bits, byte);
+        }
+
+        if bits.is_empty() {
+            return;
+        }
+        bits.resize(self.block_len << 3, 0);
+
+        let mut permuted = vec![0u8; bits.len()];
+        for (n, i) in block_order(bits.len(), self.width).enumerate() {
+            if self.inverse {
+                permuted[i] = bits[n];
+            } else {
+                permuted[n] = bits[i];
+            }
+        }
+
+        self.output.extend(
+            permuted
+                .chunks(8)
+                .map(|byte| byte.iter().fold(0, |byte, bit| (byte << 1) | bit)),
+        );
+    }
+}
+
+impl<'a, I: Iterator<Item = u8>> Iterator for BlockInterleaver<'a, I> {
+    type Item = u8;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.output.is_empty() {
+            self.next_block();
+        }
+
+        self.output.pop_front()
+    }
+}
+
+/// A convolutional (Forney) interleaver with `branches` branches, where branch number `j` delays
+/// the bits by `j * delay` bits.
+///
+/// Two neighbouring bits on the channel are `branches * delay - 1` bits apart in the original
+/// stream, so a burst of up to `branches` bits is spread across codewords as long as this is at
+/// least the codeword length.
+///
+/// The interleaver adds `branches * (branches - 1) * delay` bits (rounded up to whole bytes) to
+/// flush the branches, and the deinterleaver removes them again.
+pub struct ConvolutionalInterleaver<'a, I: Iterator<Item = u8>> {
+    iterator: &'a mut I,
+    branches: Vec<VecDeque<u8>>,
+    branch: usize,
+    bits: VecDeque<u8>,
+    flush: usize,
+    skip: usize,
+}
+
+impl<'a, I: Iterator<Item = u8>> ConvolutionalInterleaver<'a, I> {
+    fn new(iterator: &'a mut I, branches: usize, delay: usize, inverse: bool) -> Self {
+        assert!(branches > 0, "the interleaver needs at least one branch");
+        let total_delay = branches * (branches - 1) * delay;
+
+        ConvolutionalInterleaver {
+            iterator,
+            branches: (0..branches)
+                .map(|j| {
+                    let d = if inverse { branches - 1 - j } else { j };
+                    std::iter::repeat_n(0, d * delay).collect()
+                })
+                .collect(),
+            branch: 0,
+            bits: VecDeque::new(),
+            // round up to whole bytes
+            flush: if inverse { 0 } else { (total_delay + 7) & !7 },
+            skip: if inverse { total_delay } else { 0 },
+        }
+    }
+
+    /// Push a bit into the current branch, and get the oldest bit in the branch out.
+    fn shift(&mut self, bit: u8) -> u8 {
+        let branch = self.branch;
+        self.branch = (branch + 1) % self.branches.len();
+
+        self.branches[branch].push_back(bit);
+        self.branches[branch].pop_front().unwrap()
+    }
+
+    fn next_bit(&mut self) -> Option<u8> {
+        if self.bits.is_empty() {
+            if let Some(byte) = self.iterator.next() {
+                push_bits(&mut self.bits, byte);
+            } else if self.flush > 0 {
+                self.flush -= 1;
+                return Some(0);
+            } else {
+                return None;
+            }
+        }
+
+        self.bits.pop_front()
+    }
+}
+
+impl<'a, I: Iterator<Item = u8>> Iterator for ConvolutionalInterleaver<'a, I> {
+    type Item = u8;
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut byte = 0;
+        let mut count = 0;
+
+        while count < 8 {
+            // a partial byte at the end is only the tail of the flush, and is dropped.
+            let bit = self.next_bit()?;
+            let bit = self.shift(bit);
+
+            if self.skip > 0 {
+                self.skip -= 1;
+                continue;
+            }
+
+            byte = (byte << 1) | bit;
+            count += 1;
+        }
+
+        Some(byte)
+    }
+}
+
+pub trait Interleaving<'a, I: Iterator<Item = u8>> {
+    fn block_interleave(&mut self, depth: usize, width: usize) -> BlockInterleaver<'_, I>;
+    fn block_deinterleave(&mut self, depth: usize, width: usize) -> BlockInterleaver<'_, I>;
+    fn convolutional_interleave(
+        &mut self,
+        branches: usize,
+        delay: usize,
+    ) -> ConvolutionalInterleaver<'_, I>;
+    fn convolutional_deinterleave(
+        &mut self,
+        branches: usize,
+        delay: usize,
+    ) -> ConvolutionalInterleaver<'_, I>;
+}
+
+impl<'a, I: Iterator<Item = u8>> Interleaving<'a, I> for I {
+    fn block_interleave(&mut self, depth: usize, width: usize) -> BlockInterleaver<'_, I> {
+        BlockInterleaver::new(self, depth, width, false)
+    }
+
+    fn block_deinterleave(&mut self, depth: usize, width: usize) -> BlockInterleaver<'_, I> {
+        BlockInterleaver::new(self, depth, width, true)
+    }
+
+    fn convolutional_interleave(
+        &mut self,
+        branches: usize,
+        delay: usize,
+    ) -> ConvolutionalInterleaver<'_, I> {
+        ConvolutionalInterleaver::new(self, branches, delay, false)
+    }
+
+    fn convolutional_deinterleave(
+        &mut self,
+        branches: usize,
+        delay: usize,
+    ) -> ConvolutionalInterleaver<'_, I> {
+        ConvolutionalInterleaver::new(self, branches, delay, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::hamming::ErrorDetection;
+
+    const MESSAGE: &[u8] = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit. In pretium magna commodo, posuere lacus nec, tempor mi.";
+
+    /// flip `len` consecutive bits, starting at bit `start`
+    fn burst(data: &mut [u8], start: usize, len: usize) {
+        for i in start..(start + len) {
+            data[i >> 3] ^= 1 << (7 - (i & 7));
+        }
+    }
+
+    #[test]
+    fn block_order_is_permutation() {
+        for rows in 1..10 {
+            let mut order: Vec<usize> = block_order(rows * 16, 16).collect();
+            order.sort_unstable();
+            assert_eq!(order, (0..rows * 16).collect::<Vec<usize>>());
+        }
+    }
+
+    #[test]
+    fn block_roundtrip() {
+        for len in 0..MESSAGE.len() {
+            let interleaved: Vec<u8> = MESSAGE[..len]
+                .iter()
+                .copied()
+                .block_interleave(8, 16)
+                .collect();
+            assert_eq!(interleaved.len(), (len + 15) & !15);
+            let deinterleaved: Vec<u8> = interleaved
+                .into_iter()
+                .block_deinterleave(8, 16)
+                .collect();
+            assert_eq!(deinterleaved[..len], MESSAGE[..len]);
+            assert!(deinterleaved[len..].iter().all(|b| *b == 0));
+        }
+    }
+
+    #[test]
+    fn block_interleave_columns() {
+        // two codewords of 8 bits
+        let interleaved: Vec<u8> = vec![0xff, 0x00].into_iter().block_interleave(2, 8).collect();
+        assert_eq!(interleaved, [0b10101010, 0b10101010]);
+    }
+
+    #[test]
+    fn block_burst() {
+        let encoded: Vec<u8> = MESSAGE.iter().copied().encode().collect();
+
+        for start in (0..(encoded.len() << 3) - 12).step_by(7) {
+            let mut sent: Vec<u8> = encoded.iter().copied().block_interleave(12, 16).collect();
+            burst(&mut sent, start, 12);
+            let received: Vec<u8> = sent
+                .into_iter()
+                .block_deinterleave(12, 16)
+                .decode()
+                .collect();
+            assert_eq!(received[..MESSAGE.len()], MESSAGE[..], "burst at {}", start);
+        }
+    }
+
+    #[test]
+    fn burst_without_interleaving() {
+        let mut sent: Vec<u8> = MESSAGE.iter().copied().encode().collect();
+        burst(&mut sent, 4, 4);
+        let received: Vec<u8> = sent.into_iter().decode().collect();
+        assert_ne!(received[..MESSAGE.len()], MESSAGE[..]);
+    }
+
+    #[test]
+    fn convolutional_roundtrip() {
+        for &(branches, delay) in &[(1, 3), (2, 1), (3, 5), (16, 2)] {
+            for len in 0..MESSAGE.len() {
+                let interleaved: Vec<u8> = MESSAGE[..len]
+                    .iter()
+                    .copied()
+                    .convolutional_interleave(branches, delay)
+                    .collect();
+                assert_eq!(
+                    interleaved.len(),
+                    len + ((branches * (branches - 1) * delay + 7) >> 3)
+                );
+                let deinterleaved: Vec<u8> = interleaved
+                    .into_iter()
+                    .convolutional_deinterleave(branches, delay)
+                    .collect();
+                assert_eq!(deinterleaved, MESSAGE[..len]);
+            }
+        }
+    }
+
+    #[test]
+    fn convolutional_burst() {
+        let encoded: Vec<u8> = MESSAGE.iter().copied().encode().collect();
+        let interleaved: Vec<u8> = encoded
+            .into_iter()
+            .convolutional_interleave(16, 2)
+            .collect();
+
+        for start in (0..(interleaved.len() << 3) - 16).step_by(5) {
+            let mut sent = interleaved.clone();
+            burst(&mut sent, start, 16);
+            let received: Vec<u8> = sent
+                .into_iter()
+                .convolutional_deinterleave(16, 2)
+                .decode()
+                .collect();
+            assert_eq!(received[..MESSAGE.len()], MESSAGE[..], "burst at {}", start);
+        }
+    }
+}
@@ -0,0 +1,583 @@
+//! Low-density parity-check codes with belief propagation decoding.
+//!
+//! The code is given by a sparse parity-check matrix H, usually loaded from the alist format
+//! (see [David MacKay's codes](http://www.inference.org.uk/mackay/codes/alist.html)).
+//! The encoder is systematic, the generator is found by bringing H to reduced row echelon form.
+//!
+//! The decoder works on soft input, the log-likelihood ratio log(P(0) / P(1)) of every received
+//! bit, so a positive value means the bit is probably a zero.
+//!
+//!     # use codes::error::ldpc::{LDPC, BeliefPropagation};
+//!     // the (7, 4) hamming code
+//!     let alist = "7 3\n3 4\n1 1 1 2 2 2 3\n4 4 4\n1 0 0\n2 0 0\n3 0 0\n1 2 0\n1 3 0\n2 3 0\n1 2 3\n1 4 5 7\n2 4 6 7\n3 5 6 7\n";
+//!     let code = LDPC::from_alist(alist).unwrap();
+//!
+//!     let codeword = code.encode(&[1, 0, 1, 1]);
+//!
+//!     // BPSK, a zero is sent as +1 and a one as -1. The fourth bit is received wrong.
+//!     let mut llr: Vec<f64> = codeword.iter().map(|b| if *b == 0 { 2.0 } else { -2.0 }).collect();
+//!     llr[3] = -llr[3] / 4.0;
+//!
+//!     let decoded = code.decode(&llr, BeliefPropagation::SumProduct, 20).unwrap();
+//!     assert_eq!(decoded, codeword);
+//!     assert_eq!(code.extract(&decoded), [1, 0, 1, 1]);
+
+use rand::Rng;
+
+/// The update rule used in the check nodes.
+#[derive(Copy, Clone)]
+pub enum BeliefPropagation {
+    /// The exact rule, using tanh and atanh.
+    SumProduct,
+    /// Approximates the check node update with the minimum magnitude of the incoming messages.
+    MinSum,
+}
+
+pub struct LDPC {
+    n: usize,
+    /// The variables connected to each check, the rows of H.
+    checks: Vec<Vec<usize>>,
+    /// The checks connected to each variable, the columns of H.
+    variables: Vec<Vec<usize>>,
+    /// The positions of the message bits in the codeword.
+    info: Vec<usize>,
+    /// The position of each parity bit, and the message bits it is the sum of.
+    parity: Vec<(usize, Vec<usize>)>,
+}
+
+impl LDPC {
+    /// Create the code from the rows of the parity check matrix,
+    /// each row is the list of the columns with a one.
+    pub fn from_rows(n: usize, checks: Vec<Vec<usize>>) -> Result<Self, String> {
+        let mut variables = vec![Vec::new(); n];
+        for (i, row) in checks.iter().enumerate() {
+            for &v in row {
+                if v >= n {
+                    return Err(format!("column {} in row {} is out of range", v, i));
+                }
+                if variables[v].contains(&i) {
+                    return Err(format!("column {} is repeated in row {}", v, i));
+                }
+                variables[v].push(i);
+            }
+        }
+
+        let (info, parity) = systematic(n, &checks);
+
+        Ok(LDPC {
+            n,
+            checks,
+            variables,
+            info,
+            parity,
+        })
+    }
+
+    /// Parse a parity check matrix in the alist format.
+    /// The indices are 1-based, and zeros padding the lists are ignored.
+    pub fn from_alist(alist: &str) -> Result<Self, String> {
+        let mut lines = alist
+            .lines()
+            .map(|line| {
+                line.split_whitespace()
+                    .map(|x| {
+                        x.parse::<usize>()
+                            .map_err(|e| format!("{} is not a number: {}", x, e))
+                    })
+                    .collect::<Result<Vec<usize>, String>>()
+            })
+            .filter(|line| line.as_ref().map(|l| !l.is_empty()).unwrap_or(true));
+
+        let mut next_line = |what: &str| {
+            lines
+                .next()
+                .unwrap_or_else(|| Err(format!("the alist ended before {}", what)))
+        };
+
+        let size = next_line("the size")?;
+        if size.len() != 2 {
+            return Err("the first line should be the number of columns and rows".to_string());
+        }
+        let (n, m) = (size[0], size[1]);
+
+        // the max weights are not needed
+        next_line("the max weights")?;
+        let col_weights = next_line("the column weights")?;
+        let row_weights = next_line("the row weights")?;
+        if col_weights.len() != n || row_weights.len() != m {
+            return Err("wrong number of column or row weights".to_string());
+        }
+
+        let mut columns = Vec::with_capacity(n);
+        for (c, weight) in col_weights.iter().enumerate() {
+            let column: Vec<usize> = next_line("all the columns")?
+                .into_iter()
+                .filter(|x| *x != 0)
+                .map(|x| x - 1)
+                .collect();
+            if column.len() != *weight {
+                return Err(format!("column {} does not have weight {}", c + 1, weight));
+            }
+            columns.push(column);
+        }
+
+        let mut rows = Vec::with_capacity(m);
+        for (r, weight) in row_weights.iter().enumerate() {
+            let row: Vec<usize> = next_line("all the rows")?
+                .into_iter()
+                .filter(|x| *x != 0)
+                .map(|x| x - 1)
+                .collect();
+            if row.len() != *weight {
+                return Err(format!("row {} does not have weight {}", r + 1, weight));
+            }
+            rows.push(row);
+        }
+
+        let code = Self::from_rows(n, rows)?;
+
+        let mut sorted: Vec<Vec<usize>> = code.variables.clone();
+        for (a, b) in sorted.iter_mut().zip(columns.iter_mut()) {
+            a.sort_unstable();
+            b.sort_unstable();
+        }
+        if sorted != columns {
+            return Err("the columns and the rows does not describe the same matrix".to_string());
+        }
+
+        Ok(code)
+    }
+
+    /// Write the parity check matrix in the alist format.
+    pub fn to_alist(&self) -> String {
+        fn line<T: ToString>(list: impl Iterator<Item = T>) -> String {
+            list.map(|x| x.to_string())
+                .collect::<Vec<String>>()
+                .join(" ")
+                + "\n"
+        }
+        fn padded(list: &[usize], len: usize) -> String {
+            line(
+                list.iter()
+                    .map(|x| x + 1)
+                    .chain(std::iter::repeat(0))
+                    .take(len),
+            )
+        }
+
+        let max_col = self.variables.iter().map(|c| c.len()).max().unwrap_or(0);
+        let max_row = self.checks.iter().map(|r| r.len()).max().unwrap_or(0);
+
+        let mut alist = line([self.n, self.checks.len()].iter());
+        alist += &line([max_col, max_row].iter());
+        alist += &line(self.variables.iter().map(|c| c.len()));
+        alist += &line(self.checks.iter().map(|r| r.len()));
+        for c in self.variables.iter() {
+            alist += &padded(c, max_col);
+        }
+        for r in self.checks.iter() {
+            alist += &padded(r, max_row);
+        }
+        alist
+    }
+
+    /// The length of a codeword.
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// The number of message bits in a codeword.
+    pub fn k(&self) -> usize {
+        self.info.len()
+    }
+
+    /// The code rate k / n.
+    pub fn rate(&self) -> f64 {
+        self.k() as f64 / self.n as f64
+    }
+
+    /// Encode k message bits (one bit in each byte) into a codeword of n bits.
+    pub fn encode(&self, message: &[u8]) -> Vec<u8> {
+        assert_eq!(message.len(), self.k(), "the message should be k bits");
+        let mut codeword = vec![0u8; self.n];
+        for (i, bit) in self.info.iter().zip(message.iter()) {
+            codeword[*i] = *bit & 1;
+        }
+        for (p, sum) in self.parity.iter() {
+            codeword[*p] = sum.iter().fold(0, |s, i| s ^ message[*i]) & 1;
+        }
+        codeword
+    }
+
+    /// Get the message bits out of a codeword.
+    pub fn extract(&self, codeword: &[u8]) -> Vec<u8> {
+        self.info.iter().map(|i| codeword[*i]).collect()
+    }
+
+    /// Check if all the parity checks are satisfied.
+    pub fn is_codeword(&self, word: &[u8]) -> bool {
+        self.checks
+            .iter()
+            .all(|row| row.iter().fold(0, |s, v| s ^ word[*v]) == 0)
+    }
+
+    /// Decode the log-likelihood ratios of the received bits with belief propagation.
+    ///
+    /// Returns the codeword if all the parity checks are satisfied within `max_iterations`,
+    /// otherwise the best guess is returned as the error.
+    pub fn decode(
+        &self,
+        llr: &[f64],
+        algorithm: BeliefPropagation,
+        max_iterations: usize,
+    ) -> Result<Vec<u8>, Vec<u8>> {
+        assert_eq!(llr.len(), self.n, "there should be a llr for every bit");
+
+        // the messages are stored per edge, the edges of check i is at edges[i]..edges[i + 1]
+        let mut edges = vec![0];
+        for row in self.checks.iter() {
+            edges.push(edges.last().unwrap() + row.len());
+        }
+        let total_edges = *edges.last().unwrap();
+
+        let mut to_check: Vec<f64> = self
+            .checks
+            .iter()
+            .flat_map(|row| row.iter().map(|v| llr[*v]))
+            .collect();
+        let mut to_variable = vec![0f64; total_edges];
+        let mut hard: Vec<u8> = llr.iter().map(|l| (*l < 0.0) as u8).collect();
+
+        if self.is_codeword(&hard) {
+            return Ok(hard);
+        }
+
+        for _ in 0..max_iterations {
+            // check node update
+            for (i, row) in self.checks.iter().enumerate() {
+                let incoming = &to_check[edges[i]..edges[i + 1]];
+                let outgoing = &mut to_variable[edges[i]..edges[i + 1]];
+                check_update(incoming, outgoing, algorithm);
+                debug_assert_eq!(row.len(), incoming.len());
+            }
+
+            // variable node update
+            let mut total = llr.to_vec();
+            for (i, row) in self.checks.iter().enumerate() {
+                for (e, v) in (edges[i]..edges[i + 1]).zip(row.iter()) {
+                    total[*v] += to_variable[e];
+                }
+            }
+            for (i, row) in self.checks.iter().enumerate() {
+                for (e, v) in (edges[i]..edges[i + 1]).zip(row.iter()) {
+                    to_check[e] = total[*v] - to_variable[e];
+                }
+            }
+
+            for (h, t) in hard.iter_mut().zip(total.iter()) {
+                *h = (*t < 0.0) as u8;
+            }
+
+            if self.is_codeword(&hard) {
+                return Ok(hard);
+            }
+        }
+
+        Err(hard)
+    }
+}
+
+/// Compute the messages from a check node to its variables,
+/// every message is computed from all the incoming messages except the one on the same edge.
+fn check_update(incoming: &[f64], outgoing: &mut [f64], algorithm: BeliefPropagation) {
+    // avoid atanh(1) = inf
+    const LIMIT: f64 = 1.0 - 1e-12;
+
+    for (j, out) in outgoing.iter_mut().enumerate() {
+        let others = incoming
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != j)
+            .map(|(_, m)| *m);
+
+        *out = match algorithm {
+            BeliefPropagation::SumProduct => {
+                let product = others.fold(1.0, |p, m| p * (m / 2.0).tanh());
+                2.0 * product.clamp(-LIMIT, LIMIT).atanh()
+            }
+            BeliefPropagation::MinSum => {
+                let (sign, min) = others.fold((1.0, f64::INFINITY), |(s, min), m| {
+                    (if m < 0.0 { -s } else { s }, min.min(m.abs()))
+                });
+                sign * min
+            }
+        };
+    }
+}
+
+/// Bring H to reduced row echelon form with Gaussian elimination over GF(2).
+/// The pivot columns are the parity bits, and the rest are the message bits.
+fn systematic(n: usize, checks: &[Vec<usize>]) -> (Vec<usize>, Vec<(usize, Vec<usize>)>) {
+    let words = (n + 63) >> 6;
+    let get = |row: &[u64], c: usize| (row[c >> 6] >> (c & 63)) & 1 == 1;
+
+    let mut h: Vec<Vec<u64>> = checks
+        .iter()
+        .map(|row| {
+            let mut bits = vec![0u64; words];
+            for c in row {
+                bits[c >> 6] ^= 1 << (c & 63);
+            }
+            bits
+        })
+        .collect();
+
+    let mut pivots = Vec::new();
+    for c in 0..n {
+        let r = pivots.len();
+        if r == h.len() {
+            break;
+        }
+        let pivot = match (r..h.len()).find(|i| get(&h[*i], c)) {
+            Some(pivot) => pivot,
+            None => continue,
+        };
+        h.swap(r, pivot);
+
+        let pivot_row = h[r].clone();
+        for (i, row) in h.iter_mut().enumerate() {
+            if i != r && get(row, c) {
+                for (a, b) in row.iter_mut().zip(pivot_row.iter()) {
+                    *a ^= b;
+                }
+            }
+        }
+        pivots.push(c);
+    }
+
+    let info: Vec<usize> = (0..n).filter(|c| !pivots.contains(c)).collect();
+    let parity = pivots
+        .iter()
+        .zip(h.iter())
+        .map(|(p, row)| {
+            let sum = info
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| get(row, **c))
+                .map(|(i, _)| i)
+                .collect();
+            (*p, sum)
+        })
+        .collect();
+
+    (info, parity)
+}
+
+/// An additive white gaussian noise channel with BPSK modulation,
+/// a zero is sent as +1 and a one as -1.
+pub struct AWGN {
+    sigma: f64,
+}
+
+impl AWGN {
+    /// A channel with the given noise standard deviation.
+    pub fn new(sigma: f64) -> Self {
+        AWGN { sigma }
+    }
+
+    /// A channel with the given signal to noise ratio per information bit, Eb/N0 in dB,
+    /// for a code with the given rate.
+    pub fn from_ebn0(ebn0_db: f64, rate: f64) -> Self {
+        let ebn0 = 10f64.powf(ebn0_db / 10.0);
+        AWGN {
+            sigma: (1.0 / (2.0 * rate * ebn0)).sqrt(),
+        }
+    }
+
+    /// Send the bits over the channel, and return the log-likelihood ratios of the received
+    /// symbols.
+    pub fn transmit<R: Rng>(&self, bits: &[u8], rng: &mut R) -> Vec<f64> {
+        let variance = self.sigma * self.sigma;
+        bits.iter()
+            .map(|b| {
+                let y = if *b == 0 { 1.0 } else { -1.0 } + self.sigma * gaussian(rng);
+                2.0 * y / variance
+            })
+            .collect()
+    }
+}
+
+/// A standard normal sample, with the Box-Muller transform.
+fn gaussian<R: Rng>(rng: &mut R) -> f64 {
+    let u1: f64 = 1.0 - rng.gen::<f64>();
+    let u2: f64 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const HAMMING: &str = "7 3
+3 4
+1 1 1 2 2 2 3
+4 4 4
+1 0 0
+2 0 0
+3 0 0
+1 2 0
+1 3 0
+2 3 0
+1 2 3
+1 4 5 7
+2 4 6 7
+3 5 6 7
+";
+
+    /// A regular Gallager code, with columns of weight wc and rows of weight wr.
+    fn gallager(n: usize, wc: usize, wr: usize, seed: u64) -> LDPC {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let band = n / wr;
+        let mut rows: Vec<Vec<usize>> = (0..band)
+            .map(|i| ((i * wr)..((i + 1) * wr)).collect())
+            .collect();
+
+        for _ in 1..wc {
+            let mut perm: Vec<usize> = (0..n).collect();
+            for i in (1..n).rev() {
+                perm.swap(i, rng.gen_range(0, i + 1));
+            }
+            for i in 0..band {
+                rows.push(rows[i].iter().map(|c| perm[*c]).collect());
+            }
+        }
+
+        LDPC::from_rows(n, rows).unwrap()
+    }
+
+    #[test]
+    fn parse_alist() {
+        let code = LDPC::from_alist(HAMMING).unwrap();
+        assert_eq!(code.n(), 7);
+        assert_eq!(code.k(), 4);
+        assert_eq!(code.to_alist(), HAMMING);
+
+        assert!(LDPC::from_alist("7 3\n3 4\n").is_err());
+        assert!(LDPC::from_alist(&HAMMING.replace("1 4 5 7", "1 4 5 6")).is_err());
+        assert!(LDPC::from_alist(&HAMMING.replace("1 4 5 7", "1 4 5 x")).is_err());
+    }
+
+    #[test]
+    fn alist_roundtrip() {
+        let code = gallager(96, 3, 6, 1);
+        let parsed = LDPC::from_alist(&code.to_alist()).unwrap();
+        assert_eq!(parsed.to_alist(), code.to_alist());
+    }
+
+    #[test]
+    fn encode_all_hamming() {
+        let code = LDPC::from_alist(HAMMING).unwrap();
+        let mut codewords = Vec::new();
+        for m in 0..16u8 {
+            let message: Vec<u8> = (0..4).map(|i| (m >> i) & 1).collect();
+            let codeword = code.encode(&message);
+            assert!(code.is_codeword(&codeword));
+            assert_eq!(code.extract(&codeword), message);
+            codewords.push(codeword);
+        }
+        codewords.sort();
+        codewords.dedup();
+        assert_eq!(codewords.len(), 16);
+    }
+
+    #[test]
+    fn encode_gallager() {
+        let code = gallager(204, 3, 6, 2);
+        let mut rng = StdRng::seed_from_u64(3);
+        // Gallager codes have linearly dependent rows, so k is at least n/2
+        assert!(code.k() >= 102);
+        for _ in 0..10 {
+            let message: Vec<u8> = (0..code.k()).map(|_| rng.gen_range(0, 2)).collect();
+            let codeword = code.encode(&message);
+            assert!(code.is_codeword(&codeword));
+            assert_eq!(code.extract(&codeword), message);
+        }
+    }
+
+    #[test]
+    fn correct_hard_errors() {
+        let code = gallager(504, 3, 6, 4);
+        let mut rng = StdRng::seed_from_u64(5);
+        let message: Vec<u8> = (0..code.k()).map(|_| rng.gen_range(0, 2)).collect();
+        let codeword = code.encode(&message);
+
+        for &algorithm in &[BeliefPropagation::SumProduct, BeliefPropagation::MinSum] {
+            let mut llr: Vec<f64> = codeword
+                .iter()
+                .map(|b| if *b == 0 { 3.0 } else { -3.0 })
+                .collect();
+            for i in (0..504).step_by(50) {
+                llr[i] = -llr[i];
+            }
+            assert_eq!(code.decode(&llr, algorithm, 50), Ok(codeword.clone()));
+        }
+    }
+
+    /// The bit error rate of the message bits when sending `frames` codewords over the channel.
+    fn bit_error_rate(
+        code: &LDPC,
+        ebn0_db: f64,
+        algorithm: BeliefPropagation,
+        frames: usize,
+        seed: u64,
+    ) -> f64 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let channel = AWGN::from_ebn0(ebn0_db, code.rate());
+        let mut errors = 0;
+
+        for _ in 0..frames {
+            let message: Vec<u8> = (0..code.k()).map(|_| rng.gen_range(0, 2)).collect();
+            let llr = channel.transmit(&code.encode(&message), &mut rng);
+            let decoded = match code.decode(&llr, algorithm, 50) {
+                Ok(codeword) => codeword,
+                Err(guess) => guess,
+            };
+            errors += code
+                .extract(&decoded)
+                .iter()
+                .zip(message.iter())
+                .filter(|(a, b)| a != b)
+                .count();
+        }
+
+        errors as f64 / (frames * code.k()) as f64
+    }
+
+    #[test]
+    fn ber_vs_snr() {
+        let code = gallager(504, 3, 6, 6);
+
+        for &algorithm in &[BeliefPropagation::SumProduct, BeliefPropagation::MinSum] {
+            let curve: Vec<f64> = [1.0, 2.0, 3.0]
+                .iter()
+                .map(|snr| bit_error_rate(&code, *snr, algorithm, 40, 7))
+                .collect();
+            // the bit error rate falls with the SNR
+            assert!(curve[0] > curve[1] && curve[1] >= curve[2], "{:?}", curve);
+            assert!(curve[2] < 1e-3, "{:?}", curve);
+        }
+
+        // uncoded BPSK at 3 dB has a bit error rate of about 2.3%
+        let mut rng = StdRng::seed_from_u64(8);
+        let channel = AWGN::from_ebn0(3.0, 1.0);
+        let bits = vec![0u8; 100000];
+        let errors = channel
+            .transmit(&bits, &mut rng)
+            .iter()
+            .filter(|l| **l < 0.0)
+            .count();
+        assert!((1800..2800).contains(&errors), "{}", errors);
+    }
+}
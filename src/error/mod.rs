@@ -1 +1,7 @@
+pub mod bch;
+pub mod fountain;
+pub mod galois;
+pub mod golay;
 pub mod hamming;
+pub mod interleave;
+pub mod ldpc;
@@ -15,6 +15,11 @@ pub mod crypt;
 
 /// Simple codes for error detection and correction.
 /// 11-5 hamming is implemented, corrects one bit in 16 bit block and detects two bit errors.
+/// Block and convolutional interleavers spread burst errors across several hamming blocks.
+/// Binary BCH codes correct t errors per codeword, using arithmetic over GF(2^m).
+/// The Golay codes correct three errors in every 23 (or 24) bit block.
+/// LDPC codes are decoded from soft input with belief propagation.
+/// LT fountain codes recover data from any set of slightly more than k received symbols.
 pub mod error;
 
 /// Extended euclidian to find inverse etc.